@@ -0,0 +1,368 @@
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use bech32::{FromBase32, ToBase32, Variant};
+use chacha20poly1305::{aead::Aead, ChaCha20Poly1305, KeyInit, Nonce};
+use core::{fmt, str::FromStr};
+use hkdf::Hkdf;
+use kem::{Decapsulate, Encapsulate};
+use ml_kem::{Ciphertext, Encoded, EncodedSizeUser, KemCore, MlKem768, SharedKey, B32};
+use rand_core::CryptoRngCore;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use crate::{Error, FileKey, Stanza, STANZA_TAG};
+
+type DecapsulationKey = <MlKem768 as KemCore>::DecapsulationKey;
+type EncapsulationKey = <MlKem768 as KemCore>::EncapsulationKey;
+
+// The human-readable parts of the Bech32 encodings of recipients and identities
+const RECIPIENT_HRP: &str = "age1mlkem";
+const IDENTITY_HRP: &str = "age-plugin-mlkem-";
+
+// The HKDF info string, which separates the wrapping key from other uses of the shared secrets
+const KDF_LABEL: &[u8] = b"age-plugin-mlkem/v1/mlkem768x25519-plugin";
+
+const EK_LEN: usize = 1184;
+const CT_LEN: usize = 1088;
+const X25519_LEN: usize = 32;
+const SEED_LEN: usize = 64;
+const WRAPPED_KEY_LEN: usize = 16 + 16;
+
+/// A recipient, to whom file keys are wrapped: An ML-KEM-768 encapsulation key and an X25519
+/// public key.  Its string form is Bech32 with the `age1mlkem` prefix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recipient {
+    ek: EncapsulationKey,
+    x25519: PublicKey,
+}
+
+impl Recipient {
+    /// Wrap `file_key` to the recipient, producing an `mlkem768x25519-plugin` stanza.
+    ///
+    /// The stanza is `-> mlkem768x25519-plugin <X25519 share> <ML-KEM ciphertext>`, with the
+    /// ChaCha20-Poly1305 encryption of the file key as its body.  The wrapping key is derived
+    /// with HKDF-SHA256 from both shared secrets, salted with the ephemeral and recipient X25519
+    /// public keys.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidKey`] if the X25519 public key has low order.
+    pub fn wrap_file_key(
+        &self,
+        file_key: &FileKey,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<Stanza, Error> {
+        let ephemeral = EphemeralSecret::random_from_rng(&mut *rng);
+        let share = PublicKey::from(&ephemeral);
+        let x25519_secret = ephemeral.diffie_hellman(&self.x25519);
+        if !x25519_secret.was_contributory() {
+            return Err(Error::InvalidKey);
+        }
+
+        let (ct, mlkem_secret) = encapsulate(&self.ek, rng);
+
+        let cipher = wrapping_cipher(
+            &mlkem_secret,
+            x25519_secret.as_bytes(),
+            &share,
+            &self.x25519,
+        );
+        let Ok(body) = cipher.encrypt(&Nonce::default(), &file_key[..]) else {
+            unreachable!("the file key is short enough to encrypt")
+        };
+
+        let share = STANDARD_NO_PAD.encode(share.as_bytes());
+        let ct = STANDARD_NO_PAD.encode(ct);
+        Ok(Stanza::new(STANZA_TAG, &[&share, &ct], &body))
+    }
+}
+
+impl fmt::Display for Recipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut data = Vec::with_capacity(EK_LEN + X25519_LEN);
+        data.extend_from_slice(&self.ek.as_bytes());
+        data.extend_from_slice(self.x25519.as_bytes());
+        let s = bech32::encode(RECIPIENT_HRP, data.to_base32(), Variant::Bech32)
+            .map_err(|_| fmt::Error)?;
+        f.write_str(&s)
+    }
+}
+
+impl FromStr for Recipient {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let data = decode_bech32(s, RECIPIENT_HRP)?;
+        if data.len() != EK_LEN + X25519_LEN {
+            return Err(Error::Encoding);
+        }
+        let (ek, x25519) = data.split_at(EK_LEN);
+
+        let ek = Encoded::<EncapsulationKey>::try_from(ek).map_err(|_| Error::Encoding)?;
+        let x25519: [u8; X25519_LEN] = x25519.try_into().map_err(|_| Error::Encoding)?;
+        Ok(Self {
+            ek: EncapsulationKey::from_bytes_checked(&ek).ok_or(Error::InvalidKey)?,
+            x25519: PublicKey::from(x25519),
+        })
+    }
+}
+
+/// An identity, with which file keys are unwrapped: The seed of an ML-KEM-768 key pair and an
+/// X25519 secret key.  Its string form is upper-case Bech32 with the `AGE-PLUGIN-MLKEM-` prefix.
+#[derive(Clone)]
+pub struct Identity {
+    seed: [u8; SEED_LEN],
+    dk: DecapsulationKey,
+    x25519: StaticSecret,
+}
+
+impl Identity {
+    /// Generate a new identity
+    pub fn generate(rng: &mut impl CryptoRngCore) -> Self {
+        let mut seed = [0; SEED_LEN];
+        rng.fill_bytes(&mut seed);
+        let x25519 = StaticSecret::random_from_rng(rng);
+        Self::from_parts(seed, x25519)
+    }
+
+    fn from_parts(seed: [u8; SEED_LEN], x25519: StaticSecret) -> Self {
+        let d = B32::from_fn(|i| seed[i]);
+        let z = B32::from_fn(|i| seed[32 + i]);
+        let (dk, _) = MlKem768::generate_deterministic(&d, &z);
+        Self { seed, dk, x25519 }
+    }
+
+    /// The recipient to whom file keys for this identity are wrapped
+    #[must_use]
+    pub fn to_public(&self) -> Recipient {
+        Recipient {
+            ek: self.dk.encapsulation_key().clone(),
+            x25519: PublicKey::from(&self.x25519),
+        }
+    }
+
+    /// Unwrap the file key from `stanza`.  Returns `None` if the stanza is not an
+    /// `mlkem768x25519-plugin` stanza.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Stanza`] if the stanza is malformed, and [`Error::Unwrap`] if the file key
+    /// does not unwrap, e.g., because it was wrapped to a different recipient.
+    #[must_use]
+    pub fn unwrap_stanza(&self, stanza: &Stanza) -> Option<Result<FileKey, Error>> {
+        (stanza.tag == STANZA_TAG).then(|| self.unwrap(stanza))
+    }
+
+    fn unwrap(&self, stanza: &Stanza) -> Result<FileKey, Error> {
+        let [share, ct] = &stanza.args[..] else {
+            return Err(Error::Stanza);
+        };
+        let share: [u8; X25519_LEN] = decode_base64(share)?;
+        let ct: [u8; CT_LEN] = decode_base64(ct)?;
+        if stanza.body.len() != WRAPPED_KEY_LEN {
+            return Err(Error::Stanza);
+        }
+
+        let share = PublicKey::from(share);
+        let x25519_secret = self.x25519.diffie_hellman(&share);
+        if !x25519_secret.was_contributory() {
+            return Err(Error::Stanza);
+        }
+
+        let mlkem_secret = decapsulate(&self.dk, &Ciphertext::<MlKem768>::from(ct));
+
+        let cipher = wrapping_cipher(
+            &mlkem_secret,
+            x25519_secret.as_bytes(),
+            &share,
+            &PublicKey::from(&self.x25519),
+        );
+        let file_key = cipher
+            .decrypt(&Nonce::default(), &stanza.body[..])
+            .map_err(|_| Error::Unwrap)?;
+        file_key.try_into().map_err(|_| Error::Unwrap)
+    }
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut data = Vec::with_capacity(SEED_LEN + X25519_LEN);
+        data.extend_from_slice(&self.seed);
+        data.extend_from_slice(self.x25519.as_bytes());
+        let s = bech32::encode(IDENTITY_HRP, data.to_base32(), Variant::Bech32)
+            .map_err(|_| fmt::Error)?;
+        f.write_str(&s.to_uppercase())
+    }
+}
+
+impl FromStr for Identity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let data = decode_bech32(s, IDENTITY_HRP)?;
+        if data.len() != SEED_LEN + X25519_LEN {
+            return Err(Error::Encoding);
+        }
+        let (seed, x25519) = data.split_at(SEED_LEN);
+
+        let seed = seed.try_into().map_err(|_| Error::Encoding)?;
+        let x25519: [u8; X25519_LEN] = x25519.try_into().map_err(|_| Error::Encoding)?;
+        Ok(Self::from_parts(seed, StaticSecret::from(x25519)))
+    }
+}
+
+impl fmt::Debug for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Identity").finish_non_exhaustive()
+    }
+}
+
+// ML-KEM encapsulation and decapsulation, whose `Result`s are always `Ok`
+fn encapsulate(
+    ek: &EncapsulationKey,
+    rng: &mut impl CryptoRngCore,
+) -> (Ciphertext<MlKem768>, SharedKey<MlKem768>) {
+    let Ok(encapsulated) = ek.encapsulate(rng) else {
+        unreachable!("ML-KEM encapsulation is infallible")
+    };
+    encapsulated
+}
+
+fn decapsulate(dk: &DecapsulationKey, ct: &Ciphertext<MlKem768>) -> SharedKey<MlKem768> {
+    let Ok(mlkem_secret) = dk.decapsulate(ct) else {
+        unreachable!("ML-KEM decapsulation is infallible")
+    };
+    mlkem_secret
+}
+
+// Derive the cipher that wraps the file key
+fn wrapping_cipher(
+    mlkem_secret: &[u8],
+    x25519_secret: &[u8],
+    share: &PublicKey,
+    recipient: &PublicKey,
+) -> ChaCha20Poly1305 {
+    let mut ikm = [0; 64];
+    ikm[..32].copy_from_slice(mlkem_secret);
+    ikm[32..].copy_from_slice(x25519_secret);
+    let mut salt = [0; 64];
+    salt[..32].copy_from_slice(share.as_bytes());
+    salt[32..].copy_from_slice(recipient.as_bytes());
+
+    let mut key = [0; 32];
+    let Ok(()) = Hkdf::<Sha256>::new(Some(&salt), &ikm).expand(KDF_LABEL, &mut key) else {
+        unreachable!("32 bytes is a valid HKDF-SHA256 output length")
+    };
+    ChaCha20Poly1305::new(&key.into())
+}
+
+fn decode_bech32(s: &str, hrp: &str) -> Result<Vec<u8>, Error> {
+    let (decoded_hrp, data, variant) = bech32::decode(s).map_err(|_| Error::Encoding)?;
+    if decoded_hrp != hrp || variant != Variant::Bech32 {
+        return Err(Error::Encoding);
+    }
+    Vec::<u8>::from_base32(&data).map_err(|_| Error::Encoding)
+}
+
+fn decode_base64<const N: usize>(s: &str) -> Result<[u8; N], Error> {
+    let bytes = STANDARD_NO_PAD.decode(s).map_err(|_| Error::Stanza)?;
+    bytes.try_into().map_err(|_| Error::Stanza)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn encoding() {
+        let mut rng = rand::thread_rng();
+        let identity = Identity::generate(&mut rng);
+        let recipient = identity.to_public();
+
+        let s = identity.to_string();
+        assert!(s.starts_with("AGE-PLUGIN-MLKEM-1"));
+        let decoded: Identity = s.parse().unwrap();
+        assert_eq!(decoded.to_public(), recipient);
+        let decoded: Identity = s.to_lowercase().parse().unwrap();
+        assert_eq!(decoded.to_public(), recipient);
+
+        let s = recipient.to_string();
+        assert!(s.starts_with("age1mlkem1"));
+        assert_eq!(s.parse(), Ok(recipient));
+
+        // Each kind of key has its own prefix
+        assert_eq!(
+            identity.to_string().parse::<Recipient>(),
+            Err(Error::Encoding)
+        );
+        assert_eq!(s.parse::<Identity>().map(|_| ()), Err(Error::Encoding));
+
+        // Modified strings fail the checksum
+        let mut modified = s.into_bytes();
+        let last = modified.len() - 1;
+        modified[last] = if modified[last] == b'q' { b'p' } else { b'q' };
+        let modified = String::from_utf8(modified).unwrap();
+        assert_eq!(modified.parse::<Recipient>(), Err(Error::Encoding));
+    }
+
+    #[test]
+    fn invalid_recipient() {
+        // An ML-KEM encapsulation key that fails the modulus check
+        let data = [0xff; EK_LEN + X25519_LEN];
+        let s = bech32::encode(RECIPIENT_HRP, data.to_base32(), Variant::Bech32).unwrap();
+        assert_eq!(s.parse::<Recipient>(), Err(Error::InvalidKey));
+
+        let s = bech32::encode(
+            RECIPIENT_HRP,
+            data[1..].to_vec().to_base32(),
+            Variant::Bech32,
+        )
+        .unwrap();
+        assert_eq!(s.parse::<Recipient>(), Err(Error::Encoding));
+    }
+
+    #[test]
+    fn wrap_unwrap() {
+        let mut rng = rand::thread_rng();
+        let identity = Identity::generate(&mut rng);
+        let other = Identity::generate(&mut rng);
+        let file_key = [0x42; 16];
+
+        let stanza = identity
+            .to_public()
+            .wrap_file_key(&file_key, &mut rng)
+            .unwrap();
+        assert_eq!(stanza.tag, STANZA_TAG);
+        assert_eq!(stanza.args[0].len(), 43);
+        assert_eq!(stanza.args[1].len(), 1451);
+        assert_eq!(stanza.body.len(), WRAPPED_KEY_LEN);
+
+        assert_eq!(identity.unwrap_stanza(&stanza), Some(Ok(file_key)));
+        assert_eq!(other.unwrap_stanza(&stanza), Some(Err(Error::Unwrap)));
+
+        // Stanzas of other types are skipped
+        let x25519 = Stanza::new("X25519", &["AAAA"], &[0; 32]);
+        assert_eq!(identity.unwrap_stanza(&x25519), None);
+
+        // Modified stanzas
+        let mut modified = stanza.clone();
+        modified.body[0] ^= 1;
+        assert_eq!(identity.unwrap_stanza(&modified), Some(Err(Error::Unwrap)));
+
+        let mut modified = stanza.clone();
+        modified.args.pop();
+        assert_eq!(identity.unwrap_stanza(&modified), Some(Err(Error::Stanza)));
+
+        let mut modified = stanza.clone();
+        modified.args[1].pop();
+        assert_eq!(identity.unwrap_stanza(&modified), Some(Err(Error::Stanza)));
+
+        let mut modified = stanza.clone();
+        modified.body.push(0);
+        assert_eq!(identity.unwrap_stanza(&modified), Some(Err(Error::Stanza)));
+
+        // A low-order X25519 share
+        let mut modified = stanza;
+        modified.args[0] = STANDARD_NO_PAD.encode([0; 32]);
+        assert_eq!(identity.unwrap_stanza(&modified), Some(Err(Error::Stanza)));
+    }
+}
@@ -0,0 +1,79 @@
+#![cfg_attr(docsrs, feature(doc_auto_cfg))]
+#![doc = include_str!("../README.md")]
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/RustCrypto/meta/master/logo.svg",
+    html_favicon_url = "https://raw.githubusercontent.com/RustCrypto/meta/master/logo.svg"
+)]
+#![warn(clippy::pedantic)] // Be pedantic by default
+#![deny(missing_docs)] // Require all public interfaces to be documented
+
+//! # Usage
+//!
+//! An [`Identity`] holds the secret keys, and its [`Recipient`] the public keys to which file
+//! keys are wrapped.  Both have string forms in the usual age style.
+//!
+//! ```
+//! use age_plugin_mlkem::{Identity, Recipient};
+//!
+//! let mut rng = rand::thread_rng();
+//! let identity = Identity::generate(&mut rng);
+//! let recipient: Recipient = identity.to_public().to_string().parse().unwrap();
+//!
+//! let file_key = [0x42; 16];
+//! let stanza = recipient.wrap_file_key(&file_key, &mut rng).unwrap();
+//! assert_eq!(identity.unwrap_stanza(&stanza), Some(Ok(file_key)));
+//! ```
+//!
+//! The [`plugin`] module implements the plugin protocol on top of these, for the
+//! `age-plugin-mlkem` binary.
+
+pub mod plugin;
+
+mod keys;
+mod stanza;
+
+pub use keys::{Identity, Recipient};
+pub use stanza::Stanza;
+
+use core::fmt;
+
+/// The name of the plugin, which age derives from the recipient and identity prefixes
+pub const PLUGIN_NAME: &str = "mlkem";
+
+/// The tag of the recipient stanzas produced by this plugin.  The `-plugin` suffix keeps them
+/// apart from the `mlkem768x25519` stanzas of age's native hybrid recipients, which use a
+/// different construction.
+pub const STANZA_TAG: &str = "mlkem768x25519-plugin";
+
+/// An age file key
+pub type FileKey = [u8; 16];
+
+/// An error in parsing keys or in wrapping or unwrapping file keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A recipient or identity is not a valid Bech32 encoding of the right kind of key
+    Encoding,
+
+    /// A key is invalid: The ML-KEM encapsulation key fails the modulus check of FIPS 203, or the
+    /// X25519 public key has low order
+    InvalidKey,
+
+    /// A stanza has the wrong arguments or body
+    Stanza,
+
+    /// The file key could not be unwrapped
+    Unwrap,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Encoding => "invalid encoding",
+            Self::InvalidKey => "invalid key",
+            Self::Stanza => "malformed stanza",
+            Self::Unwrap => "file key could not be unwrapped",
+        })
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,47 @@
+//! The `age-plugin-mlkem` binary.  Run without arguments, it generates a new identity; age runs it
+//! with `--age-plugin=<state machine>` to encrypt and decrypt files.
+
+use age_plugin_mlkem::{plugin, Identity};
+use rand_core::OsRng;
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage:
+    age-plugin-mlkem [--generate]
+    age-plugin-mlkem --age-plugin=<recipient-v1|identity-v1>
+
+Without arguments, or with --generate, print a new identity and its recipient.  The
+--age-plugin option is used by age clients to run the plugin protocol.";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        [] | ["--generate"] => generate(),
+        ["--age-plugin=recipient-v1"] => {
+            plugin::run_recipient_v1(io::stdin().lock(), io::stdout().lock(), &mut OsRng)
+        }
+        ["--age-plugin=identity-v1"] => {
+            plugin::run_identity_v1(io::stdin().lock(), io::stdout().lock())
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("age-plugin-mlkem: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn generate() -> io::Result<()> {
+    let identity = Identity::generate(&mut OsRng);
+    let mut out = io::stdout().lock();
+    writeln!(out, "# public key: {}", identity.to_public())?;
+    writeln!(out, "{identity}")
+}
@@ -0,0 +1,234 @@
+//! The `recipient-v1` and `identity-v1` state machines of the age plugin protocol.
+//!
+//! An age client runs the plugin binary with `--age-plugin=recipient-v1` to encrypt, or with
+//! `--age-plugin=identity-v1` to decrypt, and the two exchange stanzas over the plugin's standard
+//! input and output.  In phase 1, the client sends its commands, ending with `done`.  In phase 2,
+//! the plugin sends its own commands, to each of which the client responds, again ending with
+//! `done`.  The state machines here read and write any [`BufRead`] and [`Write`], so a local
+//! harness can drive them with in-memory buffers, as long as it supplies the client's responses
+//! to phase 2 commands along with its phase 1 commands.
+
+use rand_core::CryptoRngCore;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+
+use crate::{Identity, Recipient, Stanza};
+
+/// The label with which recipients mark file keys as post-quantum, so that clients refuse to
+/// mix them with recipients that are not
+pub const POSTQUANTUM_LABEL: &str = "postquantum";
+
+/// Run the `recipient-v1` state machine, wrapping each file key the client sends to each of its
+/// recipients.  Identities the client sends are wrapped to as their recipients.
+///
+/// # Errors
+///
+/// Returns an error if reading or writing fails, or with [`io::ErrorKind::InvalidData`] if the
+/// client violates the protocol.  Invalid recipients and identities are instead reported to the
+/// client.
+pub fn run_recipient_v1(
+    mut input: impl BufRead,
+    mut output: impl Write,
+    rng: &mut impl CryptoRngCore,
+) -> io::Result<()> {
+    let mut recipients = Vec::new();
+    let mut identities = Vec::new();
+    let mut file_keys = Vec::new();
+    let mut labels = false;
+    for command in phase_one(&mut input)? {
+        match (command.tag.as_str(), &command.args[..]) {
+            ("add-recipient", [recipient]) => recipients.push(recipient.clone()),
+            ("add-identity", [identity]) => identities.push(identity.clone()),
+            ("wrap-file-key", []) => {
+                let file_key = command.body.try_into().map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidData, "file key has the wrong length")
+                })?;
+                file_keys.push(file_key);
+            }
+            ("extension-labels", []) => labels = true,
+            _ => {}
+        }
+    }
+
+    let mut parsed = Vec::with_capacity(recipients.len() + identities.len());
+    let mut errors = Vec::new();
+    for (index, recipient) in recipients.iter().enumerate() {
+        match recipient.parse::<Recipient>() {
+            Ok(recipient) => parsed.push(recipient),
+            Err(e) => errors.push(("recipient", index, format!("invalid recipient: {e}"))),
+        }
+    }
+    for (index, identity) in identities.iter().enumerate() {
+        match identity.parse::<Identity>() {
+            Ok(identity) => parsed.push(identity.to_public()),
+            Err(e) => errors.push(("identity", index, format!("invalid identity: {e}"))),
+        }
+    }
+    if !errors.is_empty() {
+        for (kind, index, message) in errors {
+            let index = index.to_string();
+            command(
+                &mut input,
+                &mut output,
+                &Stanza::new("error", &[kind, &index], message.as_bytes()),
+            )?;
+        }
+        return done(&mut output);
+    }
+
+    if labels {
+        command(
+            &mut input,
+            &mut output,
+            &Stanza::new("labels", &[POSTQUANTUM_LABEL], &[]),
+        )?;
+    }
+    for (file_index, file_key) in file_keys.iter().enumerate() {
+        for recipient in &parsed {
+            let stanza = match recipient.wrap_file_key(file_key, rng) {
+                Ok(stanza) => stanza,
+                Err(e) => {
+                    let message = format!("failed to wrap file key: {e}");
+                    command(
+                        &mut input,
+                        &mut output,
+                        &Stanza::new("error", &["internal"], message.as_bytes()),
+                    )?;
+                    return done(&mut output);
+                }
+            };
+
+            let file_index = file_index.to_string();
+            let mut args = vec![file_index.as_str(), stanza.tag.as_str()];
+            args.extend(stanza.args.iter().map(String::as_str));
+            command(
+                &mut input,
+                &mut output,
+                &Stanza::new("recipient-stanza", &args, &stanza.body),
+            )?;
+        }
+    }
+    done(&mut output)
+}
+
+/// Run the `identity-v1` state machine, unwrapping the file key of each file the client sends
+/// stanzas for with any of its identities.  Files that none of the identities can unwrap are
+/// skipped.
+///
+/// # Errors
+///
+/// Returns an error if reading or writing fails, or with [`io::ErrorKind::InvalidData`] if the
+/// client violates the protocol.  Invalid identities and malformed stanzas are instead reported
+/// to the client.
+pub fn run_identity_v1(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut identities = Vec::new();
+    let mut files: BTreeMap<usize, Vec<Stanza>> = BTreeMap::new();
+    for command in phase_one(&mut input)? {
+        match (command.tag.as_str(), &command.args[..]) {
+            ("add-identity", [identity]) => identities.push(identity.clone()),
+            ("recipient-stanza", [file_index, tag, args @ ..]) => {
+                let file_index = file_index.parse().map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidData, "invalid file index")
+                })?;
+                let args: Vec<_> = args.iter().map(String::as_str).collect();
+                files
+                    .entry(file_index)
+                    .or_default()
+                    .push(Stanza::new(tag, &args, &command.body));
+            }
+            _ => {}
+        }
+    }
+
+    let mut parsed = Vec::with_capacity(identities.len());
+    for (index, identity) in identities.iter().enumerate() {
+        match identity.parse::<Identity>() {
+            Ok(identity) => parsed.push(identity),
+            Err(e) => {
+                let index = index.to_string();
+                let message = format!("invalid identity: {e}");
+                command(
+                    &mut input,
+                    &mut output,
+                    &Stanza::new("error", &["identity", &index], message.as_bytes()),
+                )?;
+            }
+        }
+    }
+    if parsed.len() != identities.len() {
+        return done(&mut output);
+    }
+
+    for (file_index, stanzas) in &files {
+        let file_index = file_index.to_string();
+        'stanzas: for (stanza_index, stanza) in stanzas.iter().enumerate() {
+            for identity in &parsed {
+                match identity.unwrap_stanza(stanza) {
+                    None => continue 'stanzas,
+                    Some(Ok(file_key)) => {
+                        command(
+                            &mut input,
+                            &mut output,
+                            &Stanza::new("file-key", &[&file_index], &file_key),
+                        )?;
+                        break 'stanzas;
+                    }
+                    Some(Err(crate::Error::Unwrap)) => {}
+                    Some(Err(e)) => {
+                        let stanza_index = stanza_index.to_string();
+                        let message = format!("invalid stanza: {e}");
+                        command(
+                            &mut input,
+                            &mut output,
+                            &Stanza::new(
+                                "error",
+                                &["stanza", &file_index, &stanza_index],
+                                message.as_bytes(),
+                            ),
+                        )?;
+                        break 'stanzas;
+                    }
+                }
+            }
+        }
+    }
+    done(&mut output)
+}
+
+// Read the client's commands up to `done`
+fn phase_one(input: &mut impl BufRead) -> io::Result<Vec<Stanza>> {
+    let mut commands = Vec::new();
+    loop {
+        let command = Stanza::read_from(input)?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "client did not finish phase 1",
+            )
+        })?;
+        if command.tag == "done" {
+            return Ok(commands);
+        }
+        commands.push(command);
+    }
+}
+
+// Send a phase 2 command, and wait for the client to respond with `ok` or `fail`
+fn command(input: &mut impl BufRead, output: &mut impl Write, command: &Stanza) -> io::Result<()> {
+    command.write_to(output)?;
+    output.flush()?;
+
+    let response = Stanza::read_from(input)?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "client did not respond"))?;
+    match response.tag.as_str() {
+        "ok" | "fail" => Ok(()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unexpected response from client",
+        )),
+    }
+}
+
+fn done(output: &mut impl Write) -> io::Result<()> {
+    Stanza::new("done", &[], &[]).write_to(output)?;
+    output.flush()
+}
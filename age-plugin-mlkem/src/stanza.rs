@@ -0,0 +1,168 @@
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use std::io::{self, BufRead, Write};
+
+// The number of base64 characters in each full line of a stanza body
+const LINE_LEN: usize = 64;
+
+/// An age stanza: A tag, a list of arguments, and a binary body.  Stanzas make up both the
+/// recipient stanzas in a file header and the messages of the plugin protocol.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stanza {
+    /// The tag, which identifies the type of stanza
+    pub tag: String,
+
+    /// The arguments
+    pub args: Vec<String>,
+
+    /// The body
+    pub body: Vec<u8>,
+}
+
+impl Stanza {
+    /// Create a stanza
+    #[must_use]
+    pub fn new(tag: &str, args: &[&str], body: &[u8]) -> Self {
+        Self {
+            tag: tag.into(),
+            args: args.iter().map(|&arg| arg.into()).collect(),
+            body: body.into(),
+        }
+    }
+
+    /// Write the stanza, with its body base64-encoded and wrapped at 64 columns.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails.
+    pub fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        write!(w, "-> {}", self.tag)?;
+        for arg in &self.args {
+            write!(w, " {arg}")?;
+        }
+        writeln!(w)?;
+
+        // The last line is always shorter than a full line, even if it is empty
+        let body = STANDARD_NO_PAD.encode(&self.body);
+        let mut lines = body.as_bytes().chunks(LINE_LEN);
+        loop {
+            let line = lines.next().unwrap_or_default();
+            w.write_all(line)?;
+            writeln!(w)?;
+            if line.len() < LINE_LEN {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Read a stanza, or `None` at the end of the input.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading fails, or with [`io::ErrorKind::InvalidData`] if the input is
+    /// not a well-formed stanza.
+    pub fn read_from(r: &mut impl BufRead) -> io::Result<Option<Self>> {
+        let mut line = String::new();
+        if r.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let mut words = line
+            .strip_suffix('\n')
+            .and_then(|line| line.strip_prefix("-> "))
+            .ok_or_else(|| invalid("stanza does not start with \"-> \""))?
+            .split(' ');
+        let tag = words.next().unwrap_or_default().to_owned();
+        let args: Vec<_> = words.map(String::from).collect();
+        if !is_word(&tag) || !args.iter().all(|arg| is_word(arg)) {
+            return Err(invalid("invalid stanza tag or argument"));
+        }
+
+        let mut body = String::new();
+        loop {
+            line.clear();
+            r.read_line(&mut line)?;
+            let line = line
+                .strip_suffix('\n')
+                .ok_or_else(|| invalid("truncated stanza body"))?;
+            if line.len() > LINE_LEN {
+                return Err(invalid("stanza body line too long"));
+            }
+            body.push_str(line);
+            if line.len() < LINE_LEN {
+                break;
+            }
+        }
+        let body = STANDARD_NO_PAD
+            .decode(body)
+            .map_err(|_| invalid("invalid base64 in stanza body"))?;
+
+        Ok(Some(Self { tag, args, body }))
+    }
+}
+
+// Whether `word` is a non-empty string of visible ASCII characters
+fn is_word(word: &str) -> bool {
+    !word.is_empty() && word.bytes().all(|b| b.is_ascii_graphic())
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn encode(stanza: &Stanza) -> String {
+        let mut out = Vec::new();
+        stanza.write_to(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn decode(s: &str) -> io::Result<Option<Stanza>> {
+        Stanza::read_from(&mut s.as_bytes())
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(encode(&Stanza::new("done", &[], &[])), "-> done\n\n");
+        assert_eq!(
+            encode(&Stanza::new("tag", &["a", "b"], b"hello")),
+            "-> tag a b\naGVsbG8\n"
+        );
+
+        // A body that exactly fills a line is followed by an empty line
+        let full = encode(&Stanza::new("tag", &[], &[0; 48]));
+        assert_eq!(full, format!("-> tag\n{}\n\n", "A".repeat(64)));
+        let long = encode(&Stanza::new("tag", &[], &[0; 49]));
+        assert_eq!(long, format!("-> tag\n{}\nAA\n", "A".repeat(64)));
+
+        for s in [full, long] {
+            assert_eq!(encode(&decode(&s).unwrap().unwrap()), s);
+        }
+        assert_eq!(decode("").unwrap(), None);
+    }
+
+    #[test]
+    fn invalid_stanzas() {
+        for s in [
+            "tag\n\n",
+            "-> tag\n",
+            "-> tag\naGVsbG8",
+            "-> \n\n",
+            "-> tag  a\n\n",
+            "-> tag a \n\n",
+            "-> tag\naGVsbG8=\n",
+            "-> tag\naGVsbG9\n",
+        ] {
+            let err = decode(s).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{s:?}");
+        }
+
+        // A full line must be followed by another line
+        let s = format!("-> tag\n{}\n", "A".repeat(64));
+        assert!(decode(&s).is_err());
+        let s = format!("-> tag\n{}\n", "A".repeat(65));
+        assert!(decode(&s).is_err());
+    }
+}
@@ -0,0 +1,172 @@
+//! A local harness for the plugin protocol, which plays the part of the age client.  Since the
+//! client's responses to phase 2 commands are known in advance, the harness writes them after its
+//! phase 1 commands, runs a state machine over the whole script, and then checks the commands the
+//! plugin sent.
+
+use age_plugin_mlkem::{plugin, Identity, Stanza, STANZA_TAG};
+
+fn script(commands: &[Stanza], responses: usize) -> Vec<u8> {
+    let mut input = Vec::new();
+    for command in commands {
+        command.write_to(&mut input).unwrap();
+    }
+    Stanza::new("done", &[], &[]).write_to(&mut input).unwrap();
+    for _ in 0..responses {
+        Stanza::new("ok", &[], &[]).write_to(&mut input).unwrap();
+    }
+    input
+}
+
+fn read_all(output: &[u8]) -> Vec<Stanza> {
+    let mut output = output;
+    let mut stanzas = Vec::new();
+    while let Some(stanza) = Stanza::read_from(&mut output).unwrap() {
+        stanzas.push(stanza);
+    }
+    assert_eq!(stanzas.pop().unwrap().tag, "done");
+    stanzas
+}
+
+fn encrypt(commands: &[Stanza], responses: usize) -> Vec<Stanza> {
+    let input = script(commands, responses);
+    let mut output = Vec::new();
+    plugin::run_recipient_v1(&input[..], &mut output, &mut rand::thread_rng()).unwrap();
+    read_all(&output)
+}
+
+fn decrypt(commands: &[Stanza], responses: usize) -> Vec<Stanza> {
+    let input = script(commands, responses);
+    let mut output = Vec::new();
+    plugin::run_identity_v1(&input[..], &mut output).unwrap();
+    read_all(&output)
+}
+
+// Convert the `recipient-stanza` commands of the recipient state machine into those of the
+// identity state machine, as the client would after writing and reading a file header
+fn recipient_stanzas(commands: &[Stanza]) -> Vec<Stanza> {
+    commands
+        .iter()
+        .filter(|command| command.tag == "recipient-stanza")
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn round_trip() {
+    let mut rng = rand::thread_rng();
+    let alice = Identity::generate(&mut rng);
+    let bob = Identity::generate(&mut rng);
+    let file_keys = [[1; 16], [2; 16]];
+
+    let mut commands = vec![
+        Stanza::new("add-recipient", &[&alice.to_public().to_string()], &[]),
+        Stanza::new("add-identity", &[&bob.to_string()], &[]),
+    ];
+    for file_key in &file_keys {
+        commands.push(Stanza::new("wrap-file-key", &[], file_key));
+    }
+    let sent = encrypt(&commands, 4);
+
+    // One stanza for each recipient and file
+    assert_eq!(sent.len(), 4);
+    for (command, file_index) in sent.iter().zip(["0", "0", "1", "1"]) {
+        assert_eq!(command.tag, "recipient-stanza");
+        assert_eq!(command.args[..2], [file_index, STANZA_TAG]);
+        assert_eq!(command.args.len(), 4);
+    }
+
+    for identity in [&alice, &bob] {
+        let mut commands = vec![Stanza::new("add-identity", &[&identity.to_string()], &[])];
+        commands.extend(recipient_stanzas(&sent));
+        let sent = decrypt(&commands, 2);
+        assert_eq!(sent.len(), 2);
+        for (command, (file_index, file_key)) in sent.iter().zip(["0", "1"].iter().zip(file_keys)) {
+            assert_eq!(command.tag, "file-key");
+            assert_eq!(command.args, [*file_index]);
+            assert_eq!(command.body, file_key);
+        }
+    }
+
+    // An identity for which nothing was wrapped unwraps nothing
+    let carol = Identity::generate(&mut rng);
+    let mut commands = vec![Stanza::new("add-identity", &[&carol.to_string()], &[])];
+    commands.extend(recipient_stanzas(&sent));
+    assert!(decrypt(&commands, 0).is_empty());
+}
+
+#[test]
+fn labels() {
+    let identity = Identity::generate(&mut rand::thread_rng());
+    let commands = [
+        Stanza::new("add-recipient", &[&identity.to_public().to_string()], &[]),
+        Stanza::new("extension-labels", &[], &[]),
+        Stanza::new("wrap-file-key", &[], &[0; 16]),
+    ];
+    let sent = encrypt(&commands, 2);
+    assert_eq!(sent[0], Stanza::new("labels", &["postquantum"], &[]));
+    assert_eq!(sent[1].tag, "recipient-stanza");
+}
+
+#[test]
+fn errors() {
+    let mut rng = rand::thread_rng();
+    let identity = Identity::generate(&mut rng);
+
+    // Invalid recipients and identities are reported, and nothing is wrapped
+    let commands = [
+        Stanza::new("add-recipient", &[&identity.to_public().to_string()], &[]),
+        Stanza::new("add-recipient", &["age1mlkem1invalid"], &[]),
+        Stanza::new("add-identity", &["AGE-PLUGIN-MLKEM-1INVALID"], &[]),
+        Stanza::new("wrap-file-key", &[], &[0; 16]),
+    ];
+    let sent = encrypt(&commands, 2);
+    assert_eq!(sent.len(), 2);
+    assert_eq!(sent[0].tag, "error");
+    assert_eq!(sent[0].args, ["recipient", "1"]);
+    assert_eq!(sent[1].args, ["identity", "0"]);
+
+    let commands = [Stanza::new(
+        "add-identity",
+        &["AGE-PLUGIN-MLKEM-1INVALID"],
+        &[],
+    )];
+    let sent = decrypt(&commands, 1);
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].args, ["identity", "0"]);
+
+    // Malformed stanzas are reported, and stanzas of other types are ignored
+    let commands = [
+        Stanza::new("add-identity", &[&identity.to_string()], &[]),
+        Stanza::new("recipient-stanza", &["0", "X25519", "AAAA"], &[0; 32]),
+        Stanza::new("recipient-stanza", &["0", STANZA_TAG, "AAAA"], &[0; 32]),
+    ];
+    let sent = decrypt(&commands, 1);
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].tag, "error");
+    assert_eq!(sent[0].args, ["stanza", "0", "1"]);
+
+    // The client must finish phase 1 and respond to each command
+    let identity = identity.to_string();
+    let mut input = Vec::new();
+    Stanza::new("add-identity", &[&identity], &[])
+        .write_to(&mut input)
+        .unwrap();
+    assert!(plugin::run_identity_v1(&input[..], Vec::new()).is_err());
+
+    let commands = [
+        Stanza::new("add-identity", &[&identity], &[]),
+        Stanza::new("wrap-file-key", &[], &[0; 16]),
+    ];
+    let input = script(&commands, 0);
+    let result = plugin::run_recipient_v1(&input[..], Vec::new(), &mut rng);
+    assert!(result.is_err());
+
+    // File keys have a fixed length
+    let commands = [
+        Stanza::new("add-identity", &[&identity], &[]),
+        Stanza::new("wrap-file-key", &[], &[0; 15]),
+    ];
+    let input = script(&commands, 0);
+    let result = plugin::run_recipient_v1(&input[..], Vec::new(), &mut rng);
+    assert!(result.is_err());
+}
@@ -12,50 +12,60 @@ pub fn rand<L: ArraySize>(rng: &mut impl CryptoRngCore) -> Array<u8, L> {
     val
 }
 
-fn criterion_benchmark(c: &mut Criterion) {
+fn bench_kem<K: KemCore>(c: &mut Criterion, name: &str) {
     let mut rng = rand::thread_rng();
     let d: B32 = rand(&mut rng);
     let z: B32 = rand(&mut rng);
     let m: B32 = rand(&mut rng);
 
-    let (dk, ek) = MlKem768::generate_deterministic(&d, &z);
+    let (dk, ek) = K::generate_deterministic(&d, &z);
     let dk_bytes = dk.as_bytes();
     let ek_bytes = ek.as_bytes();
     let (ct, _sk) = ek.encapsulate(&mut rng).unwrap();
 
+    let mut group = c.benchmark_group(name);
+
     // Key generation
-    c.bench_function("keygen", |b| {
+    group.bench_function("keygen", |b| {
         b.iter(|| {
-            let (dk, ek) = <MlKem768 as KemCore>::generate_deterministic(&d, &z);
+            let (dk, ek) = K::generate_deterministic(&d, &z);
             let _dk_bytes = dk.as_bytes();
             let _ek_bytes = ek.as_bytes();
         })
     });
 
     // Encapsulation
-    c.bench_function("encapsulate", |b| {
+    group.bench_function("encapsulate", |b| {
         b.iter(|| {
-            let ek = <MlKem768 as KemCore>::EncapsulationKey::from_bytes(&ek_bytes);
+            let ek = K::EncapsulationKey::from_bytes(&ek_bytes);
             ek.encapsulate_deterministic(&m).unwrap();
         })
     });
 
     // Decapsulation
-    c.bench_function("decapsulate", |b| {
+    group.bench_function("decapsulate", |b| {
         b.iter(|| {
-            let dk = <MlKem768 as KemCore>::DecapsulationKey::from_bytes(&dk_bytes);
+            let dk = K::DecapsulationKey::from_bytes(&dk_bytes);
             dk.decapsulate(&ct).unwrap();
         })
     });
 
     // Round trip
-    c.bench_function("round_trip", |b| {
+    group.bench_function("round_trip", |b| {
         b.iter(|| {
-            let (dk, ek) = <MlKem768 as KemCore>::generate_deterministic(&d, &z);
+            let (dk, ek) = K::generate_deterministic(&d, &z);
             let (ct, _sk) = ek.encapsulate(&mut rng).unwrap();
             dk.decapsulate(&ct).unwrap();
         })
     });
+
+    group.finish();
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    bench_kem::<MlKem512>(c, "ML-KEM-512");
+    bench_kem::<MlKem768>(c, "ML-KEM-768");
+    bench_kem::<MlKem1024>(c, "ML-KEM-1024");
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -0,0 +1,383 @@
+//! This follows the `Kyber.AKE` construction from "CRYSTALS – Kyber: a CCA-secure
+//! module-lattice-based KEM" by Bos et al. (EuroS&P 2018).
+//!
+//! Each party has a static key pair, and knows the other party's static encapsulation key.
+//!
+//! 1. The initiator generates an ephemeral key pair, encapsulates a key `K_R` to the responder's
+//!    static key, and sends the ephemeral encapsulation key and the ciphertext.
+//! 2. The responder decapsulates `K_R`, encapsulates a key `K_E` to the ephemeral key and a key
+//!    `K_I` to the initiator's static key, and sends both ciphertexts with a confirmation tag.
+//! 3. The initiator decapsulates `K_E` and `K_I`, and checks the confirmation tag.
+//!
+//! Only the holder of the responder's static key can recover `K_R`, and only the holder of the
+//! initiator's static key can recover `K_I`, so the session key authenticates both parties.  The
+//! session key and the confirmation tag are derived as
+//!
+//! ```text
+//! SHAKE256("ML-KEM AKE v1" || ek_I || ek_R || ek_E || ct_R || ct_E || ct_I || K_R || K_E || K_I)
+//! ```
+//!
+//! so they are bound to the whole transcript of the exchange.
+//!
+//! ```
+//! # use ml_kem::{ake, KemCore, MlKem768};
+//! let mut rng = rand::thread_rng();
+//! let (dk_i, ek_i) = MlKem768::generate(&mut rng);
+//! let (dk_r, ek_r) = MlKem768::generate(&mut rng);
+//!
+//! let initiator = ake::Initiator::<MlKem768>::new(&dk_i, &ek_i, &ek_r);
+//! let responder = ake::Responder::<MlKem768>::new(&dk_r, &ek_r, &ek_i);
+//!
+//! let (initiator, request) = initiator.start(&mut rng);
+//! let (response, k_responder) = responder.respond(&request, &mut rng).unwrap();
+//! let k_initiator = initiator.finish(&response).unwrap();
+//! assert_eq!(k_initiator, k_responder);
+//! ```
+//!
+//! The responder cannot tell whether the request came from the expected initiator, or was
+//! replayed: In either case, it derives a key that only the holder of the initiator's static
+//! key can compute.  The responder should therefore not send application data protected with
+//! the session key until it has received some from the initiator.
+
+use core::fmt;
+use hybrid_array::typenum::U32;
+use rand_core::CryptoRngCore;
+use sha3::{
+    digest::{ExtendableOutput, Update, XofReader},
+    Shake256,
+};
+
+use crate::kem::{constant_time_eq, decapsulate, encapsulate};
+use crate::util::B32;
+use crate::{Ciphertext, Encoded, EncodedSizeUser, KemCore};
+
+/// A session key established by the key exchange
+pub type SessionKey = B32;
+
+const LABEL: &[u8] = b"ML-KEM AKE v1";
+
+/// The first message of the exchange, from the initiator to the responder
+pub struct Request<K>
+where
+    K: KemCore<SharedKeySize = U32>,
+{
+    /// The initiator's ephemeral encapsulation key
+    pub ek: Encoded<K::EncapsulationKey>,
+
+    /// The ciphertext encapsulating `K_R` to the responder's static key
+    pub ct: Ciphertext<K>,
+}
+
+/// The second message of the exchange, from the responder to the initiator
+pub struct Response<K>
+where
+    K: KemCore<SharedKeySize = U32>,
+{
+    /// The ciphertext encapsulating `K_E` to the initiator's ephemeral key
+    pub ct_ephemeral: Ciphertext<K>,
+
+    /// The ciphertext encapsulating `K_I` to the initiator's static key
+    pub ct_static: Ciphertext<K>,
+
+    /// A tag confirming that the responder derived the same session key
+    pub confirmation: B32,
+}
+
+/// An error in the key exchange
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The responder's confirmation tag does not match.  The responder does not hold the expected
+    /// static key, does not expect this initiator, or the messages were modified or replayed.
+    Confirmation,
+
+    /// The initiator's ephemeral encapsulation key fails the modulus check of FIPS 203
+    InvalidKey,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Confirmation => "key exchange confirmation failed",
+            Self::InvalidKey => "invalid ephemeral encapsulation key",
+        })
+    }
+}
+
+/// The initiator of a key exchange, before it has sent its request
+pub struct Initiator<'a, K>
+where
+    K: KemCore<SharedKeySize = U32>,
+{
+    dk: &'a K::DecapsulationKey,
+    ek: &'a K::EncapsulationKey,
+    peer: &'a K::EncapsulationKey,
+}
+
+impl<'a, K> Initiator<'a, K>
+where
+    K: KemCore<SharedKeySize = U32>,
+{
+    /// Prepare to exchange keys with the holder of `peer`, using the static key pair `(dk, ek)`
+    pub fn new(
+        dk: &'a K::DecapsulationKey,
+        ek: &'a K::EncapsulationKey,
+        peer: &'a K::EncapsulationKey,
+    ) -> Self {
+        Self { dk, ek, peer }
+    }
+
+    /// Generate an ephemeral key pair, encapsulate to the responder, and produce the request to
+    /// send to it
+    pub fn start(self, rng: &mut impl CryptoRngCore) -> (AwaitingResponse<'a, K>, Request<K>) {
+        let (dk_ephemeral, ek_ephemeral) = K::generate(rng);
+        let (ct, K_R) = encapsulate(self.peer, rng);
+        let request = Request {
+            ek: ek_ephemeral.as_bytes(),
+            ct,
+        };
+
+        let mut transcript = transcript::<K>(self.ek, self.peer);
+        transcript.update(&request.ek);
+        transcript.update(&request.ct);
+
+        let state = AwaitingResponse {
+            dk: self.dk,
+            dk_ephemeral,
+            transcript,
+            K_R,
+        };
+        (state, request)
+    }
+}
+
+/// The initiator of a key exchange, after it has sent its request
+pub struct AwaitingResponse<'a, K>
+where
+    K: KemCore<SharedKeySize = U32>,
+{
+    dk: &'a K::DecapsulationKey,
+    dk_ephemeral: K::DecapsulationKey,
+    transcript: Shake256,
+    K_R: B32,
+}
+
+impl<K> AwaitingResponse<'_, K>
+where
+    K: KemCore<SharedKeySize = U32>,
+{
+    /// Process the responder's response, and return the session key.  This consumes the state,
+    /// so that the ephemeral key is used only once.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Confirmation`] if the responder did not derive the same session key.
+    pub fn finish(self, response: &Response<K>) -> Result<SessionKey, Error> {
+        let K_E = decapsulate(&self.dk_ephemeral, &response.ct_ephemeral);
+        let K_I = decapsulate(self.dk, &response.ct_static);
+
+        let mut transcript = self.transcript;
+        transcript.update(&response.ct_ephemeral);
+        transcript.update(&response.ct_static);
+        let (session_key, confirmation) = derive(transcript, &[&self.K_R, &K_E, &K_I]);
+
+        let equal = confirmation
+            .iter()
+            .zip(response.confirmation.iter())
+            .map(|(&x, &y)| constant_time_eq(x, y))
+            .fold(0xff, |x, y| x & y);
+        if equal == 0xff {
+            Ok(session_key)
+        } else {
+            Err(Error::Confirmation)
+        }
+    }
+}
+
+/// The responder of a key exchange
+pub struct Responder<'a, K>
+where
+    K: KemCore<SharedKeySize = U32>,
+{
+    dk: &'a K::DecapsulationKey,
+    ek: &'a K::EncapsulationKey,
+    peer: &'a K::EncapsulationKey,
+}
+
+impl<'a, K> Responder<'a, K>
+where
+    K: KemCore<SharedKeySize = U32>,
+{
+    /// Prepare to exchange keys with the holder of `peer`, using the static key pair `(dk, ek)`
+    pub fn new(
+        dk: &'a K::DecapsulationKey,
+        ek: &'a K::EncapsulationKey,
+        peer: &'a K::EncapsulationKey,
+    ) -> Self {
+        Self { dk, ek, peer }
+    }
+
+    /// Process the initiator's request, and return the response to send to it together with the
+    /// session key
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidKey`] if the initiator's ephemeral encapsulation key is invalid.
+    pub fn respond(
+        self,
+        request: &Request<K>,
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<(Response<K>, SessionKey), Error> {
+        // Decoding reduces the coefficients of the key modulo q, so a key fails the modulus check
+        // exactly when it does not re-encode to the same bytes
+        let ek_ephemeral = K::EncapsulationKey::from_bytes(&request.ek);
+        if ek_ephemeral.as_bytes() != request.ek {
+            return Err(Error::InvalidKey);
+        }
+
+        let K_R = decapsulate(self.dk, &request.ct);
+        let (ct_ephemeral, K_E) = encapsulate(&ek_ephemeral, rng);
+        let (ct_static, K_I) = encapsulate(self.peer, rng);
+
+        let mut transcript = transcript::<K>(self.peer, self.ek);
+        transcript.update(&request.ek);
+        transcript.update(&request.ct);
+        transcript.update(&ct_ephemeral);
+        transcript.update(&ct_static);
+        let (session_key, confirmation) = derive(transcript, &[&K_R, &K_E, &K_I]);
+
+        let response = Response {
+            ct_ephemeral,
+            ct_static,
+            confirmation,
+        };
+        Ok((response, session_key))
+    }
+}
+
+// Start the transcript with the static keys of the initiator and responder
+fn transcript<K: KemCore>(ek_i: &K::EncapsulationKey, ek_r: &K::EncapsulationKey) -> Shake256 {
+    let mut transcript = Shake256::default();
+    transcript.update(LABEL);
+    transcript.update(&ek_i.as_bytes());
+    transcript.update(&ek_r.as_bytes());
+    transcript
+}
+
+// Absorb the shared keys into the transcript, and derive the session key and confirmation tag
+fn derive(mut transcript: Shake256, keys: &[&B32; 3]) -> (SessionKey, B32) {
+    for key in keys {
+        transcript.update(key.as_slice());
+    }
+
+    let mut reader = transcript.finalize_xof();
+    let mut session_key = SessionKey::default();
+    let mut confirmation = B32::default();
+    reader.read(&mut session_key);
+    reader.read(&mut confirmation);
+    (session_key, confirmation)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MlKem1024, MlKem512, MlKem768};
+
+    fn round_trip_test<K>()
+    where
+        K: KemCore<SharedKeySize = U32>,
+    {
+        let mut rng = rand::thread_rng();
+        let (dk_i, ek_i) = K::generate(&mut rng);
+        let (dk_r, ek_r) = K::generate(&mut rng);
+
+        let (initiator, request) = Initiator::<K>::new(&dk_i, &ek_i, &ek_r).start(&mut rng);
+        let (response, k_r) = Responder::<K>::new(&dk_r, &ek_r, &ek_i)
+            .respond(&request, &mut rng)
+            .unwrap();
+        let k_i = initiator.finish(&response).unwrap();
+        assert_eq!(k_i, k_r);
+    }
+
+    #[test]
+    fn round_trip() {
+        round_trip_test::<MlKem512>();
+        round_trip_test::<MlKem768>();
+        round_trip_test::<MlKem1024>();
+    }
+
+    #[test]
+    fn mismatched_keys() {
+        let mut rng = rand::thread_rng();
+        let (dk_i, ek_i) = MlKem768::generate(&mut rng);
+        let (dk_r, ek_r) = MlKem768::generate(&mut rng);
+        let (dk_x, ek_x) = MlKem768::generate(&mut rng);
+
+        // The initiator expects a different responder
+        let (initiator, request) = Initiator::<MlKem768>::new(&dk_i, &ek_i, &ek_x).start(&mut rng);
+        let (response, _) = Responder::<MlKem768>::new(&dk_r, &ek_r, &ek_i)
+            .respond(&request, &mut rng)
+            .unwrap();
+        assert_eq!(initiator.finish(&response), Err(Error::Confirmation));
+
+        // The responder expects a different initiator
+        let (initiator, request) = Initiator::<MlKem768>::new(&dk_i, &ek_i, &ek_r).start(&mut rng);
+        let (response, _) = Responder::<MlKem768>::new(&dk_r, &ek_r, &ek_x)
+            .respond(&request, &mut rng)
+            .unwrap();
+        assert_eq!(initiator.finish(&response), Err(Error::Confirmation));
+
+        // The initiator uses a different static key than the responder expects
+        let (initiator, request) = Initiator::<MlKem768>::new(&dk_x, &ek_x, &ek_r).start(&mut rng);
+        let (response, _) = Responder::<MlKem768>::new(&dk_r, &ek_r, &ek_i)
+            .respond(&request, &mut rng)
+            .unwrap();
+        assert_eq!(initiator.finish(&response), Err(Error::Confirmation));
+    }
+
+    #[test]
+    fn invalid_ephemeral_key() {
+        let mut rng = rand::thread_rng();
+        let (dk_i, ek_i) = MlKem768::generate(&mut rng);
+        let (dk_r, ek_r) = MlKem768::generate(&mut rng);
+
+        // Set the first coefficient of the ephemeral key to q, which fails the modulus check
+        let (_, mut request) = Initiator::<MlKem768>::new(&dk_i, &ek_i, &ek_r).start(&mut rng);
+        request.ek[0] = 0x01;
+        request.ek[1] = (request.ek[1] & 0xf0) | 0x0d;
+        assert!(matches!(
+            Responder::<MlKem768>::new(&dk_r, &ek_r, &ek_i).respond(&request, &mut rng),
+            Err(Error::InvalidKey)
+        ));
+    }
+
+    #[test]
+    fn replayed_messages() {
+        let mut rng = rand::thread_rng();
+        let (dk_i, ek_i) = MlKem768::generate(&mut rng);
+        let (dk_r, ek_r) = MlKem768::generate(&mut rng);
+
+        let (initiator, request) = Initiator::<MlKem768>::new(&dk_i, &ek_i, &ek_r).start(&mut rng);
+        let (response, k_r) = Responder::<MlKem768>::new(&dk_r, &ek_r, &ek_i)
+            .respond(&request, &mut rng)
+            .unwrap();
+        assert_eq!(initiator.finish(&response).unwrap(), k_r);
+
+        // A replayed request yields a fresh session key, which the attacker cannot compute
+        let (_, k_replay) = Responder::<MlKem768>::new(&dk_r, &ek_r, &ek_i)
+            .respond(&request, &mut rng)
+            .unwrap();
+        assert_ne!(k_replay, k_r);
+
+        // A replayed response does not match a new session of the initiator
+        let (initiator, _) = Initiator::<MlKem768>::new(&dk_i, &ek_i, &ek_r).start(&mut rng);
+        assert_eq!(initiator.finish(&response), Err(Error::Confirmation));
+
+        // Nor does a response with a modified ciphertext
+        let (initiator, request) = Initiator::<MlKem768>::new(&dk_i, &ek_i, &ek_r).start(&mut rng);
+        let (mut response, _) = Responder::<MlKem768>::new(&dk_r, &ek_r, &ek_i)
+            .respond(&request, &mut rng)
+            .unwrap();
+        response.ct_static[0] ^= 0x01;
+        assert_eq!(initiator.finish(&response), Err(Error::Confirmation));
+    }
+}
@@ -1,4 +1,4 @@
-use core::ops::{Add, Mul, Sub};
+use core::ops::{Add, AddAssign, Mul, Sub, SubAssign};
 use hybrid_array::{typenum::U256, Array};
 use sha3::digest::XofReader;
 
@@ -7,20 +7,34 @@ use crate::encode::Encode;
 use crate::param::{ArraySize, CbdSamplingSize};
 use crate::util::{Truncate, B32};
 
+/// The integer type underlying a `FieldElement`
 pub type Integer = u16;
 
 /// An element of GF(q).  Although `q` is only 16 bits wide, we use a wider uint type to so that we
 /// can defer modular reductions.
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
-pub struct FieldElement(pub Integer);
+pub struct FieldElement(pub(crate) Integer);
 
 impl FieldElement {
+    /// The modulus `q = 3329`
     pub const Q: Integer = 3329;
-    pub const Q32: u32 = Self::Q as u32;
+    pub(crate) const Q32: u32 = Self::Q as u32;
     const Q64: u64 = Self::Q as u64;
     const BARRETT_SHIFT: usize = 24;
     const BARRETT_MULTIPLIER: u64 = (1 << Self::BARRETT_SHIFT) / Self::Q64;
 
+    /// Create a field element from an integer, reducing it mod q
+    #[must_use]
+    pub const fn new(x: Integer) -> Self {
+        Self(x % Self::Q)
+    }
+
+    /// The canonical representative of this element, in the range `0..q`
+    #[must_use]
+    pub const fn value(self) -> Integer {
+        self.0
+    }
+
     // A fast modular reduction for small numbers `x < 2*q`
     fn small_reduce(x: u16) -> u16 {
         if x < Self::Q {
@@ -37,22 +51,66 @@ impl FieldElement {
         Self::small_reduce(remainder.truncate())
     }
 
-    // Algorithm 11. BaseCaseMultiply
-    //
-    // This is a hot loop.  We promote to u64 so that we can do the absolute minimum number of
-    // modular reductions, since these are the expensive operation.
-    fn base_case_multiply(a0: Self, a1: Self, b0: Self, b1: Self, i: usize) -> (Self, Self) {
-        let a0 = u32::from(a0.0);
-        let a1 = u32::from(a1.0);
-        let b0 = u32::from(b0.0);
-        let b1 = u32::from(b1.0);
-        let g = u32::from(GAMMA[i].0);
+    // Constants for signed Montgomery arithmetic, with R = 2^16
+    #[allow(clippy::cast_possible_wrap)]
+    const Q16: i16 = Self::Q as i16;
+    const QINV: i16 = -3327; // q^-1 mod 2^16
+    const MONT_R2: i16 = 1353; // 2^32 mod q
+    const INV_128: i16 = 3303; // 128^-1 mod q
+    const BARRETT_V: i32 = 20159; // round(2^26 / q)
+
+    // Montgomery reduction: For `|a| < q * 2^15`, returns `t = a * 2^-16 mod q` with `|t| < q`
+    #[allow(clippy::cast_possible_truncation)]
+    fn montgomery_reduce(a: i32) -> i16 {
+        let t = (a as i16).wrapping_mul(Self::QINV);
+        ((a - i32::from(t) * i32::from(Self::Q16)) >> 16) as i16
+    }
+
+    // Montgomery multiplication: Returns `a * b * 2^-16 mod q` with `|t| < q`, provided that
+    // `|a * b| < q * 2^15`
+    fn fqmul(a: i16, b: i16) -> i16 {
+        Self::montgomery_reduce(i32::from(a) * i32::from(b))
+    }
 
-        let b1g = u32::from(Self::barrett_reduce(b1 * g));
+    // Signed Barrett reduction: For any `a`, returns the representative of `a mod q` in the
+    // range `-(q-1)/2..=(q-1)/2`
+    #[allow(clippy::cast_possible_truncation)]
+    fn barrett_reduce_signed(a: i16) -> i16 {
+        let a = i32::from(a);
+        let t = (Self::BARRETT_V * a + (1 << 25)) >> 26;
+        (a - t * i32::from(Self::Q16)) as i16
+    }
+
+    // Convert a signed representative with `|x| < q` to the canonical one
+    #[allow(clippy::cast_sign_loss)]
+    fn from_signed(x: i16) -> Self {
+        Self((x + ((x >> 15) & Self::Q16)) as u16)
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    fn to_montgomery(self) -> i16 {
+        Self::fqmul(self.0 as i16, Self::MONT_R2)
+    }
 
-        let c0 = Self::barrett_reduce(a0 * b0 + a1 * b1g);
-        let c1 = Self::barrett_reduce(a0 * b1 + a1 * b0);
-        (Self(c0), Self(c1))
+    fn from_montgomery(x: i16) -> Self {
+        Self::from_signed(Self::montgomery_reduce(i32::from(x)))
+    }
+
+    // Algorithm 11. BaseCaseMultiply
+    //
+    // This is a hot loop.  The inputs and outputs are in Montgomery form.  We accumulate the
+    // products in 32 bits, so that each output only requires one Montgomery reduction.
+    fn base_case_multiply(a0: i16, a1: i16, b0: i16, b1: i16, i: usize) -> (i16, i16) {
+        let a1b1g = i32::from(Self::fqmul(a1, b1)) * i32::from(GAMMA_MONT[i]);
+
+        let a0 = i32::from(a0);
+        let a1 = i32::from(a1);
+        let b0 = i32::from(b0);
+        let b1 = i32::from(b1);
+
+        let c0 = Self::montgomery_reduce(a0 * b0 + a1b1g);
+        let c1 = Self::montgomery_reduce(a0 * b1 + a1 * b0);
+        (c0, c1)
     }
 }
 
@@ -85,19 +143,51 @@ impl Mul<FieldElement> for FieldElement {
 
 /// An element of the ring `R_q`, i.e., a polynomial over `Z_q` of degree 255
 #[derive(Clone, Copy, Default, Debug, PartialEq)]
-pub struct Polynomial(pub Array<FieldElement, U256>);
+pub struct Polynomial(pub(crate) Array<FieldElement, U256>);
+
+impl Polynomial {
+    /// Create a polynomial from its coefficients, lowest degree first
+    #[must_use]
+    #[allow(clippy::large_types_passed_by_value)]
+    pub const fn new(coefficients: Array<FieldElement, U256>) -> Self {
+        Self(coefficients)
+    }
+
+    /// The coefficients of this polynomial, lowest degree first
+    #[must_use]
+    pub fn coefficients(&self) -> &Array<FieldElement, U256> {
+        &self.0
+    }
+
+    /// Mutable access to the coefficients of this polynomial, lowest degree first
+    pub fn coefficients_mut(&mut self) -> &mut Array<FieldElement, U256> {
+        &mut self.0
+    }
+}
+
+impl AddAssign<&Polynomial> for Polynomial {
+    fn add_assign(&mut self, rhs: &Polynomial) {
+        for (x, &y) in self.0.iter_mut().zip(rhs.0.iter()) {
+            *x = *x + y;
+        }
+    }
+}
+
+impl SubAssign<&Polynomial> for Polynomial {
+    fn sub_assign(&mut self, rhs: &Polynomial) {
+        for (x, &y) in self.0.iter_mut().zip(rhs.0.iter()) {
+            *x = *x - y;
+        }
+    }
+}
 
 impl Add<&Polynomial> for &Polynomial {
     type Output = Polynomial;
 
     fn add(self, rhs: &Polynomial) -> Polynomial {
-        Polynomial(
-            self.0
-                .iter()
-                .zip(rhs.0.iter())
-                .map(|(&x, &y)| x + y)
-                .collect(),
-        )
+        let mut out = *self;
+        out += rhs;
+        out
     }
 }
 
@@ -105,13 +195,9 @@ impl Sub<&Polynomial> for &Polynomial {
     type Output = Polynomial;
 
     fn sub(self, rhs: &Polynomial) -> Polynomial {
-        Polynomial(
-            self.0
-                .iter()
-                .zip(rhs.0.iter())
-                .map(|(&x, &y)| x - y)
-                .collect(),
-        )
+        let mut out = *self;
+        out -= rhs;
+        out
     }
 }
 
@@ -124,11 +210,13 @@ impl Mul<&Polynomial> for FieldElement {
 }
 
 impl Polynomial {
-    // Algorithm 7. SamplePolyCBD_eta(B)
+    /// Sample a polynomial from the centered binomial distribution with parameter `Eta`, using
+    /// the output of `PRF` (Algorithm 7. `SamplePolyCBD_eta(B)`)
     //
     // To avoid all the bitwise manipulation in the algorithm as written, we reuse the logic in
     // ByteDecode.  We decode the PRF output into integers with eta bits, then use
     // `count_ones` to perform the summation described in the algorithm.
+    #[must_use]
     pub fn sample_cbd<Eta>(B: &PrfOutput<Eta>) -> Self
     where
         Eta: CbdSamplingSize,
@@ -140,23 +228,56 @@ impl Polynomial {
 
 /// A vector of polynomials of length `k`
 #[derive(Clone, Default, Debug, PartialEq)]
-pub struct PolynomialVector<K: ArraySize>(pub Array<Polynomial, K>);
+pub struct PolynomialVector<K: ArraySize>(pub(crate) Array<Polynomial, K>);
+
+impl<K: ArraySize> PolynomialVector<K> {
+    /// Create a vector from its entries
+    #[must_use]
+    pub const fn new(polynomials: Array<Polynomial, K>) -> Self {
+        Self(polynomials)
+    }
+
+    /// The entries of this vector
+    #[must_use]
+    pub fn polynomials(&self) -> &Array<Polynomial, K> {
+        &self.0
+    }
+
+    /// Mutable access to the entries of this vector
+    pub fn polynomials_mut(&mut self) -> &mut Array<Polynomial, K> {
+        &mut self.0
+    }
+}
+
+impl<K: ArraySize> AddAssign<&PolynomialVector<K>> for PolynomialVector<K> {
+    fn add_assign(&mut self, rhs: &PolynomialVector<K>) {
+        for (x, y) in self.0.iter_mut().zip(rhs.0.iter()) {
+            *x += y;
+        }
+    }
+}
+
+impl<K: ArraySize> SubAssign<&PolynomialVector<K>> for PolynomialVector<K> {
+    fn sub_assign(&mut self, rhs: &PolynomialVector<K>) {
+        for (x, y) in self.0.iter_mut().zip(rhs.0.iter()) {
+            *x -= y;
+        }
+    }
+}
 
 impl<K: ArraySize> Add<PolynomialVector<K>> for PolynomialVector<K> {
     type Output = PolynomialVector<K>;
 
-    fn add(self, rhs: PolynomialVector<K>) -> PolynomialVector<K> {
-        PolynomialVector(
-            self.0
-                .iter()
-                .zip(rhs.0.iter())
-                .map(|(x, y)| x + y)
-                .collect(),
-        )
+    fn add(mut self, rhs: PolynomialVector<K>) -> PolynomialVector<K> {
+        self += &rhs;
+        self
     }
 }
 
 impl<K: ArraySize> PolynomialVector<K> {
+    /// Sample each entry of the vector from the centered binomial distribution with parameter
+    /// `Eta`, with entry `i` using `PRF(sigma, start_n + i)`
+    #[must_use]
     pub fn sample_cbd<Eta>(sigma: &B32, start_n: u8) -> Self
     where
         Eta: CbdSamplingSize,
@@ -170,20 +291,58 @@ impl<K: ArraySize> PolynomialVector<K> {
 }
 
 /// An element of the ring `T_q`, i.e., a tuple of 128 elements of the direct sum components of `T_q`.
-#[derive(Clone, Default, Debug, PartialEq)]
-pub struct NttPolynomial(pub Array<FieldElement, U256>);
+///
+/// Unlike `Polynomial`, the coefficients are kept in signed Montgomery form: The value `x` is
+/// represented by some `y = x * 2^16 mod q` with `|y| < q`.  This allows the NTT and
+/// multiplication to skip most modular reductions.  Values are converted back to canonical form
+/// when they leave the NTT domain, i.e., on `ntt_inverse` and on encoding.
+///
+/// To construct an `NttPolynomial` from canonical coefficients, or to recover them, use the
+/// `From` conversions to and from `Array<FieldElement, U256>`.
+#[derive(Clone, Default, Debug)]
+pub struct NttPolynomial(pub(crate) Array<i16, U256>);
+
+impl NttPolynomial {
+    /// The canonical values of the coefficients of this polynomial
+    #[must_use]
+    pub fn coefficients(&self) -> Array<FieldElement, U256> {
+        self.into()
+    }
+}
+
+// Two representatives are equal if they represent the same value mod q
+impl PartialEq for NttPolynomial {
+    fn eq(&self, other: &Self) -> bool {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .all(|(&x, &y)| (i32::from(x) - i32::from(y)) % i32::from(FieldElement::Q16) == 0)
+    }
+}
+
+impl AddAssign<&NttPolynomial> for NttPolynomial {
+    fn add_assign(&mut self, rhs: &NttPolynomial) {
+        for (x, &y) in self.0.iter_mut().zip(rhs.0.iter()) {
+            *x = FieldElement::barrett_reduce_signed(*x + y);
+        }
+    }
+}
+
+impl SubAssign<&NttPolynomial> for NttPolynomial {
+    fn sub_assign(&mut self, rhs: &NttPolynomial) {
+        for (x, &y) in self.0.iter_mut().zip(rhs.0.iter()) {
+            *x = FieldElement::barrett_reduce_signed(*x - y);
+        }
+    }
+}
 
 impl Add<&NttPolynomial> for &NttPolynomial {
     type Output = NttPolynomial;
 
     fn add(self, rhs: &NttPolynomial) -> NttPolynomial {
-        NttPolynomial(
-            self.0
-                .iter()
-                .zip(rhs.0.iter())
-                .map(|(&x, &y)| x + y)
-                .collect(),
-        )
+        let mut out = self.clone();
+        out += rhs;
+        out
     }
 }
 
@@ -244,10 +403,11 @@ impl<'a> FieldElementReader<'a> {
 }
 
 impl NttPolynomial {
-    // Algorithm 6 SampleNTT(B)
+    /// Sample a uniformly random NTT-domain polynomial from the output of `XOF` (Algorithm 6.
+    /// `SampleNTT(B)`)
     pub fn sample_uniform(B: &mut impl XofReader) -> Self {
         let mut reader = FieldElementReader::new(B);
-        Self(Array::from_fn(|_| reader.next()))
+        Self(Array::from_fn(|_| reader.next().to_montgomery()))
     }
 }
 
@@ -307,12 +467,33 @@ const GAMMA: [FieldElement; 128] = {
     gamma
 };
 
+// The same tables in signed Montgomery form, i.e., `x * 2^16 mod q` centered on zero, for use in
+// NTT-domain arithmetic
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+const fn to_montgomery_table(table: &[FieldElement; 128]) -> [i16; 128] {
+    let mut out = [0i16; 128];
+    let mut i = 0;
+    while i < 128 {
+        let x = ((table[i].0 as u32) << 16) % FieldElement::Q32;
+        out[i] = if x > FieldElement::Q32 / 2 {
+            x as i16 - FieldElement::Q16
+        } else {
+            x as i16
+        };
+        i += 1;
+    }
+    out
+}
+
+const ZETA_POW_BITREV_MONT: [i16; 128] = to_montgomery_table(&ZETA_POW_BITREV);
+const GAMMA_MONT: [i16; 128] = to_montgomery_table(&GAMMA);
+
 // Algorithm 10. MuliplyNTTs
 impl Mul<&NttPolynomial> for &NttPolynomial {
     type Output = NttPolynomial;
 
     fn mul(self, rhs: &NttPolynomial) -> NttPolynomial {
-        let mut out = NttPolynomial(Array::default());
+        let mut out = NttPolynomial::default();
 
         for i in 0..128 {
             let (c0, c1) = FieldElement::base_case_multiply(
@@ -331,73 +512,148 @@ impl Mul<&NttPolynomial> for &NttPolynomial {
     }
 }
 
+// Conversion from canonical coefficients into Montgomery form
 impl From<Array<FieldElement, U256>> for NttPolynomial {
     fn from(f: Array<FieldElement, U256>) -> NttPolynomial {
-        NttPolynomial(f)
+        NttPolynomial(f.iter().map(|x| x.to_montgomery()).collect())
+    }
+}
+
+// Conversion from Montgomery form back to canonical coefficients
+impl From<&NttPolynomial> for Array<FieldElement, U256> {
+    fn from(f_hat: &NttPolynomial) -> Array<FieldElement, U256> {
+        f_hat
+            .0
+            .iter()
+            .map(|&x| FieldElement::from_montgomery(x))
+            .collect()
     }
 }
 
 impl From<NttPolynomial> for Array<FieldElement, U256> {
     fn from(f_hat: NttPolynomial) -> Array<FieldElement, U256> {
-        f_hat.0
+        (&f_hat).into()
     }
 }
 
 // Algorithm 8. NTT
+//
+// The butterflies are not reduced.  Starting from canonical coefficients, each layer increases the
+// bound on `|f[j]|` by q, so after seven layers `|f[j]| < 8q < 2^15`.  The zetas are in Montgomery
+// form, so the butterflies leave the result in normal form, and a final multiplication by
+// `2^32 mod q` both reduces it and moves it into Montgomery form.
 impl Polynomial {
+    /// Compute the NTT representation of this polynomial
+    #[must_use]
     pub fn ntt(&self) -> NttPolynomial {
-        let mut k = 1;
+        let mut f_hat = NttPolynomial::default();
+        self.ntt_into(&mut f_hat);
+        f_hat
+    }
+
+    /// Compute the NTT representation of this polynomial directly in the buffer `out`, without any
+    /// temporaries.  `self` is left unchanged.
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn ntt_into(&self, out: &mut NttPolynomial) {
+        let f = &mut out.0;
+        for (y, x) in f.iter_mut().zip(self.0.iter()) {
+            *y = x.0 as i16;
+        }
 
-        let mut f = self.0;
+        let mut k = 1;
         for len in [128, 64, 32, 16, 8, 4, 2] {
             for start in (0..256).step_by(2 * len) {
-                let zeta = ZETA_POW_BITREV[k];
+                let zeta = ZETA_POW_BITREV_MONT[k];
                 k += 1;
 
                 for j in start..(start + len) {
-                    let t = zeta * f[j + len];
+                    let t = FieldElement::fqmul(zeta, f[j + len]);
                     f[j + len] = f[j] - t;
-                    f[j] = f[j] + t;
+                    f[j] += t;
                 }
             }
         }
 
-        f.into()
+        for x in f.iter_mut() {
+            *x = FieldElement::fqmul(*x, FieldElement::MONT_R2);
+        }
     }
 }
 
 // Algorithm 9. NTT^{-1}
+//
+// Each layer reduces the sums with a Barrett reduction and the differences with the Montgomery
+// multiplication by zeta, so that `|f[j]| < q` throughout.  The final multiplication by 128^-1
+// also takes the result out of Montgomery form.
 impl NttPolynomial {
+    /// Compute the polynomial represented by this NTT-domain polynomial
+    #[must_use]
     pub fn ntt_inverse(&self) -> Polynomial {
-        let mut f: Array<FieldElement, U256> = self.0.clone();
+        let mut f = self.clone();
+        let mut out = Polynomial::default();
+        f.ntt_inverse_into(&mut out);
+        out
+    }
+
+    /// Compute the inverse NTT of this polynomial into the buffer `out`, writing the canonical
+    /// coefficients of the result.
+    ///
+    /// **This overwrites `self`**, which is used as scratch space and holds no meaningful value
+    /// afterwards.  Use [`NttPolynomial::ntt_inverse`] to keep `self`.
+    pub fn ntt_inverse_into(&mut self, out: &mut Polynomial) {
+        let f = &mut self.0;
 
         let mut k = 127;
         for len in [2, 4, 8, 16, 32, 64, 128] {
             for start in (0..256).step_by(2 * len) {
-                let zeta = ZETA_POW_BITREV[k];
+                let zeta = ZETA_POW_BITREV_MONT[k];
                 k -= 1;
 
                 for j in start..(start + len) {
                     let t = f[j];
-                    f[j] = t + f[j + len];
-                    f[j + len] = zeta * (f[j + len] - t);
+                    f[j] = FieldElement::barrett_reduce_signed(t + f[j + len]);
+                    f[j + len] = FieldElement::fqmul(zeta, f[j + len] - t);
                 }
             }
         }
 
-        FieldElement(3303) * &Polynomial(f)
+        for (y, &x) in out.0.iter_mut().zip(f.iter()) {
+            *y = FieldElement::from_signed(FieldElement::fqmul(x, FieldElement::INV_128));
+        }
     }
 }
 
 /// A vector of K NTT-domain polynomials
 #[derive(Clone, Default, Debug, PartialEq)]
-pub struct NttVector<K: ArraySize>(pub Array<NttPolynomial, K>);
+pub struct NttVector<K: ArraySize>(pub(crate) Array<NttPolynomial, K>);
 
 impl<K: ArraySize> NttVector<K> {
+    /// Create a vector from its entries
+    #[must_use]
+    pub const fn new(polynomials: Array<NttPolynomial, K>) -> Self {
+        Self(polynomials)
+    }
+
+    /// The entries of this vector
+    #[must_use]
+    pub fn polynomials(&self) -> &Array<NttPolynomial, K> {
+        &self.0
+    }
+
+    /// Mutable access to the entries of this vector
+    pub fn polynomials_mut(&mut self) -> &mut Array<NttPolynomial, K> {
+        &mut self.0
+    }
+
+    /// Sample row `i` of the matrix `A_hat` generated from the seed `rho`, or row `i` of its
+    /// transpose if `transpose` is set.  Entry `j` is sampled from `XOF(rho, j, i)`, or
+    /// `XOF(rho, i, j)` for the transpose.
+    //
     // Note the transpose here: Apparently the specification is incorrect, and the proper order
     // of indices is reversed.
     //
     // https://github.com/FiloSottile/mlkem768/blob/main/mlkem768.go#L110C4-L112C51
+    #[must_use]
     pub fn sample_uniform(rho: &B32, i: usize, transpose: bool) -> Self {
         Self(Array::from_fn(|j| {
             let (i, j) = if transpose { (i, j) } else { (j, i) };
@@ -407,48 +663,130 @@ impl<K: ArraySize> NttVector<K> {
     }
 }
 
+impl<K: ArraySize> AddAssign<&NttVector<K>> for NttVector<K> {
+    fn add_assign(&mut self, rhs: &NttVector<K>) {
+        for (x, y) in self.0.iter_mut().zip(rhs.0.iter()) {
+            *x += y;
+        }
+    }
+}
+
+impl<K: ArraySize> SubAssign<&NttVector<K>> for NttVector<K> {
+    fn sub_assign(&mut self, rhs: &NttVector<K>) {
+        for (x, y) in self.0.iter_mut().zip(rhs.0.iter()) {
+            *x -= y;
+        }
+    }
+}
+
 impl<K: ArraySize> Add<&NttVector<K>> for &NttVector<K> {
     type Output = NttVector<K>;
 
     fn add(self, rhs: &NttVector<K>) -> NttVector<K> {
-        NttVector(
-            self.0
-                .iter()
-                .zip(rhs.0.iter())
-                .map(|(x, y)| x + y)
-                .collect(),
-        )
+        let mut out = self.clone();
+        out += rhs;
+        out
     }
 }
 
+// The inner product sums the unreduced base case products of up to four terms in 32 bits, and
+// reduces once per output coefficient rather than once per term.  Since all inputs have `|x| < q`,
+// the largest sum is `2 * 4 * q^2 < q * 2^15`, within the range of the Montgomery reduction.  For
+// K <= 4, which covers all the ML-KEM parameter sets, this is the whole product.  Larger vectors
+// are split into runs of four terms, whose reduced products are added.
+//
+// The matrix-vector product below is a sequence of these inner products, so it benefits as well.
 impl<K: ArraySize> Mul<&NttVector<K>> for &NttVector<K> {
     type Output = NttPolynomial;
 
+    #[allow(clippy::suspicious_arithmetic_impl)] // An inner product is a sum of products
     fn mul(self, rhs: &NttVector<K>) -> NttPolynomial {
-        self.0
-            .iter()
-            .zip(rhs.0.iter())
-            .map(|(x, y)| x * y)
-            .fold(NttPolynomial::default(), |x, y| &x + &y)
+        let mut runs = self.0.chunks(LAZY_TERMS).zip(rhs.0.chunks(LAZY_TERMS));
+        let Some((x, y)) = runs.next() else {
+            return NttPolynomial::default();
+        };
+
+        let mut out = lazy_inner_product(x, y);
+        for (x, y) in runs {
+            out += &lazy_inner_product(x, y);
+        }
+        out
     }
 }
 
+// The number of terms whose products can be summed before a reduction is needed
+const LAZY_TERMS: usize = 4;
+
+fn lazy_inner_product(lhs: &[NttPolynomial], rhs: &[NttPolynomial]) -> NttPolynomial {
+    debug_assert!(lhs.len() <= LAZY_TERMS && rhs.len() <= LAZY_TERMS);
+
+    let mut out = NttPolynomial::default();
+    for (i, &gamma) in GAMMA_MONT.iter().enumerate() {
+        let mut a0b0 = 0i32;
+        let mut a1b1 = 0i32;
+        let mut c1 = 0i32;
+        for (x, y) in lhs.iter().zip(rhs.iter()) {
+            let a0 = i32::from(x.0[2 * i]);
+            let a1 = i32::from(x.0[2 * i + 1]);
+            let b0 = i32::from(y.0[2 * i]);
+            let b1 = i32::from(y.0[2 * i + 1]);
+
+            a0b0 += a0 * b0;
+            a1b1 += a1 * b1;
+            c1 += a0 * b1 + a1 * b0;
+        }
+
+        let a1b1g = i32::from(FieldElement::montgomery_reduce(a1b1)) * i32::from(gamma);
+        out.0[2 * i] = FieldElement::montgomery_reduce(a0b0 + a1b1g);
+        out.0[2 * i + 1] = FieldElement::montgomery_reduce(c1);
+    }
+
+    out
+}
+
 impl<K: ArraySize> PolynomialVector<K> {
+    /// Compute the NTT representation of each entry of this vector
+    #[must_use]
     pub fn ntt(&self) -> NttVector<K> {
-        NttVector(self.0.iter().map(Polynomial::ntt).collect())
+        let mut out = NttVector::default();
+        self.ntt_into(&mut out);
+        out
+    }
+
+    /// Compute the NTT representation of each entry of this vector directly in the buffer `out`.
+    /// `self` is left unchanged.
+    pub fn ntt_into(&self, out: &mut NttVector<K>) {
+        for (x, y) in self.0.iter().zip(out.0.iter_mut()) {
+            x.ntt_into(y);
+        }
     }
 }
 
 impl<K: ArraySize> NttVector<K> {
+    /// Compute the vector of polynomials represented by this vector
+    #[must_use]
     pub fn ntt_inverse(&self) -> PolynomialVector<K> {
-        PolynomialVector(self.0.iter().map(NttPolynomial::ntt_inverse).collect())
+        let mut f = self.clone();
+        let mut out = PolynomialVector::default();
+        f.ntt_inverse_into(&mut out);
+        out
+    }
+
+    /// Compute the inverse NTT of each entry of this vector into the buffer `out`.
+    ///
+    /// **This overwrites `self`**, which is used as scratch space and holds no meaningful value
+    /// afterwards.  Use [`NttVector::ntt_inverse`] to keep `self`.
+    pub fn ntt_inverse_into(&mut self, out: &mut PolynomialVector<K>) {
+        for (x, y) in self.0.iter_mut().zip(out.0.iter_mut()) {
+            x.ntt_inverse_into(y);
+        }
     }
 }
 
 /// A K x K matrix of NTT-domain polynomials.  Each vector represents a row of the matrix, so that
 /// multiplying on the right just requires iteration.
 #[derive(Clone, Default, Debug, PartialEq)]
-pub struct NttMatrix<K: ArraySize>(Array<NttVector<K>, K>);
+pub struct NttMatrix<K: ArraySize>(pub(crate) Array<NttVector<K>, K>);
 
 impl<K: ArraySize> Mul<&NttVector<K>> for &NttMatrix<K> {
     type Output = NttVector<K>;
@@ -459,12 +797,29 @@ impl<K: ArraySize> Mul<&NttVector<K>> for &NttMatrix<K> {
 }
 
 impl<K: ArraySize> NttMatrix<K> {
+    /// Create a matrix from its rows
+    #[must_use]
+    pub const fn new(rows: Array<NttVector<K>, K>) -> Self {
+        Self(rows)
+    }
+
+    /// The rows of this matrix
+    #[must_use]
+    pub fn rows(&self) -> &Array<NttVector<K>, K> {
+        &self.0
+    }
+
+    /// Sample the matrix `A_hat` from the seed `rho` (Algorithm 12, lines 3-7), or its transpose
+    /// if `transpose` is set (Algorithm 13, lines 4-8)
+    #[must_use]
     pub fn sample_uniform(rho: &B32, transpose: bool) -> Self {
         Self(Array::from_fn(|i| {
             NttVector::sample_uniform(rho, i, transpose)
         }))
     }
 
+    /// The transpose of this matrix
+    #[must_use]
     pub fn transpose(&self) -> Self {
         Self(Array::from_fn(|i| {
             NttVector(Array::from_fn(|j| self.0[j].0[i].clone()))
@@ -476,7 +831,8 @@ impl<K: ArraySize> NttMatrix<K> {
 mod test {
     use super::*;
     use crate::util::Flatten;
-    use hybrid_array::typenum::{U2, U3, U8};
+    use hybrid_array::typenum::{U2, U3, U4, U5, U8, U9};
+    use rand::Rng;
 
     // Multiplication in R_q, modulo X^256 + 1
     impl Mul<&Polynomial> for &Polynomial {
@@ -499,6 +855,41 @@ mod test {
         }
     }
 
+    #[test]
+    fn montgomery() {
+        let q = i32::from(FieldElement::Q16);
+        let r = (1 << 16) % q;
+
+        // Conversion into and out of Montgomery form round-trips, with the expected values
+        for x in 0..FieldElement::Q {
+            let x_mont = FieldElement(x).to_montgomery();
+            assert!(i32::from(x_mont).abs() < q);
+            assert_eq!((i32::from(x_mont) - i32::from(x) * r).rem_euclid(q), 0);
+            assert_eq!(FieldElement::from_montgomery(x_mont), FieldElement(x));
+        }
+
+        // Barrett reduction is correct and centered over the whole input range
+        for a in i16::MIN..=i16::MAX {
+            let t = FieldElement::barrett_reduce_signed(a);
+            assert!(i32::from(t).abs() <= (q - 1) / 2);
+            assert_eq!((i32::from(a) - i32::from(t)).rem_euclid(q), 0);
+        }
+
+        // Montgomery reduction is correct and bounded at the edges of its input range
+        for a in [
+            -q * (1 << 15),
+            -q * (1 << 15) + 1,
+            -1,
+            0,
+            1,
+            q * (1 << 15) - 1,
+        ] {
+            let t = FieldElement::montgomery_reduce(a);
+            assert!(i32::from(t).abs() < q);
+            assert_eq!((i32::from(t) * (1 << 16) - a).rem_euclid(q), 0);
+        }
+    }
+
     // A polynomial with only a scalar component, to make simple test cases
     fn const_ntt(x: Integer) -> NttPolynomial {
         let mut p = Polynomial::default();
@@ -507,6 +898,7 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::cast_possible_truncation)]
     fn polynomial_ops() {
         let f = Polynomial(Array::from_fn(|i| FieldElement(i as Integer)));
         let g = Polynomial(Array::from_fn(|i| FieldElement(2 * i as Integer)));
@@ -514,9 +906,25 @@ mod test {
         assert_eq!((&f + &g), sum);
         assert_eq!((&sum - &g), f);
         assert_eq!(FieldElement(3) * &f, sum);
+
+        let mut h = f;
+        h += &g;
+        assert_eq!(h, sum);
+        h -= &g;
+        assert_eq!(h, f);
+
+        // The same in the NTT domain
+        let f_hat = f.ntt();
+        let g_hat = g.ntt();
+        let mut h_hat = f_hat.clone();
+        h_hat += &g_hat;
+        assert_eq!(h_hat, sum.ntt());
+        h_hat -= &g_hat;
+        assert_eq!(h_hat, f_hat);
     }
 
     #[test]
+    #[allow(clippy::cast_possible_truncation, clippy::similar_names)]
     fn ntt() {
         let f = Polynomial(Array::from_fn(|i| FieldElement(i as Integer)));
         let g = Polynomial(Array::from_fn(|i| FieldElement(2 * i as Integer)));
@@ -527,6 +935,15 @@ mod test {
         let f_unhat = f_hat.ntt_inverse();
         assert_eq!(f, f_unhat);
 
+        // Verify that the variants writing into a buffer agree
+        let mut f_hat_into = NttPolynomial::default();
+        f.ntt_into(&mut f_hat_into);
+        assert_eq!(f_hat_into, f_hat);
+
+        let mut f_unhat_into = Polynomial::default();
+        f_hat_into.ntt_inverse_into(&mut f_unhat_into);
+        assert_eq!(f_unhat_into, f);
+
         // Verify that NTT is a homomorphism with regard to addition
         let fg = &f + &g;
         let f_hat_g_hat = &f_hat + &g_hat;
@@ -548,12 +965,50 @@ mod test {
         let v3: NttVector<U3> = NttVector(Array([const_ntt(3), const_ntt(3), const_ntt(3)]));
         assert_eq!((&v1 + &v2), v3);
 
+        let mut v = v1.clone();
+        v += &v2;
+        assert_eq!(v, v3);
+        v -= &v2;
+        assert_eq!(v, v1);
+
         // Verify dot product
         assert_eq!((&v1 * &v2), const_ntt(6));
         assert_eq!((&v1 * &v3), const_ntt(9));
         assert_eq!((&v2 * &v3), const_ntt(18));
     }
 
+    fn inner_product_test<K: ArraySize>() {
+        // The lazy inner product agrees with a sum of fully reduced products, including for
+        // inputs at the edges of the allowed range
+        let mut rng = rand::thread_rng();
+        let edge = FieldElement::Q16 - 1;
+        let v1: NttVector<K> = NttVector(Array::from_fn(|_| {
+            NttPolynomial(Array::from_fn(|i| if i % 3 == 0 { -edge } else { edge }))
+        }));
+        let v2: NttVector<K> = NttVector(Array::from_fn(|_| {
+            NttPolynomial(Array::from_fn(|_| rng.gen_range(-edge..=edge)))
+        }));
+
+        let expected =
+            v1.0.iter()
+                .zip(v2.0.iter())
+                .map(|(x, y)| x * y)
+                .fold(NttPolynomial::default(), |x, y| &x + &y);
+        let actual = &v1 * &v2;
+        assert_eq!(actual, expected);
+        assert!(actual.0.iter().all(|x| x.abs() < FieldElement::Q16));
+    }
+
+    #[test]
+    fn inner_product() {
+        inner_product_test::<U2>();
+        inner_product_test::<U4>();
+
+        // Vectors longer than the lazy run are reduced after every four terms
+        inner_product_test::<U5>();
+        inner_product_test::<U9>();
+    }
+
     #[test]
     fn ntt_matrix() {
         // Verify matrix multiplication by a vector
@@ -609,6 +1064,7 @@ mod test {
     // for k in $-\eta, \ldots, \eta$.  The cases of interest here are \eta = 2, 3.
     type Distribution = [f64; Q_SIZE];
     const Q_SIZE: usize = FieldElement::Q as usize;
+    #[allow(clippy::large_const_arrays)]
     const CBD2: Distribution = {
         let mut dist = [0.0; Q_SIZE];
         dist[Q_SIZE - 2] = 1.0 / 16.0;
@@ -618,6 +1074,7 @@ mod test {
         dist[2] = 1.0 / 16.0;
         dist
     };
+    #[allow(clippy::large_const_arrays)]
     const CBD3: Distribution = {
         let mut dist = [0.0; Q_SIZE];
         dist[Q_SIZE - 3] = 1.0 / 64.0;
@@ -629,6 +1086,7 @@ mod test {
         dist[3] = 1.0 / 64.0;
         dist
     };
+    #[allow(clippy::large_const_arrays)]
     const UNIFORM: Distribution = [1.0 / (FieldElement::Q as f64); Q_SIZE];
 
     fn kl_divergence(p: &Distribution, q: &Distribution) -> f64 {
@@ -638,6 +1096,7 @@ mod test {
             .sum()
     }
 
+    #[allow(clippy::large_stack_arrays, clippy::cast_precision_loss)]
     fn test_sample(sample: &[FieldElement], ref_dist: &Distribution) {
         // Verify data and compute the empirical distribution
         let mut sample_dist: Distribution = [0.0; Q_SIZE];
@@ -654,6 +1113,7 @@ mod test {
     }
 
     #[test]
+    #[allow(clippy::cast_possible_truncation)]
     fn sample_uniform() {
         // We require roughly Q/2 samples to verify the uniform distribution.  This is because for
         // M < N, the uniform distribution over a subset of M elements has KL distance:
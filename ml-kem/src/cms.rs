@@ -0,0 +1,479 @@
+//! The algorithm choices for ML-KEM follow draft-ietf-lamps-cms-kyber.
+//!
+//! A sender wraps the content-encryption key (CEK) of an `EnvelopedData` for each recipient.
+//! For an ML-KEM recipient, the sender encapsulates a shared key, derives a key-encryption key
+//! (KEK) from it with HKDF-SHA256, and wraps the CEK with AES key wrap.  The result is carried in
+//! an `OtherRecipientInfo` of type [`ID_ORI_KEM`].
+//!
+//! ```
+//! # use ml_kem::{cms, KemCore, MlKem768};
+//! # use ::cms::enveloped_data::RecipientIdentifier;
+//! # use der::asn1::OctetString;
+//! let mut rng = rand::thread_rng();
+//! let (dk, ek) = MlKem768::generate(&mut rng);
+//! let rid = RecipientIdentifier::SubjectKeyIdentifier(OctetString::new([1; 20]).unwrap().into());
+//!
+//! let cek = [0x42; 32];
+//! let ri = cms::wrap(&ek, rid, &cek, None, &mut rng).unwrap();
+//! let ori = ri.to_ori().unwrap();
+//!
+//! let ri = cms::KemRecipientInfo::try_from(&ori).unwrap();
+//! assert_eq!(cms::unwrap(&dk, &ri).unwrap(), cek);
+//! ```
+//!
+//! # Algorithms
+//!
+//! | KEM         | KDF         | Key wrap    | `kekLength` |
+//! |-------------|-------------|-------------|-------------|
+//! | ML-KEM-512  | HKDF-SHA256 | AES-128-KW  | 16          |
+//! | ML-KEM-768  | HKDF-SHA256 | AES-256-KW  | 32          |
+//! | ML-KEM-1024 | HKDF-SHA256 | AES-256-KW  | 32          |
+//!
+//! The KEK is derived as
+//!
+//! ```text
+//! KEK = HKDF-SHA256(salt = absent, ikm = ss, info = DER(CMSORIforKEMOtherInfo), L = kekLength)
+//! ```
+//!
+//! where `CMSORIforKEMOtherInfo` binds the key-wrap algorithm, `kekLength`, and the optional user
+//! keying material.  When unwrapping, either key-wrap algorithm is accepted, as long as
+//! `kekLength` matches its key size.
+//!
+//! [`ID_ORI_KEM`]: crate::cms::ID_ORI_KEM
+
+use ::cms::content_info::CmsVersion;
+use ::cms::enveloped_data::{
+    EncryptedKey, OtherRecipientInfo, RecipientIdentifier, UserKeyingMaterial,
+};
+use aes_kw::{KekAes128, KekAes256};
+use alloc::vec::Vec;
+use core::fmt;
+use der::{asn1::OctetString, oid::ObjectIdentifier, Any, Encode, Sequence};
+use hkdf::Hkdf;
+use rand_core::CryptoRngCore;
+use sha2::Sha256;
+use spki::AlgorithmIdentifierOwned;
+
+use crate::kem::{decapsulate, encapsulate, DecapsulationKey, EncapsulationKey, SharedKey};
+use crate::param::{EncodedCiphertext, KemParams, StandardParams};
+
+/// `id-ori-kem`, the `oriType` of an `OtherRecipientInfo` holding a [`KemRecipientInfo`]
+pub const ID_ORI_KEM: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.16.13.3");
+
+/// `id-alg-ml-kem-512`
+pub const ID_ALG_ML_KEM_512: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.4.1");
+
+/// `id-alg-ml-kem-768`
+pub const ID_ALG_ML_KEM_768: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.4.2");
+
+/// `id-alg-ml-kem-1024`
+pub const ID_ALG_ML_KEM_1024: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.4.3");
+
+/// `id-alg-hkdf-with-sha256`, from RFC 8619
+pub const ID_ALG_HKDF_WITH_SHA256: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.16.3.28");
+
+/// `id-aes128-wrap`, from RFC 3394
+pub const ID_AES128_WRAP: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.1.5");
+
+/// `id-aes256-wrap`, from RFC 3394
+pub const ID_AES256_WRAP: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.1.45");
+
+/// The `KEMRecipientInfo` type from RFC 9629.
+///
+/// ```text
+/// KEMRecipientInfo ::= SEQUENCE {
+///   version CMSVersion,  -- always set to 0
+///   rid RecipientIdentifier,
+///   kem KEMAlgorithmIdentifier,
+///   kemct OCTET STRING,
+///   kdf KeyDerivationAlgorithmIdentifier,
+///   kekLength INTEGER (1..65535),
+///   ukm [0] EXPLICIT UserKeyingMaterial OPTIONAL,
+///   wrap KeyEncryptionAlgorithmIdentifier,
+///   encryptedKey EncryptedKey }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+#[allow(missing_docs)]
+pub struct KemRecipientInfo {
+    pub version: CmsVersion,
+    pub rid: RecipientIdentifier,
+    pub kem: AlgorithmIdentifierOwned,
+    pub kem_ct: OctetString,
+    pub kdf: AlgorithmIdentifierOwned,
+    pub kek_length: u16,
+    #[asn1(context_specific = "0", tag_mode = "EXPLICIT", optional = "true")]
+    pub ukm: Option<UserKeyingMaterial>,
+    pub wrap: AlgorithmIdentifierOwned,
+    pub encrypted_key: EncryptedKey,
+}
+
+impl KemRecipientInfo {
+    /// Wrap this `KEMRecipientInfo` in an `OtherRecipientInfo`, for inclusion in the
+    /// `recipientInfos` of an `EnvelopedData` or `AuthEnvelopedData`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Der`] if the structure cannot be encoded.
+    pub fn to_ori(&self) -> Result<OtherRecipientInfo, Error> {
+        Ok(OtherRecipientInfo {
+            ori_type: ID_ORI_KEM,
+            ori_value: Any::encode_from(self)?,
+        })
+    }
+}
+
+impl TryFrom<&OtherRecipientInfo> for KemRecipientInfo {
+    type Error = Error;
+
+    fn try_from(ori: &OtherRecipientInfo) -> Result<Self, Error> {
+        if ori.ori_type != ID_ORI_KEM {
+            return Err(Error::NotKemRecipientInfo);
+        }
+        Ok(ori.ori_value.decode_as()?)
+    }
+}
+
+/// The `CMSORIforKEMOtherInfo` type from RFC 9629, which is the `info` input to the KDF.
+///
+/// ```text
+/// CMSORIforKEMOtherInfo ::= SEQUENCE {
+///   wrap KeyEncryptionAlgorithmIdentifier,
+///   kekLength INTEGER (1..65535),
+///   ukm [0] EXPLICIT UserKeyingMaterial OPTIONAL }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct CmsOriForKemOtherInfo {
+    wrap: AlgorithmIdentifierOwned,
+    kek_length: u16,
+    #[asn1(context_specific = "0", tag_mode = "EXPLICIT", optional = "true")]
+    ukm: Option<UserKeyingMaterial>,
+}
+
+/// An error in wrapping or unwrapping a content-encryption key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A structure could not be encoded or decoded
+    Der(der::Error),
+
+    /// The `OtherRecipientInfo` is not of type [`ID_ORI_KEM`]
+    NotKemRecipientInfo,
+
+    /// The `KEMRecipientInfo` has a version other than 0
+    UnsupportedVersion,
+
+    /// The `KEMRecipientInfo` is for a different KEM or parameter set, or the KEM ciphertext has
+    /// the wrong length.  When wrapping, the KEM is not one of the standard ML-KEM parameter sets.
+    WrongKem,
+
+    /// The KDF is not HKDF-SHA256
+    UnsupportedKdf,
+
+    /// The key-wrap algorithm is not AES key wrap, or its key size does not match `kekLength`
+    UnsupportedWrap,
+
+    /// The content-encryption key could not be wrapped, because its length is not a multiple of
+    /// 8 bytes of at least 16 bytes, or could not be unwrapped, because the ciphertext or the
+    /// recipient's key is wrong
+    KeyWrap,
+}
+
+impl From<der::Error> for Error {
+    fn from(err: der::Error) -> Self {
+        Self::Der(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Der(err) => write!(f, "DER error: {err}"),
+            Self::NotKemRecipientInfo => f.write_str("not a KEMRecipientInfo"),
+            Self::UnsupportedVersion => f.write_str("unsupported KEMRecipientInfo version"),
+            Self::WrongKem => f.write_str("KEMRecipientInfo is for a different KEM"),
+            Self::UnsupportedKdf => f.write_str("unsupported KDF"),
+            Self::UnsupportedWrap => f.write_str("unsupported key-wrap algorithm"),
+            Self::KeyWrap => f.write_str("AES key wrap failed"),
+        }
+    }
+}
+
+/// Wrap the content-encryption key `cek` for the holder of the decapsulation key corresponding
+/// to `ek`, identified by `rid`.  The optional user keying material `ukm` is included in the KDF
+/// input, and carried in the `KEMRecipientInfo`.
+///
+/// # Errors
+///
+/// Returns [`Error::WrongKem`] if `P` is not one of the standard ML-KEM parameter sets,
+/// [`Error::KeyWrap`] if `cek` cannot be wrapped with AES key wrap, and [`Error::Der`] if the KDF
+/// input cannot be encoded.
+pub fn wrap<P: KemParams>(
+    ek: &EncapsulationKey<P>,
+    rid: RecipientIdentifier,
+    cek: &[u8],
+    ukm: Option<UserKeyingMaterial>,
+    rng: &mut impl CryptoRngCore,
+) -> Result<KemRecipientInfo, Error> {
+    let (kem, wrap_oid, kek_length) = algorithms::<P>()?;
+    let (kem_ct, ss) = encapsulate(ek, rng);
+
+    let wrap = algorithm(wrap_oid);
+    let kek = derive_kek(&ss, &wrap, kek_length, ukm.as_ref())?;
+    let encrypted_key = match kek_length {
+        16 => KekAes128::try_from(kek.as_slice()).and_then(|kek| kek.wrap_vec(cek)),
+        _ => KekAes256::try_from(kek.as_slice()).and_then(|kek| kek.wrap_vec(cek)),
+    }
+    .map_err(|_| Error::KeyWrap)?;
+
+    Ok(KemRecipientInfo {
+        version: CmsVersion::V0,
+        rid,
+        kem: algorithm(kem),
+        kem_ct: OctetString::new(kem_ct.as_slice())?,
+        kdf: algorithm(ID_ALG_HKDF_WITH_SHA256),
+        kek_length,
+        ukm,
+        wrap,
+        encrypted_key: OctetString::new(encrypted_key)?,
+    })
+}
+
+/// Unwrap the content-encryption key from a `KEMRecipientInfo` addressed to the holder of `dk`.
+/// The caller is responsible for selecting the `KEMRecipientInfo` whose `rid` matches `dk`.
+///
+/// # Errors
+///
+/// Returns an error if the `KEMRecipientInfo` uses unsupported algorithms, or if the wrapped key
+/// does not unwrap, e.g., because it was wrapped for a different recipient.
+pub fn unwrap<P: KemParams>(
+    dk: &DecapsulationKey<P>,
+    ri: &KemRecipientInfo,
+) -> Result<Vec<u8>, Error> {
+    if ri.version != CmsVersion::V0 {
+        return Err(Error::UnsupportedVersion);
+    }
+
+    let (kem, _, _) = algorithms::<P>()?;
+    if ri.kem != algorithm(kem) {
+        return Err(Error::WrongKem);
+    }
+    if ri.kdf != algorithm(ID_ALG_HKDF_WITH_SHA256) {
+        return Err(Error::UnsupportedKdf);
+    }
+    let kek_length = match ri.wrap.oid {
+        ID_AES128_WRAP => 16,
+        ID_AES256_WRAP => 32,
+        _ => return Err(Error::UnsupportedWrap),
+    };
+    if ri.wrap.parameters.is_some() || ri.kek_length != kek_length {
+        return Err(Error::UnsupportedWrap);
+    }
+
+    let kem_ct =
+        EncodedCiphertext::<P>::try_from(ri.kem_ct.as_bytes()).map_err(|_| Error::WrongKem)?;
+    let ss = decapsulate(dk, &kem_ct);
+
+    let kek = derive_kek(&ss, &ri.wrap, kek_length, ri.ukm.as_ref())?;
+    let encrypted_key = ri.encrypted_key.as_bytes();
+    match kek_length {
+        16 => KekAes128::try_from(kek.as_slice()).and_then(|kek| kek.unwrap_vec(encrypted_key)),
+        _ => KekAes256::try_from(kek.as_slice()).and_then(|kek| kek.unwrap_vec(encrypted_key)),
+    }
+    .map_err(|_| Error::KeyWrap)
+}
+
+// The KEM, key-wrap algorithm, and KEK length for the parameter set `P`
+fn algorithms<P: KemParams>() -> Result<(ObjectIdentifier, ObjectIdentifier, u16), Error> {
+    match StandardParams::of::<P>() {
+        Some(StandardParams::MlKem512) => Ok((ID_ALG_ML_KEM_512, ID_AES128_WRAP, 16)),
+        Some(StandardParams::MlKem768) => Ok((ID_ALG_ML_KEM_768, ID_AES256_WRAP, 32)),
+        Some(StandardParams::MlKem1024) => Ok((ID_ALG_ML_KEM_1024, ID_AES256_WRAP, 32)),
+        None => Err(Error::WrongKem),
+    }
+}
+
+// An algorithm identifier with absent parameters, as used for all of the algorithms here
+fn algorithm(oid: ObjectIdentifier) -> AlgorithmIdentifierOwned {
+    AlgorithmIdentifierOwned {
+        oid,
+        parameters: None,
+    }
+}
+
+fn derive_kek(
+    ss: &SharedKey,
+    wrap: &AlgorithmIdentifierOwned,
+    kek_length: u16,
+    ukm: Option<&UserKeyingMaterial>,
+) -> Result<Vec<u8>, Error> {
+    let info = CmsOriForKemOtherInfo {
+        wrap: wrap.clone(),
+        kek_length,
+        ukm: ukm.cloned(),
+    }
+    .to_der()?;
+
+    let mut kek = alloc::vec![0u8; usize::from(kek_length)];
+    Hkdf::<Sha256>::new(None, ss)
+        .expand(&info, &mut kek)
+        .expect("KEK lengths are valid HKDF output lengths");
+    Ok(kek)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kem::Kem;
+    use crate::{KemCore, MlKem1024Params, MlKem512Params, MlKem768Params, ParameterSet};
+    use der::Decode;
+    use hex_literal::hex;
+    use hybrid_array::typenum::{Unsigned, U10, U2, U3, U4};
+
+    fn rid() -> RecipientIdentifier {
+        RecipientIdentifier::SubjectKeyIdentifier(OctetString::new([7; 20]).unwrap().into())
+    }
+
+    fn round_trip_test<P: KemParams>(kem: ObjectIdentifier, kek_length: u16) {
+        let mut rng = rand::thread_rng();
+        let (dk, ek) = Kem::<P>::generate(&mut rng);
+        let (dk2, _) = Kem::<P>::generate(&mut rng);
+        let cek = [0x42; 32];
+
+        for ukm in [
+            None,
+            Some(OctetString::new(*b"user keying material").unwrap()),
+        ] {
+            let ri = wrap(&ek, rid(), &cek, ukm.clone(), &mut rng).unwrap();
+            assert_eq!(ri.kem.oid, kem);
+            assert_eq!(ri.kek_length, kek_length);
+            assert_eq!(ri.ukm, ukm);
+            assert_eq!(ri.kem_ct.as_bytes().len(), P::CiphertextSize::USIZE);
+            assert_eq!(unwrap(&dk, &ri).unwrap(), cek);
+
+            // Through DER and `OtherRecipientInfo`
+            let ori =
+                OtherRecipientInfo::from_der(&ri.to_ori().unwrap().to_der().unwrap()).unwrap();
+            let decoded = KemRecipientInfo::try_from(&ori).unwrap();
+            assert_eq!(decoded, ri);
+
+            // The wrong recipient gets an implicitly rejected shared key, which fails to unwrap
+            assert_eq!(unwrap(&dk2, &ri), Err(Error::KeyWrap));
+
+            // The user keying material is bound by the KDF
+            let mut bad = ri.clone();
+            bad.ukm = Some(OctetString::new(*b"other").unwrap());
+            assert_eq!(unwrap(&dk, &bad), Err(Error::KeyWrap));
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        round_trip_test::<MlKem512Params>(ID_ALG_ML_KEM_512, 16);
+        round_trip_test::<MlKem768Params>(ID_ALG_ML_KEM_768, 32);
+        round_trip_test::<MlKem1024Params>(ID_ALG_ML_KEM_1024, 32);
+    }
+
+    #[test]
+    fn unsupported() {
+        let mut rng = rand::thread_rng();
+        let (dk, ek) = Kem::<MlKem768Params>::generate(&mut rng);
+        let ri = wrap(&ek, rid(), &[0x42; 16], None, &mut rng).unwrap();
+        assert!(unwrap(&dk, &ri).is_ok());
+
+        let mut bad = ri.clone();
+        bad.version = CmsVersion::V1;
+        assert_eq!(unwrap(&dk, &bad), Err(Error::UnsupportedVersion));
+
+        let mut bad = ri.clone();
+        bad.kem = algorithm(ID_ALG_ML_KEM_1024);
+        assert_eq!(unwrap(&dk, &bad), Err(Error::WrongKem));
+
+        let mut bad = ri.clone();
+        bad.kem_ct = OctetString::new(&ri.kem_ct.as_bytes()[1..]).unwrap();
+        assert_eq!(unwrap(&dk, &bad), Err(Error::WrongKem));
+
+        let mut bad = ri.clone();
+        bad.kdf = algorithm(ID_AES256_WRAP);
+        assert_eq!(unwrap(&dk, &bad), Err(Error::UnsupportedKdf));
+
+        // The key-wrap algorithm must match `kekLength`
+        let mut bad = ri.clone();
+        bad.wrap = algorithm(ID_AES128_WRAP);
+        assert_eq!(unwrap(&dk, &bad), Err(Error::UnsupportedWrap));
+        bad.kek_length = 16;
+        assert_eq!(unwrap(&dk, &bad), Err(Error::KeyWrap));
+
+        // A content-encryption key that AES key wrap cannot handle
+        assert_eq!(
+            wrap(&ek, rid(), &[0x42; 12], None, &mut rng),
+            Err(Error::KeyWrap)
+        );
+
+        let ori = OtherRecipientInfo {
+            ori_type: ID_AES256_WRAP,
+            ori_value: ri.to_ori().unwrap().ori_value,
+        };
+        assert_eq!(
+            KemRecipientInfo::try_from(&ori),
+            Err(Error::NotKemRecipientInfo)
+        );
+    }
+
+    // A parameter set that has no algorithm identifier in CMS, although it shares `k` and the
+    // ciphertext size with ML-KEM-768
+    #[derive(Default, Clone, Debug, PartialEq)]
+    struct NonstandardParams;
+
+    impl ParameterSet for NonstandardParams {
+        type K = U3;
+        type Eta1 = U3;
+        type Eta2 = U2;
+        type Du = U10;
+        type Dv = U4;
+    }
+
+    #[test]
+    fn nonstandard_parameter_set() {
+        type NonstandardKem = Kem<NonstandardParams>;
+
+        let mut rng = rand::thread_rng();
+        let (dk, ek) = NonstandardKem::generate(&mut rng);
+        assert_eq!(
+            wrap(&ek, rid(), &[0x42; 16], None, &mut rng),
+            Err(Error::WrongKem)
+        );
+
+        let (_, ek) = Kem::<MlKem1024Params>::generate(&mut rng);
+        let ri = wrap(&ek, rid(), &[0x42; 16], None, &mut rng).unwrap();
+        assert_eq!(unwrap(&dk, &ri), Err(Error::WrongKem));
+    }
+
+    #[test]
+    fn other_info_encoding() {
+        // CMSORIforKEMOtherInfo for AES-256 key wrap, with and without user keying material
+        let wrap = algorithm(ID_AES256_WRAP);
+        let info = CmsOriForKemOtherInfo {
+            wrap: wrap.clone(),
+            kek_length: 32,
+            ukm: None,
+        };
+        assert_eq!(
+            info.to_der().unwrap(),
+            hex!("3010 300b 0609 60864801650304012d 020120")
+        );
+
+        let info = CmsOriForKemOtherInfo {
+            wrap,
+            kek_length: 32,
+            ukm: Some(OctetString::new(hex!("abcd")).unwrap()),
+        };
+        assert_eq!(
+            info.to_der().unwrap(),
+            hex!("3016 300b 0609 60864801650304012d 020120 a004 0402abcd")
+        );
+    }
+}
@@ -2,9 +2,12 @@ use crate::algebra::{FieldElement, Integer, Polynomial, PolynomialVector};
 use crate::param::{ArraySize, EncodingSize};
 use crate::util::Truncate;
 
-// A convenience trait to allow us to associate some constants with a typenum
+/// A convenience trait to allow us to associate some constants with a typenum
 pub trait CompressionFactor: EncodingSize {
+    /// `2^(d-1)`, used for rounding on decompression
     const POW2_HALF: u32;
+
+    /// `2^d - 1`, the mask for a compressed value
     const MASK: Integer;
 }
 
@@ -16,9 +19,14 @@ where
     const MASK: Integer = ((1 as Integer) << T::USIZE) - 1;
 }
 
-// Traits for objects that allow compression / decompression
+/// Traits for objects that allow compression / decompression
 pub trait Compress {
+    /// Compress each value in place to `D` bits, `x -> round((2^D / q) x) mod 2^D`.  The compressed
+    /// values are not field elements; they are only meaningful as input to encoding or
+    /// decompression.
     fn compress<D: CompressionFactor>(&mut self) -> &Self;
+
+    /// Decompress each value in place from `D` bits, `y -> round((q / 2^D) y)`
     fn decompress<D: CompressionFactor>(&mut self) -> &Self;
 }
 
@@ -87,6 +95,11 @@ pub(crate) mod test {
 
     // Verify that the integer compression routine produces the same results as rounding with
     // floats.
+    #[allow(
+        clippy::cast_lossless,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
     fn compression_known_answer_test<D: CompressionFactor>() {
         let fq: f64 = FieldElement::Q as f64;
         let f2d: f64 = 2.0_f64.powi(D::I32);
@@ -0,0 +1,970 @@
+//! A `COSE_recipient` wraps the content-encryption key (CEK) of a `COSE_Encrypt` message for an
+//! ML-KEM recipient.
+//!
+//! Everything in this module works without an allocator: Structures are encoded into
+//! caller-provided buffers, and decoded structures borrow from their input.
+//!
+//! # Keys
+//!
+//! Keys use the `AKP` ("algorithm key pair") key type from draft-ietf-cose-dilithium, in which
+//! the key's algorithm determines the format of the public and private key parameters.  For
+//! ML-KEM, `pub` (-1) is the encoded encapsulation key, and `priv` (-2) is the 64-byte seed
+//! `d || z` from which the key pair is generated.
+//!
+//! ```
+//! # use ml_kem::cose::{Algorithm, CoseKey};
+//! # use ml_kem::{EncodedSizeUser, KemCore, MlKem768, MlKem768Params};
+//! let mut rng = rand::thread_rng();
+//! let (dk, ek) = MlKem768::generate(&mut rng);
+//!
+//! let public = ek.as_bytes();
+//! let key = CoseKey {
+//!     alg: Algorithm::MlKem768A192Kw,
+//!     kid: b"device-1",
+//!     public: &public,
+//!     seed: None,
+//! };
+//! let mut buf = [0u8; 1300];
+//! let len = key.encode(&mut buf).unwrap();
+//!
+//! let decoded = CoseKey::decode(&buf[..len]).unwrap();
+//! assert_eq!(decoded.encapsulation_key::<MlKem768Params>().unwrap(), ek);
+//! ```
+//!
+//! # Recipients
+//!
+//! A recipient structure carries the ML-KEM ciphertext in the `ek` (-4) header parameter, and
+//! the CEK wrapped with AES key wrap as its ciphertext:
+//!
+//! ```text
+//! COSE_recipient = [
+//!     protected: bstr .cbor { 1 (alg): alg },
+//!     unprotected: { -4 (ek): bstr, ? 4 (kid): bstr },
+//!     ciphertext: bstr,
+//! ]
+//! ```
+//!
+//! The key-encryption key is derived as in RFC 9053, Section 5:
+//!
+//! ```text
+//! KEK = HKDF-SHA256(salt = absent, ikm = ss, info = COSE_KDF_Context, L = kekLength)
+//! COSE_KDF_Context = [
+//!     AlgorithmID: the key wrap algorithm (A128KW, A192KW, or A256KW),
+//!     PartyUInfo: [nil, nil, nil],
+//!     PartyVInfo: [nil, nil, nil],
+//!     SuppPubInfo: [kekLength in bits, protected],
+//! ]
+//! ```
+//!
+//! ```
+//! # use ml_kem::cose::{self, Algorithm, Recipient};
+//! # use ml_kem::{KemCore, MlKem768};
+//! let mut rng = rand::thread_rng();
+//! let (dk, ek) = MlKem768::generate(&mut rng);
+//!
+//! let cek = [0x42; 32];
+//! let mut buf = [0u8; 1200];
+//! let len = cose::wrap_cek(&ek, Algorithm::MlKem768A192Kw, b"device-1", &cek, &mut buf, &mut rng)
+//!     .unwrap();
+//!
+//! let recipient = Recipient::decode(&buf[..len]).unwrap();
+//! assert_eq!(recipient.kid, Some(&b"device-1"[..]));
+//! let mut unwrapped = [0u8; 32];
+//! assert_eq!(recipient.unwrap_cek(&dk, &mut unwrapped), Ok(32));
+//! assert_eq!(unwrapped, cek);
+//! ```
+//!
+//! The ML-KEM algorithms have not yet been assigned COSE algorithm identifiers, so this module
+//! uses values from the private-use range, which peers must agree on out of band.
+
+use aes_kw::{KekAes128, KekAes192, KekAes256};
+use core::fmt;
+use hkdf::Hkdf;
+use rand_core::CryptoRngCore;
+use sha2::Sha256;
+
+use crate::kem::{decapsulate, encapsulate, DecapsulationKey, EncapsulationKey};
+use crate::param::{EncodedCiphertext, KemParams, StandardParams};
+use crate::util::B32;
+use crate::{Encoded, EncodedSizeUser};
+
+/// The `AKP` key type
+pub const KEY_TYPE_AKP: i64 = 7;
+
+// Common header and key parameter labels
+const LABEL_KTY: i64 = 1;
+const LABEL_KID: i64 = 2;
+const LABEL_ALG: i64 = 3;
+const LABEL_PUB: i64 = -1;
+const LABEL_PRIV: i64 = -2;
+const HEADER_ALG: i64 = 1;
+const HEADER_KID: i64 = 4;
+const HEADER_EK: i64 = -4;
+
+// The length of the AES key wrap integrity check value
+const KW_IV_LEN: usize = 8;
+
+// The maximum nesting of CBOR items that are skipped when decoding
+const MAX_DEPTH: usize = 16;
+
+/// A COSE key management algorithm using ML-KEM with AES key wrap
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// ML-KEM-512 with AES-128 key wrap
+    MlKem512A128Kw,
+
+    /// ML-KEM-768 with AES-192 key wrap
+    MlKem768A192Kw,
+
+    /// ML-KEM-1024 with AES-256 key wrap
+    MlKem1024A256Kw,
+}
+
+impl Algorithm {
+    const ALL: [Self; 3] = [
+        Self::MlKem512A128Kw,
+        Self::MlKem768A192Kw,
+        Self::MlKem1024A256Kw,
+    ];
+
+    /// The COSE algorithm identifier, from the private-use range
+    #[must_use]
+    pub const fn id(self) -> i64 {
+        match self {
+            Self::MlKem512A128Kw => -65537,
+            Self::MlKem768A192Kw => -65538,
+            Self::MlKem1024A256Kw => -65539,
+        }
+    }
+
+    /// The algorithm with the given COSE identifier
+    #[must_use]
+    pub fn from_id(id: i64) -> Option<Self> {
+        Self::ALL.into_iter().find(|alg| alg.id() == id)
+    }
+
+    // The ML-KEM parameter set
+    fn params(self) -> StandardParams {
+        match self {
+            Self::MlKem512A128Kw => StandardParams::MlKem512,
+            Self::MlKem768A192Kw => StandardParams::MlKem768,
+            Self::MlKem1024A256Kw => StandardParams::MlKem1024,
+        }
+    }
+
+    // The identifier of the key wrap algorithm, and the length of its key
+    fn key_wrap(self) -> (i64, usize) {
+        match self {
+            Self::MlKem512A128Kw => (-3, 16),
+            Self::MlKem768A192Kw => (-4, 24),
+            Self::MlKem1024A256Kw => (-5, 32),
+        }
+    }
+
+    fn check<P: KemParams>(self) -> Result<(), Error> {
+        if StandardParams::of::<P>() == Some(self.params()) {
+            Ok(())
+        } else {
+            Err(Error::WrongParameterSet)
+        }
+    }
+}
+
+/// An ML-KEM key in the `COSE_Key` format
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CoseKey<'a> {
+    /// The algorithm with which the key is used (`alg`), which determines its parameter set
+    pub alg: Algorithm,
+
+    /// The key identifier (`kid`), which is omitted if empty
+    pub kid: &'a [u8],
+
+    /// The encoded encapsulation key (`pub`), as returned by [`EncodedSizeUser::as_bytes`]
+    pub public: &'a [u8],
+
+    /// The seed `d || z` of the key pair (`priv`), for a private key
+    pub seed: Option<&'a [u8; 64]>,
+}
+
+impl<'a> CoseKey<'a> {
+    /// Encode the key into `buf`, returning the length of the encoding.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::BufferTooSmall`] if `buf` cannot hold the encoding.
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        let mut enc = Encoder::new(buf);
+        let len = 3 + usize::from(!self.kid.is_empty()) + usize::from(self.seed.is_some());
+        enc.map(len);
+        enc.int(LABEL_KTY);
+        enc.int(KEY_TYPE_AKP);
+        if !self.kid.is_empty() {
+            enc.int(LABEL_KID);
+            enc.bytes(self.kid);
+        }
+        enc.int(LABEL_ALG);
+        enc.int(self.alg.id());
+        enc.int(LABEL_PUB);
+        enc.bytes(self.public);
+        if let Some(seed) = self.seed {
+            enc.int(LABEL_PRIV);
+            enc.bytes(seed);
+        }
+        enc.finish()
+    }
+
+    /// Decode a key.  Parameters other than those of an ML-KEM key are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the encoding is not valid CBOR, or if it is not an ML-KEM key.
+    pub fn decode(data: &'a [u8]) -> Result<Self, Error> {
+        let mut dec = Decoder::new(data);
+        let (mut kty, mut kid, mut alg, mut public, mut seed) = (None, None, None, None, None);
+        for _ in 0..dec.map()? {
+            let Some(label) = dec.label()? else {
+                dec.skip()?;
+                continue;
+            };
+            match label {
+                LABEL_KTY => set(&mut kty, dec.int()?)?,
+                LABEL_KID => set(&mut kid, dec.bytes()?)?,
+                LABEL_ALG => set(&mut alg, dec.int()?)?,
+                LABEL_PUB => set(&mut public, dec.bytes()?)?,
+                LABEL_PRIV => set(&mut seed, dec.bytes()?)?,
+                _ => dec.skip()?,
+            }
+        }
+        dec.finish()?;
+
+        if kty != Some(KEY_TYPE_AKP) {
+            return Err(Error::UnsupportedKeyType);
+        }
+        let seed = seed
+            .map(|seed| <&[u8; 64]>::try_from(seed).map_err(|_| Error::InvalidKey))
+            .transpose()?;
+        Ok(Self {
+            alg: alg
+                .and_then(Algorithm::from_id)
+                .ok_or(Error::UnsupportedAlgorithm)?,
+            kid: kid.unwrap_or_default(),
+            public: public.ok_or(Error::InvalidKey)?,
+            seed,
+        })
+    }
+
+    /// The encapsulation key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key is for a different parameter set, or if the key is not a valid
+    /// encoding.
+    pub fn encapsulation_key<P: KemParams>(&self) -> Result<EncapsulationKey<P>, Error> {
+        self.alg.check::<P>()?;
+        let bytes =
+            Encoded::<EncapsulationKey<P>>::try_from(self.public).map_err(|_| Error::InvalidKey)?;
+        EncapsulationKey::from_bytes_checked(&bytes).ok_or(Error::InvalidKey)
+    }
+
+    /// The decapsulation key, generated from the seed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key is for a different parameter set, if it has no seed, or if
+    /// the public key does not correspond to the seed.
+    pub fn decapsulation_key<P: KemParams>(&self) -> Result<DecapsulationKey<P>, Error> {
+        self.alg.check::<P>()?;
+        let seed = self.seed.ok_or(Error::MissingPrivateKey)?;
+        let d = B32::from_fn(|i| seed[i]);
+        let z = B32::from_fn(|i| seed[32 + i]);
+        let dk = DecapsulationKey::<P>::generate_deterministic(&d, &z);
+
+        if dk.encapsulation_key().as_bytes().as_slice() != self.public {
+            return Err(Error::InconsistentKey);
+        }
+        Ok(dk)
+    }
+}
+
+// Set a map value that must not be repeated
+fn set<T>(slot: &mut Option<T>, value: T) -> Result<(), Error> {
+    if slot.replace(value).is_some() {
+        return Err(Error::Cbor);
+    }
+    Ok(())
+}
+
+/// A decoded `COSE_recipient` for an ML-KEM recipient
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Recipient<'a> {
+    /// The key management algorithm, from the protected header
+    pub alg: Algorithm,
+
+    /// The key identifier of the recipient's key, if present
+    pub kid: Option<&'a [u8]>,
+
+    /// The ML-KEM ciphertext
+    pub kem_ct: &'a [u8],
+
+    /// The wrapped CEK
+    pub encrypted_key: &'a [u8],
+
+    // The encoded protected header, which is bound by the KDF
+    protected: &'a [u8],
+}
+
+impl<'a> Recipient<'a> {
+    /// Decode a `COSE_recipient`.  Header parameters other than those used by ML-KEM are
+    /// ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the encoding is not valid CBOR, or if it does not use an ML-KEM
+    /// algorithm.
+    pub fn decode(data: &'a [u8]) -> Result<Self, Error> {
+        let mut dec = Decoder::new(data);
+        if dec.array()? != 3 {
+            return Err(Error::Cbor);
+        }
+
+        let protected = dec.bytes()?;
+        let mut header = Decoder::new(protected);
+        let mut alg = None;
+        for _ in 0..header.map()? {
+            match header.label()? {
+                Some(HEADER_ALG) => set(&mut alg, header.int()?)?,
+                _ => header.skip()?,
+            }
+        }
+        header.finish()?;
+
+        let (mut kem_ct, mut kid) = (None, None);
+        for _ in 0..dec.map()? {
+            match dec.label()? {
+                Some(HEADER_EK) => set(&mut kem_ct, dec.bytes()?)?,
+                Some(HEADER_KID) => set(&mut kid, dec.bytes()?)?,
+                _ => dec.skip()?,
+            }
+        }
+        let encrypted_key = dec.bytes()?;
+        dec.finish()?;
+
+        Ok(Self {
+            alg: alg
+                .and_then(Algorithm::from_id)
+                .ok_or(Error::UnsupportedAlgorithm)?,
+            kid,
+            kem_ct: kem_ct.ok_or(Error::Cbor)?,
+            encrypted_key,
+            protected,
+        })
+    }
+
+    /// Unwrap the CEK into `cek`, returning its length.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the recipient is for a different parameter set, if the ciphertext is
+    /// malformed, if `cek` is too small, or if the CEK does not unwrap, e.g., because it was
+    /// wrapped for a different key.
+    pub fn unwrap_cek<P: KemParams>(
+        &self,
+        dk: &DecapsulationKey<P>,
+        cek: &mut [u8],
+    ) -> Result<usize, Error> {
+        self.alg.check::<P>()?;
+        let ct = EncodedCiphertext::<P>::try_from(self.kem_ct).map_err(|_| Error::Cbor)?;
+        let cek_len = self
+            .encrypted_key
+            .len()
+            .checked_sub(KW_IV_LEN)
+            .ok_or(Error::KeyWrap)?;
+        let cek = cek
+            .get_mut(..cek_len)
+            .ok_or(Error::BufferTooSmall { needed: cek_len })?;
+
+        let ss = decapsulate(dk, &ct);
+        let kek = derive_kek(&ss, self.alg, self.protected)?;
+        let kek = &kek[..self.alg.key_wrap().1];
+        match kek.len() {
+            16 => KekAes128::try_from(kek).and_then(|kek| kek.unwrap(self.encrypted_key, cek)),
+            24 => KekAes192::try_from(kek).and_then(|kek| kek.unwrap(self.encrypted_key, cek)),
+            _ => KekAes256::try_from(kek).and_then(|kek| kek.unwrap(self.encrypted_key, cek)),
+        }
+        .map_err(|_| Error::KeyWrap)?;
+        Ok(cek_len)
+    }
+}
+
+/// Wrap the CEK `cek` for the holder of the decapsulation key corresponding to `ek`, encoding the
+/// `COSE_recipient` into `buf` and returning its length.  The key identifier `kid` is omitted if
+/// it is empty.
+///
+/// # Errors
+///
+/// Returns an error if `alg` is for a different parameter set, if `cek` cannot be wrapped with
+/// AES key wrap, or if `buf` cannot hold the encoding.
+pub fn wrap_cek<P: KemParams>(
+    ek: &EncapsulationKey<P>,
+    alg: Algorithm,
+    kid: &[u8],
+    cek: &[u8],
+    buf: &mut [u8],
+    rng: &mut impl CryptoRngCore,
+) -> Result<usize, Error> {
+    alg.check::<P>()?;
+    if cek.len() < 2 * KW_IV_LEN || cek.len() % KW_IV_LEN != 0 || cek.len() > 64 {
+        return Err(Error::KeyWrap);
+    }
+
+    let mut protected = [0u8; 16];
+    let protected_len = {
+        let mut enc = Encoder::new(&mut protected);
+        enc.map(1);
+        enc.int(HEADER_ALG);
+        enc.int(alg.id());
+        enc.finish()?
+    };
+    let protected = &protected[..protected_len];
+
+    let (ct, ss) = encapsulate(ek, rng);
+    let kek = derive_kek(&ss, alg, protected)?;
+    let kek = &kek[..alg.key_wrap().1];
+    let mut encrypted_key = [0u8; 64 + KW_IV_LEN];
+    let encrypted_key = &mut encrypted_key[..cek.len() + KW_IV_LEN];
+    match kek.len() {
+        16 => KekAes128::try_from(kek).and_then(|kek| kek.wrap(cek, encrypted_key)),
+        24 => KekAes192::try_from(kek).and_then(|kek| kek.wrap(cek, encrypted_key)),
+        _ => KekAes256::try_from(kek).and_then(|kek| kek.wrap(cek, encrypted_key)),
+    }
+    .map_err(|_| Error::KeyWrap)?;
+
+    let mut enc = Encoder::new(buf);
+    enc.array(3);
+    enc.bytes(protected);
+    enc.map(1 + usize::from(!kid.is_empty()));
+    if !kid.is_empty() {
+        enc.int(HEADER_KID);
+        enc.bytes(kid);
+    }
+    enc.int(HEADER_EK);
+    enc.bytes(&ct);
+    enc.bytes(encrypted_key);
+    enc.finish()
+}
+
+// Derive the key-encryption key with HKDF-SHA256 and the `COSE_KDF_Context`.  The KEK is the
+// first `kekLength` bytes of the result.
+fn derive_kek(ss: &[u8], alg: Algorithm, protected: &[u8]) -> Result<[u8; 32], Error> {
+    let (wrap_id, kek_len) = alg.key_wrap();
+
+    let mut context = [0u8; 32];
+    let context_len = {
+        let mut enc = Encoder::new(&mut context);
+        enc.array(4);
+        enc.int(wrap_id);
+        for _ in 0..2 {
+            enc.array(3);
+            enc.null();
+            enc.null();
+            enc.null();
+        }
+        enc.array(2);
+        enc.int(i64::try_from(kek_len * 8).expect("KEK lengths are small"));
+        enc.bytes(protected);
+        enc.finish()?
+    };
+
+    let mut kek = [0u8; 32];
+    Hkdf::<Sha256>::new(None, ss)
+        .expand(&context[..context_len], &mut kek[..kek_len])
+        .map_err(|_| Error::KeyWrap)?;
+    Ok(kek)
+}
+
+/// An error in encoding or decoding a COSE structure
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The encoding is not valid CBOR of the expected structure
+    Cbor,
+
+    /// The output buffer is too small
+    BufferTooSmall {
+        /// The length of buffer that is needed
+        needed: usize,
+    },
+
+    /// The key has a key type other than [`KEY_TYPE_AKP`]
+    UnsupportedKeyType,
+
+    /// The algorithm is not an ML-KEM algorithm
+    UnsupportedAlgorithm,
+
+    /// The algorithm is for a different ML-KEM parameter set
+    WrongParameterSet,
+
+    /// A key has the wrong length, or the encapsulation key fails the modulus check of FIPS 203
+    InvalidKey,
+
+    /// The key has no private key
+    MissingPrivateKey,
+
+    /// The public key does not correspond to the private key
+    InconsistentKey,
+
+    /// The CEK could not be wrapped or unwrapped
+    KeyWrap,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cbor => f.write_str("invalid CBOR"),
+            Self::BufferTooSmall { needed } => write!(f, "buffer too small, {needed} bytes needed"),
+            Self::UnsupportedKeyType => f.write_str("unsupported key type"),
+            Self::UnsupportedAlgorithm => f.write_str("unsupported algorithm"),
+            Self::WrongParameterSet => f.write_str("algorithm is for a different parameter set"),
+            Self::InvalidKey => f.write_str("invalid key"),
+            Self::MissingPrivateKey => f.write_str("key has no private key"),
+            Self::InconsistentKey => f.write_str("public key does not match private key"),
+            Self::KeyWrap => f.write_str("AES key wrap failed"),
+        }
+    }
+}
+
+// A CBOR encoder that writes into a fixed buffer.  Writes past the end of the buffer are counted
+// but discarded, so that `finish` can report the length that was needed.
+struct Encoder<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Encoder<'a> {
+    fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    fn raw(&mut self, data: &[u8]) {
+        if let Some(out) = self.buf.get_mut(self.len..self.len + data.len()) {
+            out.copy_from_slice(data);
+        }
+        self.len += data.len();
+    }
+
+    // Write the initial byte and argument of an item, in the shortest form
+    fn head(&mut self, major: u8, value: u64) {
+        let major = major << 5;
+        let bytes = value.to_be_bytes();
+        match value {
+            0..=23 => self.raw(&[major | bytes[7]]),
+            24..=0xff => self.raw(&[major | 0x18, bytes[7]]),
+            0x100..=0xffff => {
+                self.raw(&[major | 0x19]);
+                self.raw(&bytes[6..]);
+            }
+            0x1_0000..=0xffff_ffff => {
+                self.raw(&[major | 0x1a]);
+                self.raw(&bytes[4..]);
+            }
+            _ => {
+                self.raw(&[major | 0x1b]);
+                self.raw(&bytes);
+            }
+        }
+    }
+
+    fn int(&mut self, value: i64) {
+        match u64::try_from(value) {
+            Ok(value) => self.head(0, value),
+            Err(_) => self.head(1, value.unsigned_abs() - 1),
+        }
+    }
+
+    fn bytes(&mut self, data: &[u8]) {
+        self.head(2, data.len() as u64);
+        self.raw(data);
+    }
+
+    fn array(&mut self, len: usize) {
+        self.head(4, len as u64);
+    }
+
+    fn map(&mut self, len: usize) {
+        self.head(5, len as u64);
+    }
+
+    fn null(&mut self) {
+        self.raw(&[0xf6]);
+    }
+
+    fn finish(self) -> Result<usize, Error> {
+        if self.len <= self.buf.len() {
+            Ok(self.len)
+        } else {
+            Err(Error::BufferTooSmall { needed: self.len })
+        }
+    }
+}
+
+// A CBOR decoder for definite-length items
+struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let end = self.pos.checked_add(len).ok_or(Error::Cbor)?;
+        let bytes = self.data.get(self.pos..end).ok_or(Error::Cbor)?;
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn peek_major(&self) -> Result<u8, Error> {
+        self.data.get(self.pos).map(|b| b >> 5).ok_or(Error::Cbor)
+    }
+
+    // Read the initial byte and argument of an item
+    fn head(&mut self) -> Result<(u8, u64), Error> {
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        let value = match info {
+            0..=23 => u64::from(info),
+            24 => u64::from(self.take(1)?[0]),
+            25 => u64::from(u16::from_be_bytes(self.array_of()?)),
+            26 => u64::from(u32::from_be_bytes(self.array_of()?)),
+            27 => u64::from_be_bytes(self.array_of()?),
+            // Reserved values, and indefinite lengths, which are not used by COSE
+            _ => return Err(Error::Cbor),
+        };
+        Ok((major, value))
+    }
+
+    fn array_of<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let bytes = self.take(N)?;
+        Ok(bytes.try_into().expect("slice has the requested length"))
+    }
+
+    fn int(&mut self) -> Result<i64, Error> {
+        let (major, value) = self.head()?;
+        let value = i64::try_from(value).map_err(|_| Error::Cbor)?;
+        match major {
+            0 => Ok(value),
+            1 => Ok(-1 - value),
+            _ => Err(Error::Cbor),
+        }
+    }
+
+    // Read a map label, returning `None` for a label that is not an integer
+    fn label(&mut self) -> Result<Option<i64>, Error> {
+        match self.peek_major()? {
+            0 | 1 => self.int().map(Some),
+            _ => self.skip().map(|()| None),
+        }
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], Error> {
+        match self.head()? {
+            (2, len) => self.take(usize::try_from(len).map_err(|_| Error::Cbor)?),
+            _ => Err(Error::Cbor),
+        }
+    }
+
+    fn array(&mut self) -> Result<u64, Error> {
+        match self.head()? {
+            (4, len) => Ok(len),
+            _ => Err(Error::Cbor),
+        }
+    }
+
+    fn map(&mut self) -> Result<u64, Error> {
+        match self.head()? {
+            (5, len) => Ok(len),
+            _ => Err(Error::Cbor),
+        }
+    }
+
+    fn skip(&mut self) -> Result<(), Error> {
+        self.skip_nested(0)
+    }
+
+    fn skip_nested(&mut self, depth: usize) -> Result<(), Error> {
+        if depth > MAX_DEPTH {
+            return Err(Error::Cbor);
+        }
+
+        let (major, value) = self.head()?;
+        let items = match major {
+            2 | 3 => {
+                self.take(usize::try_from(value).map_err(|_| Error::Cbor)?)?;
+                0
+            }
+            4 => value,
+            5 => value.checked_mul(2).ok_or(Error::Cbor)?,
+            6 => 1,
+            _ => 0,
+        };
+        for _ in 0..items {
+            self.skip_nested(depth + 1)?;
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        if self.pos == self.data.len() {
+            Ok(())
+        } else {
+            Err(Error::Cbor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kem::Kem;
+    use crate::{KemCore, MlKem1024Params, MlKem512Params, MlKem768Params};
+    use hex_literal::hex;
+    use hybrid_array::typenum::Unsigned;
+
+    #[test]
+    fn cbor() {
+        let mut buf = [0u8; 64];
+        let mut enc = Encoder::new(&mut buf);
+        for value in [0, 23, 24, 255, 256, 65536, -1, -24, -25, -65537, i64::MIN] {
+            enc.int(value);
+        }
+        enc.bytes(b"ab");
+        enc.null();
+        let len = enc.finish().unwrap();
+        assert_eq!(
+            buf[..len],
+            hex!(
+                "00 17 1818 18ff 190100 1a00010000"
+                "20 37 3818 3a00010000 3b7fffffffffffffff"
+                "426162 f6"
+            )
+        );
+
+        let mut dec = Decoder::new(&buf[..len]);
+        for value in [0, 23, 24, 255, 256, 65536, -1, -24, -25, -65537, i64::MIN] {
+            assert_eq!(dec.int(), Ok(value));
+        }
+        assert_eq!(dec.bytes(), Ok(&b"ab"[..]));
+        dec.skip().unwrap();
+        dec.finish().unwrap();
+
+        // Writing past the end of the buffer reports the needed length
+        let mut enc = Encoder::new(&mut buf[..2]);
+        enc.bytes(b"abc");
+        assert_eq!(enc.finish(), Err(Error::BufferTooSmall { needed: 4 }));
+
+        // Truncated items, indefinite lengths, and deep nesting are rejected
+        assert_eq!(Decoder::new(&hex!("43 6162")).bytes(), Err(Error::Cbor));
+        assert_eq!(Decoder::new(&hex!("5f 41 61 ff")).bytes(), Err(Error::Cbor));
+        assert_eq!(Decoder::new(&[0x81; 100]).skip(), Err(Error::Cbor));
+    }
+
+    #[test]
+    fn kdf_context() {
+        // The COSE_KDF_Context for ML-KEM-768 with A192KW, with protected header {1: -65538}
+        let protected = hex!("a1 01 3a00010001");
+        let mut buf = [0u8; 32];
+        let mut enc = Encoder::new(&mut buf);
+        enc.map(1);
+        enc.int(HEADER_ALG);
+        enc.int(Algorithm::MlKem768A192Kw.id());
+        let len = enc.finish().unwrap();
+        assert_eq!(buf[..len], protected);
+
+        // [-4, [nil, nil, nil], [nil, nil, nil], [192, h'a1013a00010001']]
+        let context = hex!("84 23 83f6f6f6 83f6f6f6 82 18c0 47a1013a00010001");
+        let ss = [7u8; 32];
+        let mut expected = [0u8; 24];
+        Hkdf::<Sha256>::new(None, &ss)
+            .expand(&context, &mut expected)
+            .unwrap();
+        let kek = derive_kek(&ss, Algorithm::MlKem768A192Kw, &protected).unwrap();
+        assert_eq!(kek[..24], expected);
+    }
+
+    fn key_test<P: KemParams>(alg: Algorithm) {
+        let (d, z) = ([1u8; 32].into(), [2u8; 32].into());
+        let dk = DecapsulationKey::<P>::generate_deterministic(&d, &z);
+        let ek = dk.encapsulation_key();
+        let public = ek.as_bytes();
+        let mut seed = [0u8; 64];
+        seed[..32].copy_from_slice(&d);
+        seed[32..].copy_from_slice(&z);
+
+        let mut buf = [0u8; 1700];
+        for (kid, seed) in [(&b""[..], None), (&b"kid"[..], Some(&seed))] {
+            let key = CoseKey {
+                alg,
+                kid,
+                public: &public,
+                seed,
+            };
+            let len = key.encode(&mut buf).unwrap();
+            let decoded = CoseKey::decode(&buf[..len]).unwrap();
+            assert_eq!(decoded, key);
+            assert_eq!(&decoded.encapsulation_key::<P>().unwrap(), ek);
+
+            match seed {
+                Some(_) => assert_eq!(decoded.decapsulation_key::<P>().unwrap(), dk),
+                None => assert_eq!(
+                    decoded.decapsulation_key::<P>(),
+                    Err(Error::MissingPrivateKey)
+                ),
+            }
+
+            assert_eq!(
+                key.encode(&mut buf[..len - 1]),
+                Err(Error::BufferTooSmall { needed: len })
+            );
+        }
+    }
+
+    #[test]
+    fn key() {
+        key_test::<MlKem512Params>(Algorithm::MlKem512A128Kw);
+        key_test::<MlKem768Params>(Algorithm::MlKem768A192Kw);
+        key_test::<MlKem1024Params>(Algorithm::MlKem1024A256Kw);
+    }
+
+    #[test]
+    fn key_encoding() {
+        let key = CoseKey {
+            alg: Algorithm::MlKem768A192Kw,
+            kid: b"k",
+            public: &[0xaa; 2],
+            seed: None,
+        };
+        let mut buf = [0u8; 32];
+        let len = key.encode(&mut buf).unwrap();
+        // {1: 7, 2: h'6b', 3: -65538, -1: h'aaaa'}
+        assert_eq!(buf[..len], hex!("a4 0107 02416b 033a00010001 2042aaaa"));
+
+        // Unknown parameters, including text labels, are ignored
+        let with_unknown = hex!("a6 0107 02416b 033a00010001 2042aaaa 6161f5 1863a0");
+        assert_eq!(CoseKey::decode(&with_unknown), Ok(key));
+
+        // Other key types, unknown algorithms, repeated labels, and trailing data are rejected
+        assert_eq!(
+            CoseKey::decode(&hex!("a4 0101 02416b 033a00010001 2042aaaa")),
+            Err(Error::UnsupportedKeyType)
+        );
+        assert_eq!(
+            CoseKey::decode(&hex!("a4 0107 02416b 0326 2042aaaa")),
+            Err(Error::UnsupportedAlgorithm)
+        );
+        assert_eq!(
+            CoseKey::decode(&hex!("a4 0107 0107 033a00010001 2042aaaa")),
+            Err(Error::Cbor)
+        );
+        assert_eq!(
+            CoseKey::decode(&hex!("a4 0107 02416b 033a00010001 2042aaaa 00")),
+            Err(Error::Cbor)
+        );
+
+        // The public key must be a valid encoding for the parameter set
+        assert_eq!(
+            key.encapsulation_key::<MlKem768Params>(),
+            Err(Error::InvalidKey)
+        );
+        assert_eq!(
+            key.encapsulation_key::<MlKem512Params>(),
+            Err(Error::WrongParameterSet)
+        );
+    }
+
+    fn recipient_test<P: KemParams>(alg: Algorithm) {
+        let mut rng = rand::thread_rng();
+        let (dk, ek) = Kem::<P>::generate(&mut rng);
+        let (dk2, _) = Kem::<P>::generate(&mut rng);
+
+        let mut buf = [0u8; 1700];
+        for (kid, cek) in [(&b""[..], &[0x42; 16][..]), (&b"kid"[..], &[0x42; 64][..])] {
+            let len = wrap_cek(&ek, alg, kid, cek, &mut buf, &mut rng).unwrap();
+            let recipient = Recipient::decode(&buf[..len]).unwrap();
+            assert_eq!(recipient.alg, alg);
+            assert_eq!(recipient.kid, (!kid.is_empty()).then_some(kid));
+            assert_eq!(recipient.kem_ct.len(), P::CiphertextSize::USIZE);
+
+            let mut unwrapped = [0u8; 64];
+            assert_eq!(recipient.unwrap_cek(&dk, &mut unwrapped), Ok(cek.len()));
+            assert_eq!(&unwrapped[..cek.len()], cek);
+
+            // The wrong key gets an implicitly rejected shared key, which fails to unwrap
+            assert_eq!(
+                recipient.unwrap_cek(&dk2, &mut unwrapped),
+                Err(Error::KeyWrap)
+            );
+            assert_eq!(
+                recipient.unwrap_cek(&dk, &mut unwrapped[..cek.len() - 1]),
+                Err(Error::BufferTooSmall { needed: cek.len() })
+            );
+
+            // Truncation
+            assert_eq!(Recipient::decode(&buf[..len - 1]), Err(Error::Cbor));
+        }
+
+        // CEKs that AES key wrap cannot handle
+        for cek in [&[0x42; 8][..], &[0x42; 20][..]] {
+            assert_eq!(
+                wrap_cek(&ek, alg, b"", cek, &mut buf, &mut rng),
+                Err(Error::KeyWrap)
+            );
+        }
+    }
+
+    #[test]
+    fn recipient() {
+        recipient_test::<MlKem512Params>(Algorithm::MlKem512A128Kw);
+        recipient_test::<MlKem768Params>(Algorithm::MlKem768A192Kw);
+        recipient_test::<MlKem1024Params>(Algorithm::MlKem1024A256Kw);
+    }
+
+    #[test]
+    fn recipient_protected_header() {
+        let mut rng = rand::thread_rng();
+        let (dk, ek) = Kem::<MlKem768Params>::generate(&mut rng);
+        let mut buf = [0u8; 1200];
+        let len = wrap_cek(
+            &ek,
+            Algorithm::MlKem768A192Kw,
+            b"",
+            &[0x42; 16],
+            &mut buf,
+            &mut rng,
+        )
+        .unwrap();
+
+        // [h'a1013a00010001', {-4: h'...'}, h'...']
+        assert_eq!(buf[..10], hex!("83 47 a1013a00010001 a1"));
+
+        // The protected header is bound by the KDF, so adding a parameter to it changes the KEK
+        let mut modified = [0u8; 1200];
+        modified[..2].copy_from_slice(&hex!("83 49"));
+        modified[2..9].copy_from_slice(&hex!("a2 013a00010001"));
+        modified[9..11].copy_from_slice(&hex!("0500"));
+        modified[11..len + 2].copy_from_slice(&buf[9..len]);
+        let recipient = Recipient::decode(&modified[..len + 2]).unwrap();
+        assert_eq!(
+            recipient.unwrap_cek(&dk, &mut [0u8; 16]),
+            Err(Error::KeyWrap)
+        );
+
+        // The wrong parameter set
+        let recipient = Recipient::decode(&buf[..len]).unwrap();
+        let (dk512, _) = Kem::<MlKem512Params>::generate(&mut rng);
+        assert_eq!(
+            recipient.unwrap_cek(&dk512, &mut [0u8; 16]),
+            Err(Error::WrongParameterSet)
+        );
+    }
+}
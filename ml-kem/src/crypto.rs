@@ -16,6 +16,7 @@ pub fn rand<L: ArraySize>(rng: &mut impl CryptoRngCore) -> Array<u8, L> {
     val
 }
 
+/// `G(c) = SHA3-512(c)`, split into two 32-byte halves
 pub fn G(inputs: &[impl AsRef<[u8]>]) -> (B32, B32) {
     let mut h = Sha3_512::new();
     for x in inputs {
@@ -31,6 +32,7 @@ pub fn G(inputs: &[impl AsRef<[u8]>]) -> (B32, B32) {
     (a, b)
 }
 
+/// `H(s) = SHA3-256(s)`
 pub fn H(x: impl AsRef<[u8]>) -> B32 {
     let mut h = Sha3_256::new();
     Digest::update(&mut h, x);
@@ -43,6 +45,7 @@ pub fn H(x: impl AsRef<[u8]>) -> B32 {
     out
 }
 
+/// `J(s) = SHAKE256(s, 32)`
 pub fn J(inputs: &[impl AsRef<[u8]>]) -> B32 {
     let mut h = Shake256::default();
     for x in inputs {
@@ -55,8 +58,11 @@ pub fn J(inputs: &[impl AsRef<[u8]>]) -> B32 {
     out
 }
 
+/// The output of `PRF`, the right size for sampling one polynomial with parameter `Eta`
 pub type PrfOutput<Eta> = EncodedPolynomial<<Eta as CbdSamplingSize>::SampleSize>;
 
+/// `PRF_eta(s, b) = SHAKE256(s || b, 64 * eta)`
+#[must_use]
 pub fn PRF<Eta>(s: &B32, b: u8) -> PrfOutput<Eta>
 where
     Eta: CbdSamplingSize,
@@ -71,6 +77,8 @@ where
     out
 }
 
+/// `XOF(rho, i, j)`, a SHAKE128 reader on `rho || i || j`
+#[must_use]
 pub fn XOF(rho: &B32, i: u8, j: u8) -> impl XofReader {
     let mut h = Shake128::default();
     h.update(rho);
@@ -65,9 +65,16 @@ fn byte_decode<D: EncodingSize>(bytes: &EncodedPolynomial<D>) -> DecodedValue {
     vals
 }
 
+/// Objects that can be encoded as byte arrays, with `D` bits per value (`ByteEncode_d` and
+/// `ByteDecode_d`).  For `D = 12`, decoding reduces each value mod q.
 pub trait Encode<D: EncodingSize> {
+    /// The size of the encoded object
     type EncodedSize: ArraySize;
+
+    /// Encode this object, using the `D` low-order bits of each value
     fn encode(&self) -> Array<u8, Self::EncodedSize>;
+
+    /// Decode an object from its encoding
     fn decode(enc: &Array<u8, Self::EncodedSize>) -> Self;
 }
 
@@ -106,15 +113,16 @@ where
     }
 }
 
+// NTT-domain values are converted to and from their canonical form at the encoding boundary
 impl<D: EncodingSize> Encode<D> for NttPolynomial {
     type EncodedSize = D::EncodedPolynomialSize;
 
     fn encode(&self) -> Array<u8, Self::EncodedSize> {
-        byte_encode::<D>(&self.0)
+        byte_encode::<D>(&self.into())
     }
 
     fn decode(enc: &Array<u8, Self::EncodedSize>) -> Self {
-        Self(byte_decode::<D>(enc))
+        byte_decode::<D>(enc).into()
     }
 }
 
@@ -170,6 +178,11 @@ pub(crate) mod test {
         }
     }
 
+    #[allow(
+        clippy::needless_pass_by_value,
+        clippy::large_types_passed_by_value,
+        clippy::default_trait_access
+    )]
     fn byte_codec_test<D>(decoded: DecodedValue, encoded: EncodedPolynomial<D>)
     where
         D: EncodingSize,
@@ -257,6 +270,7 @@ pub(crate) mod test {
         assert_eq!(actual_decoded, decoded);
     }
 
+    #[allow(clippy::needless_pass_by_value)]
     fn vector_codec_known_answer_test<D, T>(decoded: T, encoded: Array<u8, T::EncodedSize>)
     where
         D: EncodingSize,
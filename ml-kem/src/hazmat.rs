@@ -0,0 +1,66 @@
+//! ⚠️ These APIs provide no security guarantees on their own.  Combining them correctly is up to
+//! the caller; most applications should use [`KemCore`](crate::KemCore) instead.
+//!
+//! The module provides:
+//!
+//! * Arithmetic in `R_q` ([`Polynomial`], [`PolynomialVector`]) and in the NTT domain `T_q`
+//!   ([`NttPolynomial`], [`NttVector`], [`NttMatrix`]), including the NTT and its inverse.
+//! * Sampling, from the centered binomial distribution via [`PRF`] and uniformly via [`XOF`].
+//! * Compression ([`Compress`]) and byte encoding ([`Encode`]).
+//! * The hash functions [`G`], [`H`], and [`J`].
+//! * The K-PKE component scheme ([`EncryptionKey`], [`DecryptionKey`]), with its sizes and
+//!   encodings ([`PkeParams`]).  K-PKE is only IND-CPA secure, and its decryption is not
+//!   protected against chosen-ciphertext attacks; ML-KEM adds the Fujisaki-Okamoto transform on
+//!   top of it for that reason.  K-PKE is instantiated with the same parameter sets as ML-KEM,
+//!   e.g., [`MlKem768Params`](crate::MlKem768Params).
+//!
+//! NTT-domain values are held in an internal representation that is only exposed through
+//! conversions to and from canonical coefficients.
+//!
+//! ```
+//! # use ml_kem::hazmat::*;
+//! # use hybrid_array::{typenum::{U2, U10}, Array};
+//! // Sample two small polynomials and multiply them in the NTT domain
+//! let sigma = B32::default();
+//! let f = Polynomial::sample_cbd::<U2>(&PRF::<U2>(&sigma, 0));
+//! let g = Polynomial::sample_cbd::<U2>(&PRF::<U2>(&sigma, 1));
+//! let fg = (&f.ntt() * &g.ntt()).ntt_inverse();
+//!
+//! // Compress and encode the product, then decode and decompress it
+//! let mut c = fg;
+//! c.compress::<U10>();
+//! let enc = Encode::<U10>::encode(&c);
+//!
+//! let mut d: Polynomial = Encode::<U10>::decode(&enc);
+//! d.decompress::<U10>();
+//! ```
+//!
+//! [`Polynomial`]: crate::hazmat::Polynomial
+//! [`PolynomialVector`]: crate::hazmat::PolynomialVector
+//! [`NttPolynomial`]: crate::hazmat::NttPolynomial
+//! [`NttVector`]: crate::hazmat::NttVector
+//! [`NttMatrix`]: crate::hazmat::NttMatrix
+//! [`PRF`]: crate::hazmat::PRF
+//! [`XOF`]: crate::hazmat::XOF
+//! [`Compress`]: crate::hazmat::Compress
+//! [`Encode`]: crate::hazmat::Encode
+//! [`G`]: crate::hazmat::G
+//! [`H`]: crate::hazmat::H
+//! [`J`]: crate::hazmat::J
+//! [`EncryptionKey`]: crate::hazmat::EncryptionKey
+//! [`DecryptionKey`]: crate::hazmat::DecryptionKey
+//! [`PkeParams`]: crate::hazmat::PkeParams
+
+pub use crate::algebra::{
+    FieldElement, Integer, NttMatrix, NttPolynomial, NttVector, Polynomial, PolynomialVector,
+};
+pub use crate::compress::{Compress, CompressionFactor};
+pub use crate::crypto::{PrfOutput, G, H, J, PRF, XOF};
+pub use crate::encode::Encode;
+pub use crate::param::{
+    CbdSamplingSize, EncodedCiphertext, EncodedDecryptionKey, EncodedEncryptionKey,
+    EncodedNttVector, EncodedPolynomial, EncodedPolynomialSize, EncodedPolynomialVector,
+    EncodedPolynomialVectorSize, EncodedU, EncodedV, EncodingSize, PkeParams, VectorEncodingSize,
+};
+pub use crate::pke::{DecryptionKey, EncryptionKey};
+pub use crate::util::B32;
@@ -0,0 +1,460 @@
+//! These helpers follow draft-ietf-ipsecme-ikev2-mlkem.
+//!
+//! The initiator sends its encapsulation key in a KE payload, in `IKE_INTERMEDIATE` or
+//! `IKE_FOLLOWUP_KE`, and the responder replies with a KE payload carrying the ciphertext.  The
+//! resulting shared secret is `SK(n)` in the key update of RFC 9370:
+//!
+//! ```text
+//! SKEYSEED(n) = prf(SK_d(n-1), SK(n) | Ni | Nr)
+//! ```
+//!
+//! ```
+//! # use ml_kem::ikev2::{self, Initiator, KePayload};
+//! # use ml_kem::MlKem768Params;
+//! let mut rng = rand::thread_rng();
+//!
+//! let initiator = Initiator::<MlKem768Params>::new(&mut rng).unwrap();
+//! let mut request = [0u8; 1200];
+//! let len = initiator.ke_payload().encode(&mut request).unwrap();
+//!
+//! // The responder checks the payload against the negotiated transform
+//! let payload = KePayload::decode(&request[..len]).unwrap();
+//! let (response, sk_responder) = ikev2::respond::<MlKem768Params>(&payload, &mut rng).unwrap();
+//! let mut reply = [0u8; 1100];
+//! let len = response.ke_payload().encode(&mut reply).unwrap();
+//!
+//! let payload = KePayload::decode(&reply[..len]).unwrap();
+//! let sk_initiator = initiator.finish(&payload).unwrap();
+//! assert_eq!(sk_initiator, sk_responder);
+//! ```
+//!
+//! Encoded [`KePayload`]s are the payload body that follows the generic payload header, that
+//! is, the Key Exchange Method Num, two reserved bytes, and the Key Exchange Data.
+//!
+//! [`KePayload`]: crate::ikev2::KePayload
+
+use core::fmt;
+use rand_core::CryptoRngCore;
+
+use crate::kem::{decapsulate, encapsulate, DecapsulationKey, EncapsulationKey, Kem};
+use crate::param::{EncodedCiphertext, KemParams, StandardParams};
+use crate::util::B32;
+use crate::{Encoded, EncodedSizeUser, KemCore};
+
+/// The payload type of KE payloads
+pub const KE_PAYLOAD_TYPE: u8 = 34;
+
+/// The shared secret `SK(n)` of a key exchange
+pub type SharedSecret = B32;
+
+// The length of the Key Exchange Method Num and reserved fields
+const HEADER_LEN: usize = 4;
+
+/// An ML-KEM Key Exchange Method, i.e., a transform of type 4
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Method {
+    /// ML-KEM-512
+    MlKem512,
+
+    /// ML-KEM-768
+    MlKem768,
+
+    /// ML-KEM-1024
+    MlKem1024,
+}
+
+impl Method {
+    /// The transform ID
+    #[must_use]
+    pub const fn id(self) -> u16 {
+        match self {
+            Self::MlKem512 => 35,
+            Self::MlKem768 => 36,
+            Self::MlKem1024 => 37,
+        }
+    }
+
+    /// The method with the given transform ID
+    #[must_use]
+    pub const fn from_id(id: u16) -> Option<Self> {
+        match id {
+            35 => Some(Self::MlKem512),
+            36 => Some(Self::MlKem768),
+            37 => Some(Self::MlKem1024),
+            _ => None,
+        }
+    }
+
+    /// The length of the initiator's Key Exchange Data, an encapsulation key
+    #[must_use]
+    pub const fn encapsulation_key_len(self) -> usize {
+        match self {
+            Self::MlKem512 => 800,
+            Self::MlKem768 => 1184,
+            Self::MlKem1024 => 1568,
+        }
+    }
+
+    /// The length of the responder's Key Exchange Data, a ciphertext
+    #[must_use]
+    pub const fn ciphertext_len(self) -> usize {
+        match self {
+            Self::MlKem512 => 768,
+            Self::MlKem768 => 1088,
+            Self::MlKem1024 => 1568,
+        }
+    }
+
+    // The method of the parameter set `P`, if it is one of the standard ones
+    fn of<P: KemParams>() -> Result<Self, Error> {
+        match StandardParams::of::<P>() {
+            Some(StandardParams::MlKem512) => Ok(Self::MlKem512),
+            Some(StandardParams::MlKem768) => Ok(Self::MlKem768),
+            Some(StandardParams::MlKem1024) => Ok(Self::MlKem1024),
+            None => Err(Error::UnsupportedParameterSet),
+        }
+    }
+}
+
+/// The body of a KE payload
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KePayload<'a> {
+    /// The Key Exchange Method Num, a transform ID
+    pub method: u16,
+
+    /// The Key Exchange Data
+    pub data: &'a [u8],
+}
+
+impl<'a> KePayload<'a> {
+    /// Encode the payload body into `buf`, returning its length.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::BufferTooSmall`] if `buf` cannot hold the encoding.
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize, Error> {
+        let len = HEADER_LEN + self.data.len();
+        let buf = buf
+            .get_mut(..len)
+            .ok_or(Error::BufferTooSmall { needed: len })?;
+        buf[..2].copy_from_slice(&self.method.to_be_bytes());
+        buf[2..HEADER_LEN].fill(0);
+        buf[HEADER_LEN..].copy_from_slice(self.data);
+        Ok(len)
+    }
+
+    /// Decode a payload body.  The reserved field is ignored, as RFC 7296 requires.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Length`] if the body is too short to hold the fixed fields.
+    pub fn decode(body: &'a [u8]) -> Result<Self, Error> {
+        if body.len() < HEADER_LEN {
+            return Err(Error::Length);
+        }
+        Ok(Self {
+            method: u16::from_be_bytes([body[0], body[1]]),
+            data: &body[HEADER_LEN..],
+        })
+    }
+
+    // Check the payload against the negotiated method and the expected data length
+    fn check(&self, method: Method, data_len: fn(Method) -> usize) -> Result<(), Error> {
+        if self.method != method.id() {
+            return Err(Error::Method);
+        }
+        if self.data.len() != data_len(method) {
+            return Err(Error::Length);
+        }
+        Ok(())
+    }
+}
+
+/// The initiator of an ML-KEM key exchange, holding its ephemeral decapsulation key until the
+/// responder's KE payload arrives
+pub struct Initiator<P: KemParams> {
+    method: Method,
+    dk: DecapsulationKey<P>,
+    ek: Encoded<EncapsulationKey<P>>,
+}
+
+impl<P: KemParams> Initiator<P> {
+    /// Generate a fresh key pair for the exchange.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::UnsupportedParameterSet`] if `P` is not one of the standard ML-KEM
+    /// parameter sets.
+    pub fn new(rng: &mut impl CryptoRngCore) -> Result<Self, Error> {
+        let method = Method::of::<P>()?;
+        let (dk, ek) = Kem::<P>::generate(rng);
+        Ok(Self {
+            method,
+            dk,
+            ek: ek.as_bytes(),
+        })
+    }
+
+    /// The initiator's KE payload, carrying its encapsulation key
+    #[must_use]
+    pub fn ke_payload(&self) -> KePayload<'_> {
+        KePayload {
+            method: self.method.id(),
+            data: &self.ek,
+        }
+    }
+
+    /// Decapsulate the responder's KE payload, returning the shared secret.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Method`] if the payload is for a different method, and [`Error::Length`]
+    /// if its data is not a ciphertext of the right length.
+    pub fn finish(self, payload: &KePayload<'_>) -> Result<SharedSecret, Error> {
+        payload.check(self.method, Method::ciphertext_len)?;
+        let ct = EncodedCiphertext::<P>::try_from(payload.data).map_err(|_| Error::Length)?;
+        let ss = decapsulate(&self.dk, &ct);
+        Ok(ss)
+    }
+}
+
+impl<P: KemParams> fmt::Debug for Initiator<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Initiator")
+            .field("method", &self.method)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The responder's reply to an ML-KEM key exchange
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Response<P: KemParams> {
+    method: Method,
+    ct: EncodedCiphertext<P>,
+}
+
+impl<P: KemParams> Response<P> {
+    /// The responder's KE payload, carrying the ciphertext
+    #[must_use]
+    pub fn ke_payload(&self) -> KePayload<'_> {
+        KePayload {
+            method: self.method.id(),
+            data: &self.ct,
+        }
+    }
+}
+
+/// Encapsulate to the encapsulation key in the initiator's KE payload, returning the response
+/// and the shared secret.
+///
+/// # Errors
+///
+/// Returns [`Error::Method`] if the payload is for a different method, [`Error::Length`] if its
+/// data is not an encapsulation key of the right length, and [`Error::InvalidKey`] if the
+/// encapsulation key fails the modulus check of FIPS 203.  Returns
+/// [`Error::UnsupportedParameterSet`] if `P` is not one of the standard ML-KEM parameter sets.
+pub fn respond<P: KemParams>(
+    payload: &KePayload<'_>,
+    rng: &mut impl CryptoRngCore,
+) -> Result<(Response<P>, SharedSecret), Error> {
+    let method = Method::of::<P>()?;
+    payload.check(method, Method::encapsulation_key_len)?;
+    let ek = Encoded::<EncapsulationKey<P>>::try_from(payload.data).map_err(|_| Error::Length)?;
+    let ek = EncapsulationKey::<P>::from_bytes_checked(&ek).ok_or(Error::InvalidKey)?;
+
+    let (ct, ss) = encapsulate(&ek, rng);
+    Ok((Response { method, ct }, ss))
+}
+
+/// An error in an ML-KEM key exchange
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The payload is for a different Key Exchange Method than the one negotiated
+    Method,
+
+    /// The Key Exchange Data has the wrong length for the method
+    Length,
+
+    /// The encapsulation key fails the modulus check of FIPS 203
+    InvalidKey,
+
+    /// The parameter set is not one of the standard ML-KEM parameter sets, which are the only
+    /// ones with a Key Exchange Method
+    UnsupportedParameterSet,
+
+    /// The output buffer is too small
+    BufferTooSmall {
+        /// The length of buffer that is needed
+        needed: usize,
+    },
+}
+
+impl Error {
+    /// The Notify Message Type with which a responder reports the error to the initiator:
+    /// `INVALID_KE_PAYLOAD` (17) for the wrong method, and `INVALID_SYNTAX` (7) for malformed
+    /// Key Exchange Data.  An initiator that receives a malformed response instead abandons the
+    /// exchange.
+    #[must_use]
+    pub fn notify_type(&self) -> Option<u16> {
+        match self {
+            Self::Method => Some(17),
+            Self::Length | Self::InvalidKey => Some(7),
+            Self::UnsupportedParameterSet | Self::BufferTooSmall { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Method => f.write_str("unexpected Key Exchange Method"),
+            Self::Length => f.write_str("Key Exchange Data has the wrong length"),
+            Self::InvalidKey => f.write_str("invalid ML-KEM encapsulation key"),
+            Self::UnsupportedParameterSet => {
+                f.write_str("parameter set has no Key Exchange Method")
+            }
+            Self::BufferTooSmall { needed } => write!(f, "buffer too small, {needed} bytes needed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MlKem1024Params, MlKem512Params, MlKem768Params, ParameterSet};
+    use hybrid_array::typenum::{Unsigned, U10, U2, U3, U4};
+
+    #[test]
+    fn lengths() {
+        for id in 35..=37 {
+            let method = Method::from_id(id).unwrap();
+            assert_eq!(method.id(), id);
+        }
+        assert_eq!(Method::from_id(31), None);
+
+        lengths_test::<MlKem512Params>();
+        lengths_test::<MlKem768Params>();
+        lengths_test::<MlKem1024Params>();
+    }
+
+    fn lengths_test<P: KemParams>() {
+        let method = Method::of::<P>().unwrap();
+        assert_eq!(
+            method.encapsulation_key_len(),
+            <EncapsulationKey<P> as EncodedSizeUser>::EncodedSize::USIZE
+        );
+        assert_eq!(method.ciphertext_len(), P::CiphertextSize::USIZE);
+    }
+
+    #[test]
+    fn encoding() {
+        let payload = KePayload {
+            method: 36,
+            data: &[0xaa, 0xbb],
+        };
+        let mut buf = [0xff; 6];
+        assert_eq!(payload.encode(&mut buf), Ok(6));
+        assert_eq!(buf, [0x00, 0x24, 0x00, 0x00, 0xaa, 0xbb]);
+        assert_eq!(KePayload::decode(&buf), Ok(payload));
+        assert_eq!(
+            payload.encode(&mut buf[..5]),
+            Err(Error::BufferTooSmall { needed: 6 })
+        );
+
+        // The reserved field is ignored
+        buf[2] = 0x12;
+        assert_eq!(KePayload::decode(&buf), Ok(payload));
+        assert_eq!(KePayload::decode(&buf[..3]), Err(Error::Length));
+    }
+
+    fn round_trip_test<P: KemParams>() {
+        let mut rng = rand::thread_rng();
+        let initiator = Initiator::<P>::new(&mut rng).unwrap();
+        let request = initiator.ke_payload();
+        let method = Method::of::<P>().unwrap();
+        assert_eq!(request.method, method.id());
+
+        let (response, sk_responder) = respond::<P>(&request, &mut rng).unwrap();
+        let reply = response.ke_payload();
+        assert_eq!(reply.method, request.method);
+        assert_eq!(reply.data.len(), method.ciphertext_len());
+
+        // The initiator checks the method and length of the reply
+        let wrong_method = KePayload {
+            method: 31,
+            data: reply.data,
+        };
+        let short = KePayload {
+            method: reply.method,
+            data: &reply.data[1..],
+        };
+        let other = Initiator::<P>::new(&mut rng).unwrap();
+        assert_eq!(other.finish(&wrong_method), Err(Error::Method));
+        let other = Initiator::<P>::new(&mut rng).unwrap();
+        assert_eq!(other.finish(&short), Err(Error::Length));
+
+        assert_eq!(initiator.finish(&reply), Ok(sk_responder));
+    }
+
+    #[test]
+    fn round_trip() {
+        round_trip_test::<MlKem512Params>();
+        round_trip_test::<MlKem768Params>();
+        round_trip_test::<MlKem1024Params>();
+    }
+
+    #[test]
+    fn invalid_requests() {
+        let mut rng = rand::thread_rng();
+        let initiator = Initiator::<MlKem768Params>::new(&mut rng).unwrap();
+        let request = initiator.ke_payload();
+
+        // A different method than the negotiated one
+        let err = respond::<MlKem1024Params>(&request, &mut rng).unwrap_err();
+        assert_eq!(err, Error::Method);
+        assert_eq!(err.notify_type(), Some(17));
+
+        // Truncated data, and an encapsulation key that fails the modulus check
+        let short = KePayload {
+            method: request.method,
+            data: &request.data[..1183],
+        };
+        let err = respond::<MlKem768Params>(&short, &mut rng).unwrap_err();
+        assert_eq!(err, Error::Length);
+        assert_eq!(err.notify_type(), Some(7));
+
+        let invalid = KePayload {
+            method: request.method,
+            data: &[0xff; 1184],
+        };
+        let err = respond::<MlKem768Params>(&invalid, &mut rng).unwrap_err();
+        assert_eq!(err, Error::InvalidKey);
+        assert_eq!(err.notify_type(), Some(7));
+    }
+
+    // A parameter set that has no Key Exchange Method, although it shares `k` with ML-KEM-768
+    #[derive(Default, Clone, Debug, PartialEq)]
+    struct NonstandardParams;
+
+    impl ParameterSet for NonstandardParams {
+        type K = U3;
+        type Eta1 = U3;
+        type Eta2 = U2;
+        type Du = U10;
+        type Dv = U4;
+    }
+
+    #[test]
+    fn nonstandard_parameter_set() {
+        let mut rng = rand::thread_rng();
+        let err = Initiator::<NonstandardParams>::new(&mut rng).unwrap_err();
+        assert_eq!(err, Error::UnsupportedParameterSet);
+        assert_eq!(err.notify_type(), None);
+
+        let initiator = Initiator::<MlKem1024Params>::new(&mut rng).unwrap();
+        assert_eq!(
+            respond::<NonstandardParams>(&initiator.ke_payload(), &mut rng),
+            Err(Error::UnsupportedParameterSet)
+        );
+    }
+}
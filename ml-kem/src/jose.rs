@@ -0,0 +1,789 @@
+//! This module follows draft-ietf-jose-pqc-kem.
+//!
+//! # Keys
+//!
+//! Keys use the `AKP` ("algorithm key pair") key type, in which the key's algorithm determines
+//! the format of the `pub` and `priv` members.  For ML-KEM, `pub` is the encoded encapsulation
+//! key, and `priv` is the 64-byte seed `d || z` from which the key pair is generated.  The
+//! decapsulation key cannot be recovered as a seed, so a private JWK is created from the seed.
+//!
+//! ```
+//! # use ml_kem::jose::{Algorithm, Jwk};
+//! # use ml_kem::MlKem768Params;
+//! let (d, z) = ([1; 32].into(), [2; 32].into());
+//! let jwk = Jwk::private::<MlKem768Params>(&d, &z, Algorithm::MlKem768A192Kw).unwrap();
+//!
+//! let json = jwk.to_public().to_json();
+//! assert!(json.starts_with(r#"{"kty":"AKP","alg":"MLKEM768+A192KW","pub":""#));
+//!
+//! let dk = jwk.decapsulation_key::<MlKem768Params>().unwrap();
+//! let ek = Jwk::from_json(&json).unwrap().encapsulation_key::<MlKem768Params>().unwrap();
+//! assert_eq!(dk.encapsulation_key(), &ek);
+//! ```
+//!
+//! # Key management
+//!
+//! The `alg` values mirror those of ECDH-ES: With direct key agreement (`MLKEM768`), the
+//! content-encryption key (CEK) is derived from the ML-KEM shared key, and the JWE Encrypted Key
+//! is empty.  With key agreement with key wrapping (`MLKEM768+A192KW`), a random CEK is wrapped
+//! with a key-encryption key derived from the shared key.  In both cases, the ML-KEM ciphertext
+//! is sent in the `ek` header parameter, and keys are derived with the Concat KDF of RFC 7518,
+//! Section 4.6.2, taking `AlgorithmID` to be `enc` for direct key agreement and `alg` otherwise.
+//!
+//! ```
+//! # use ml_kem::jose::{self, Algorithm, Parameters};
+//! # use ml_kem::{KemCore, MlKem768};
+//! let mut rng = rand::thread_rng();
+//! let (dk, ek) = MlKem768::generate(&mut rng);
+//!
+//! let params = Parameters {
+//!     alg: Algorithm::MlKem768A192Kw,
+//!     enc: "A256GCM",
+//!     apu: b"",
+//!     apv: b"",
+//! };
+//! let encapsulated = jose::encrypt_cek(&ek, &params, &mut rng).unwrap();
+//!
+//! // ... the sender encrypts the content with `encapsulated.cek`, and sends `encapsulated.ek` in
+//! // the protected header and `encapsulated.encrypted_key` as the JWE Encrypted Key
+//!
+//! let cek = jose::decrypt_cek(&dk, &params, &encapsulated.ek, &encapsulated.encrypted_key);
+//! assert_eq!(cek.unwrap(), encapsulated.cek);
+//! ```
+//!
+//! The identifiers and key derivation follow a draft specification, and may change before it
+//! is published.
+
+use aes_kw::{KekAes128, KekAes192, KekAes256};
+use alloc::{string::String, vec, vec::Vec};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use core::{fmt, str::FromStr};
+use rand_core::CryptoRngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::kem::{decapsulate, encapsulate, DecapsulationKey, EncapsulationKey};
+use crate::param::{EncodedCiphertext, KemParams, StandardParams};
+use crate::util::B32;
+use crate::{Encoded, EncodedSizeUser};
+
+/// The JWK key type for ML-KEM keys
+pub const KEY_TYPE: &str = "AKP";
+
+/// A JWE key management algorithm using ML-KEM
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// `MLKEM512`: Direct key agreement with ML-KEM-512
+    MlKem512,
+
+    /// `MLKEM768`: Direct key agreement with ML-KEM-768
+    MlKem768,
+
+    /// `MLKEM1024`: Direct key agreement with ML-KEM-1024
+    MlKem1024,
+
+    /// `MLKEM512+A128KW`: Key agreement with ML-KEM-512, wrapping the CEK with AES-128 key wrap
+    MlKem512A128Kw,
+
+    /// `MLKEM768+A192KW`: Key agreement with ML-KEM-768, wrapping the CEK with AES-192 key wrap
+    MlKem768A192Kw,
+
+    /// `MLKEM1024+A256KW`: Key agreement with ML-KEM-1024, wrapping the CEK with AES-256 key
+    /// wrap
+    MlKem1024A256Kw,
+}
+
+impl Algorithm {
+    const ALL: [Self; 6] = [
+        Self::MlKem512,
+        Self::MlKem768,
+        Self::MlKem1024,
+        Self::MlKem512A128Kw,
+        Self::MlKem768A192Kw,
+        Self::MlKem1024A256Kw,
+    ];
+
+    /// The `alg` identifier of the algorithm
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::MlKem512 => "MLKEM512",
+            Self::MlKem768 => "MLKEM768",
+            Self::MlKem1024 => "MLKEM1024",
+            Self::MlKem512A128Kw => "MLKEM512+A128KW",
+            Self::MlKem768A192Kw => "MLKEM768+A192KW",
+            Self::MlKem1024A256Kw => "MLKEM1024+A256KW",
+        }
+    }
+
+    // The ML-KEM parameter set
+    fn params(self) -> StandardParams {
+        match self {
+            Self::MlKem512 | Self::MlKem512A128Kw => StandardParams::MlKem512,
+            Self::MlKem768 | Self::MlKem768A192Kw => StandardParams::MlKem768,
+            Self::MlKem1024 | Self::MlKem1024A256Kw => StandardParams::MlKem1024,
+        }
+    }
+
+    // The key-encryption key length, or `None` for direct key agreement
+    fn kek_len(self) -> Option<usize> {
+        match self {
+            Self::MlKem512 | Self::MlKem768 | Self::MlKem1024 => None,
+            Self::MlKem512A128Kw => Some(16),
+            Self::MlKem768A192Kw => Some(24),
+            Self::MlKem1024A256Kw => Some(32),
+        }
+    }
+
+    fn check<P: KemParams>(self) -> Result<(), Error> {
+        if StandardParams::of::<P>() == Some(self.params()) {
+            Ok(())
+        } else {
+            Err(Error::WrongParameterSet)
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Error> {
+        Self::ALL
+            .into_iter()
+            .find(|alg| alg.name() == name)
+            .ok_or(Error::UnsupportedAlgorithm)
+    }
+}
+
+/// An ML-KEM key in the JWK format
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Jwk {
+    /// The algorithm with which the key is used, which determines its parameter set
+    pub alg: Algorithm,
+
+    /// The encoded encapsulation key (`pub`)
+    pub public: Vec<u8>,
+
+    /// The seed `d || z` of the key pair (`priv`), for a private key
+    pub seed: Option<[u8; 64]>,
+
+    /// The key identifier (`kid`)
+    pub kid: Option<String>,
+}
+
+impl Jwk {
+    /// A public JWK for the encapsulation key `ek`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::WrongParameterSet`] if `alg` is for a different parameter set.
+    pub fn public<P: KemParams>(ek: &EncapsulationKey<P>, alg: Algorithm) -> Result<Self, Error> {
+        alg.check::<P>()?;
+        Ok(Self {
+            alg,
+            public: ek.as_bytes().to_vec(),
+            seed: None,
+            kid: None,
+        })
+    }
+
+    /// A private JWK for the key pair generated from the seeds `d` and `z`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::WrongParameterSet`] if `alg` is for a different parameter set.
+    pub fn private<P: KemParams>(d: &B32, z: &B32, alg: Algorithm) -> Result<Self, Error> {
+        alg.check::<P>()?;
+        let dk = DecapsulationKey::<P>::generate_deterministic(d, z);
+
+        let mut seed = [0u8; 64];
+        seed[..32].copy_from_slice(d);
+        seed[32..].copy_from_slice(z);
+        Ok(Self {
+            alg,
+            public: dk.encapsulation_key().as_bytes().to_vec(),
+            seed: Some(seed),
+            kid: None,
+        })
+    }
+
+    /// This JWK without the private key
+    #[must_use]
+    pub fn to_public(&self) -> Self {
+        Self {
+            seed: None,
+            ..self.clone()
+        }
+    }
+
+    /// The encapsulation key of this JWK.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JWK is for a different parameter set, or if the key is not a valid
+    /// encoding.
+    pub fn encapsulation_key<P: KemParams>(&self) -> Result<EncapsulationKey<P>, Error> {
+        self.alg.check::<P>()?;
+        let bytes = Encoded::<EncapsulationKey<P>>::try_from(self.public.as_slice())
+            .map_err(|_| Error::InvalidKey)?;
+        EncapsulationKey::from_bytes_checked(&bytes).ok_or(Error::InvalidKey)
+    }
+
+    /// The decapsulation key of this JWK, generated from its seed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JWK is for a different parameter set, if it has no private key, or
+    /// if its public key does not correspond to the private key.
+    pub fn decapsulation_key<P: KemParams>(&self) -> Result<DecapsulationKey<P>, Error> {
+        self.alg.check::<P>()?;
+        let seed = self.seed.as_ref().ok_or(Error::MissingPrivateKey)?;
+        let d = B32::from_fn(|i| seed[i]);
+        let z = B32::from_fn(|i| seed[32 + i]);
+        let dk = DecapsulationKey::<P>::generate_deterministic(&d, &z);
+
+        if dk.encapsulation_key().as_bytes().as_slice() != self.public {
+            return Err(Error::InconsistentKey);
+        }
+        Ok(dk)
+    }
+
+    /// Serialize this JWK as JSON
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)] // Serializing strings to JSON cannot fail
+    pub fn to_json(&self) -> String {
+        let json = JwkJson {
+            kty: KEY_TYPE.into(),
+            alg: self.alg.name().into(),
+            public: URL_SAFE_NO_PAD.encode(&self.public),
+            private: self.seed.map(|seed| URL_SAFE_NO_PAD.encode(seed)),
+            kid: self.kid.clone(),
+        };
+        serde_json::to_string(&json).expect("JWKs serialize to JSON")
+    }
+
+    /// Parse a JWK from JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JSON is malformed, or if the JWK is not an ML-KEM key.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let json: JwkJson = serde_json::from_str(json).map_err(|_| Error::Json)?;
+        if json.kty != KEY_TYPE {
+            return Err(Error::UnsupportedKeyType);
+        }
+
+        let seed = json
+            .private
+            .map(|private| {
+                let seed = decode_base64(&private)?;
+                <[u8; 64]>::try_from(seed).map_err(|_| Error::InvalidKey)
+            })
+            .transpose()?;
+
+        Ok(Self {
+            alg: json.alg.parse()?,
+            public: decode_base64(&json.public)?,
+            seed,
+            kid: json.kid,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct JwkJson {
+    kty: String,
+    alg: String,
+    #[serde(rename = "pub")]
+    public: String,
+    #[serde(rename = "priv", default, skip_serializing_if = "Option::is_none")]
+    private: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+}
+
+/// The JWE header parameters that determine how the CEK is established
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parameters<'a> {
+    /// The key management algorithm (`alg`)
+    pub alg: Algorithm,
+
+    /// The content encryption algorithm (`enc`), which determines the CEK length
+    pub enc: &'a str,
+
+    /// The agreement `PartyUInfo` (`apu`), decoded from base64url
+    pub apu: &'a [u8],
+
+    /// The agreement `PartyVInfo` (`apv`), decoded from base64url
+    pub apv: &'a [u8],
+}
+
+/// The result of establishing a CEK for a recipient
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyEncapsulation {
+    /// The base64url-encoded ML-KEM ciphertext, for the `ek` header parameter
+    pub ek: String,
+
+    /// The content-encryption key
+    pub cek: Vec<u8>,
+
+    /// The JWE Encrypted Key, which is empty for direct key agreement
+    pub encrypted_key: Vec<u8>,
+}
+
+/// Establish a CEK for the holder of the decapsulation key corresponding to `ek`.
+///
+/// # Errors
+///
+/// Returns an error if `params.alg` is for a different parameter set, or if `params.enc` is not
+/// a known content encryption algorithm.
+pub fn encrypt_cek<P: KemParams>(
+    ek: &EncapsulationKey<P>,
+    params: &Parameters<'_>,
+    rng: &mut impl CryptoRngCore,
+) -> Result<KeyEncapsulation, Error> {
+    params.alg.check::<P>()?;
+    let cek_len = cek_len(params.enc)?;
+    let (ct, z) = encapsulate(ek, rng);
+
+    let (cek, encrypted_key) = match params.alg.kek_len() {
+        None => (concat_kdf(&z, params.enc, cek_len, params), Vec::new()),
+        Some(kek_len) => {
+            let kek = concat_kdf(&z, params.alg.name(), kek_len, params);
+            let mut cek = vec![0u8; cek_len];
+            rng.fill_bytes(&mut cek);
+            let encrypted_key = key_wrap(&kek, &cek, true)?;
+            (cek, encrypted_key)
+        }
+    };
+
+    Ok(KeyEncapsulation {
+        ek: URL_SAFE_NO_PAD.encode(ct),
+        cek,
+        encrypted_key,
+    })
+}
+
+/// Recover the CEK from the `ek` header parameter and the JWE Encrypted Key.
+///
+/// # Errors
+///
+/// Returns an error if the parameters are not supported, if the ciphertext is malformed, or if
+/// the encrypted key does not unwrap.  With direct key agreement, a wrong key or ciphertext
+/// yields a wrong CEK, so that content decryption fails.
+pub fn decrypt_cek<P: KemParams>(
+    dk: &DecapsulationKey<P>,
+    params: &Parameters<'_>,
+    ek: &str,
+    encrypted_key: &[u8],
+) -> Result<Vec<u8>, Error> {
+    params.alg.check::<P>()?;
+    let cek_len = cek_len(params.enc)?;
+    let ct = EncodedCiphertext::<P>::try_from(decode_base64(ek)?.as_slice())
+        .map_err(|_| Error::Malformed)?;
+    let z = decapsulate(dk, &ct);
+
+    match params.alg.kek_len() {
+        None if encrypted_key.is_empty() => Ok(concat_kdf(&z, params.enc, cek_len, params)),
+        None => Err(Error::Malformed),
+        Some(kek_len) => {
+            let kek = concat_kdf(&z, params.alg.name(), kek_len, params);
+            let cek = key_wrap(&kek, encrypted_key, false)?;
+            if cek.len() == cek_len {
+                Ok(cek)
+            } else {
+                Err(Error::KeyWrap)
+            }
+        }
+    }
+}
+
+// The CEK length of a content encryption algorithm from RFC 7518
+fn cek_len(enc: &str) -> Result<usize, Error> {
+    match enc {
+        "A128GCM" => Ok(16),
+        "A192GCM" => Ok(24),
+        "A256GCM" | "A128CBC-HS256" => Ok(32),
+        "A192CBC-HS384" => Ok(48),
+        "A256CBC-HS512" => Ok(64),
+        _ => Err(Error::UnsupportedEnc),
+    }
+}
+
+// The Concat KDF with SHA-256, as used by ECDH-ES in RFC 7518, Section 4.6.2
+fn concat_kdf(z: &[u8], algorithm_id: &str, key_len: usize, params: &Parameters<'_>) -> Vec<u8> {
+    let key_bits = u32::try_from(key_len * 8).expect("keys are short");
+    let mut key = Vec::with_capacity(key_len.next_multiple_of(32));
+    for counter in 1u32.. {
+        if key.len() >= key_len {
+            break;
+        }
+
+        let mut hash = Sha256::new();
+        hash.update(counter.to_be_bytes());
+        hash.update(z);
+        for field in [algorithm_id.as_bytes(), params.apu, params.apv] {
+            let len = u32::try_from(field.len()).expect("fields are shorter than 4 GiB");
+            hash.update(len.to_be_bytes());
+            hash.update(field);
+        }
+        hash.update(key_bits.to_be_bytes());
+        key.extend_from_slice(&hash.finalize());
+    }
+
+    key.truncate(key_len);
+    key
+}
+
+// Wrap or unwrap `data` with AES key wrap, with the key size given by `kek`
+fn key_wrap(kek: &[u8], data: &[u8], wrap: bool) -> Result<Vec<u8>, Error> {
+    match (kek.len(), wrap) {
+        (16, true) => KekAes128::try_from(kek).and_then(|kek| kek.wrap_vec(data)),
+        (16, false) => KekAes128::try_from(kek).and_then(|kek| kek.unwrap_vec(data)),
+        (24, true) => KekAes192::try_from(kek).and_then(|kek| kek.wrap_vec(data)),
+        (24, false) => KekAes192::try_from(kek).and_then(|kek| kek.unwrap_vec(data)),
+        (_, true) => KekAes256::try_from(kek).and_then(|kek| kek.wrap_vec(data)),
+        (_, false) => KekAes256::try_from(kek).and_then(|kek| kek.unwrap_vec(data)),
+    }
+    .map_err(|_| Error::KeyWrap)
+}
+
+fn decode_base64(value: &str) -> Result<Vec<u8>, Error> {
+    URL_SAFE_NO_PAD.decode(value).map_err(|_| Error::Base64)
+}
+
+/// An error in processing a JWK or a JWE
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The JWK is not valid JSON, or is missing a required member
+    Json,
+
+    /// A member is not valid base64url
+    Base64,
+
+    /// The JWK has a key type other than [`KEY_TYPE`]
+    UnsupportedKeyType,
+
+    /// The algorithm is not an ML-KEM algorithm
+    UnsupportedAlgorithm,
+
+    /// The algorithm is for a different ML-KEM parameter set
+    WrongParameterSet,
+
+    /// A key has the wrong length, or the encapsulation key fails the modulus check of FIPS 203
+    InvalidKey,
+
+    /// The JWK has no private key
+    MissingPrivateKey,
+
+    /// The public key of the JWK does not correspond to its private key
+    InconsistentKey,
+
+    /// The content encryption algorithm is unknown
+    UnsupportedEnc,
+
+    /// The ML-KEM ciphertext has the wrong length, or an encrypted key was given for direct key
+    /// agreement
+    Malformed,
+
+    /// The CEK could not be wrapped or unwrapped
+    KeyWrap,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Json => "invalid JWK JSON",
+            Self::Base64 => "invalid base64url",
+            Self::UnsupportedKeyType => "unsupported key type",
+            Self::UnsupportedAlgorithm => "unsupported algorithm",
+            Self::WrongParameterSet => "algorithm is for a different parameter set",
+            Self::InvalidKey => "invalid key",
+            Self::MissingPrivateKey => "JWK has no private key",
+            Self::InconsistentKey => "public key does not match private key",
+            Self::UnsupportedEnc => "unsupported content encryption algorithm",
+            Self::Malformed => "malformed ML-KEM ciphertext or encrypted key",
+            Self::KeyWrap => "AES key wrap failed",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kem::Kem;
+    use crate::{KemCore, MlKem1024Params, MlKem512Params, MlKem768Params};
+    use alloc::{format, string::ToString};
+    use hex_literal::hex;
+
+    #[test]
+    fn concat_kdf_rfc7518() {
+        // The ECDH-ES example from RFC 7518, Appendix C
+        let z = [
+            158, 86, 217, 29, 129, 113, 53, 211, 114, 131, 66, 131, 191, 132, 38, 156, 251, 49,
+            110, 163, 218, 128, 106, 72, 246, 218, 167, 121, 140, 254, 144, 196,
+        ];
+        let params = Parameters {
+            alg: Algorithm::MlKem768,
+            enc: "A128GCM",
+            apu: b"Alice",
+            apv: b"Bob",
+        };
+        let key = concat_kdf(&z, "A128GCM", 16, &params);
+        assert_eq!(URL_SAFE_NO_PAD.encode(key), "VqqN6vgjbSBcIijNcacQGg");
+    }
+
+    #[test]
+    fn algorithm_names() {
+        for alg in Algorithm::ALL {
+            assert_eq!(alg.name().parse(), Ok(alg));
+            assert_eq!(alg.to_string(), alg.name());
+        }
+        assert_eq!(
+            "ECDH-ES".parse::<Algorithm>(),
+            Err(Error::UnsupportedAlgorithm)
+        );
+    }
+
+    fn jwk_test<P: KemParams>(algs: [Algorithm; 2]) {
+        let (d, z) = ([1; 32].into(), [2; 32].into());
+        let dk = DecapsulationKey::<P>::generate_deterministic(&d, &z);
+        let ek = dk.encapsulation_key().clone();
+
+        for alg in algs {
+            let private = Jwk::private::<P>(&d, &z, alg).unwrap();
+            let public = Jwk::public(&ek, alg).unwrap();
+            assert_eq!(private.to_public(), public);
+
+            // Through JSON
+            for jwk in [&private, &public] {
+                assert_eq!(&Jwk::from_json(&jwk.to_json()).unwrap(), jwk);
+            }
+            let mut with_kid = public.clone();
+            with_kid.kid = Some("key-1".into());
+            let json = with_kid.to_json();
+            assert!(json.ends_with(r#","kid":"key-1"}"#));
+            assert_eq!(Jwk::from_json(&json).unwrap(), with_kid);
+
+            assert_eq!(private.decapsulation_key::<P>().unwrap(), dk);
+            assert_eq!(private.encapsulation_key::<P>().unwrap(), ek);
+            assert_eq!(public.encapsulation_key::<P>().unwrap(), ek);
+            assert_eq!(
+                public.decapsulation_key::<P>(),
+                Err(Error::MissingPrivateKey)
+            );
+
+            // A public key that does not match the seed
+            let mut bad = private.clone();
+            bad.public[0] ^= 1;
+            assert_eq!(bad.decapsulation_key::<P>(), Err(Error::InconsistentKey));
+        }
+    }
+
+    #[test]
+    fn jwk() {
+        use Algorithm::*;
+        jwk_test::<MlKem512Params>([MlKem512, MlKem512A128Kw]);
+        jwk_test::<MlKem768Params>([MlKem768, MlKem768A192Kw]);
+        jwk_test::<MlKem1024Params>([MlKem1024, MlKem1024A256Kw]);
+    }
+
+    #[test]
+    fn invalid_jwk() {
+        let (d, z) = ([1; 32].into(), [2; 32].into());
+        let jwk = Jwk::private::<MlKem768Params>(&d, &z, Algorithm::MlKem768).unwrap();
+        let json = jwk.to_json();
+
+        assert_eq!(
+            Jwk::private::<MlKem512Params>(&d, &z, Algorithm::MlKem768),
+            Err(Error::WrongParameterSet)
+        );
+        assert_eq!(
+            jwk.encapsulation_key::<MlKem1024Params>(),
+            Err(Error::WrongParameterSet)
+        );
+
+        let replace = |from: &str, to: &str| Jwk::from_json(&json.replacen(from, to, 1));
+        assert_eq!(replace("AKP", "OKP"), Err(Error::UnsupportedKeyType));
+        assert_eq!(
+            replace("MLKEM768", "ML-KEM-768"),
+            Err(Error::UnsupportedAlgorithm)
+        );
+        assert_eq!(replace(r#""pub""#, r#""x""#), Err(Error::Json));
+        assert_eq!(replace(r#""pub":""#, r#""pub":"*"#), Err(Error::Base64));
+        assert_eq!(
+            replace(r#""priv":""#, r#""priv":"AAAA"#),
+            Err(Error::InvalidKey)
+        );
+        assert_eq!(Jwk::from_json("{"), Err(Error::Json));
+
+        // A truncated key, and a coefficient of at least q
+        let mut bad = jwk.to_public();
+        bad.public.pop();
+        assert_eq!(
+            bad.encapsulation_key::<MlKem768Params>(),
+            Err(Error::InvalidKey)
+        );
+        let mut bad = jwk.to_public();
+        bad.public[0] = 0xff;
+        bad.public[1] |= 0x0f;
+        assert_eq!(
+            bad.encapsulation_key::<MlKem768Params>(),
+            Err(Error::InvalidKey)
+        );
+    }
+
+    fn jwe_test<P: KemParams>(algs: [Algorithm; 2]) {
+        let mut rng = rand::thread_rng();
+        let (dk, ek) = Kem::<P>::generate(&mut rng);
+        let (dk2, _) = Kem::<P>::generate(&mut rng);
+
+        for alg in algs {
+            for (enc, cek_len) in [("A128GCM", 16), ("A256CBC-HS512", 64)] {
+                let params = Parameters {
+                    alg,
+                    enc,
+                    apu: b"Alice",
+                    apv: b"Bob",
+                };
+                let encapsulated = encrypt_cek(&ek, &params, &mut rng).unwrap();
+                assert_eq!(encapsulated.cek.len(), cek_len);
+                assert_eq!(
+                    encapsulated.encrypted_key.is_empty(),
+                    alg.kek_len().is_none()
+                );
+
+                let decrypt =
+                    |dk: &DecapsulationKey<P>, params: &Parameters<'_>, encrypted_key: &[u8]| {
+                        decrypt_cek(dk, params, &encapsulated.ek, encrypted_key)
+                    };
+                let encrypted_key = encapsulated.encrypted_key.as_slice();
+                assert_eq!(
+                    decrypt(&dk, &params, encrypted_key),
+                    Ok(encapsulated.cek.clone())
+                );
+
+                // A different recipient or party information yields a different CEK, or fails to
+                // unwrap it
+                let other = Parameters {
+                    apv: b"Eve",
+                    ..params
+                };
+                for (dk, params) in [(&dk2, &params), (&dk, &other)] {
+                    match decrypt(dk, params, encrypted_key) {
+                        Ok(cek) => assert_ne!(cek, encapsulated.cek),
+                        Err(err) => assert_eq!(err, Error::KeyWrap),
+                    }
+                }
+
+                // The encrypted key must be empty for direct key agreement
+                if alg.kek_len().is_none() {
+                    assert_eq!(decrypt(&dk, &params, &[0; 24]), Err(Error::Malformed));
+                }
+            }
+        }
+
+        let params = Parameters {
+            alg: algs[0],
+            enc: "A256GCM",
+            apu: b"",
+            apv: b"",
+        };
+        let encapsulated = encrypt_cek(&ek, &params, &mut rng).unwrap();
+        assert_eq!(
+            decrypt_cek(&dk, &params, &format!("*{}", &encapsulated.ek[1..]), &[]),
+            Err(Error::Base64)
+        );
+        assert_eq!(
+            decrypt_cek(&dk, &params, &encapsulated.ek[4..], &[]),
+            Err(Error::Malformed)
+        );
+        let bad = Parameters {
+            enc: "A256CTR",
+            ..params
+        };
+        assert_eq!(encrypt_cek(&ek, &bad, &mut rng), Err(Error::UnsupportedEnc));
+    }
+
+    // Known answers for both kinds of key management, to the key pair of `jwk_test`.  The
+    // ciphertexts were encapsulated with the ML-KEM of OpenSSL, through Python's `cryptography`,
+    // and the keys were derived and wrapped independently in Python.
+    #[test]
+    fn jwe_known_answers() {
+        let dk = DecapsulationKey::<MlKem768Params>::generate_deterministic(
+            &[1; 32].into(),
+            &[2; 32].into(),
+        );
+        let params = |alg| Parameters {
+            alg,
+            enc: "A256GCM",
+            apu: b"Alice",
+            apv: b"Bob",
+        };
+
+        let ek =
+            "W5IcbGUdp9sCfDv8One9m_6PSK5Q8SK_Zz1PjF0HAuBSWw3bS1876N6pQYluQSvUdp0SSVaOtaCJQUwuTKag\
+            pi2YQTOcQ6vca3UqhbJ0RaQNKjVVe6oTXIiBxoC6leybZxXBvhrNH5OdXIA9IueN6rdfuJNWetDR-yUze7Oq\
+            EDygJwdmY75-BkmLxaaumyRqaUhTJvOzlF2KLpjwUmXFrX7PSBx4OSEIwRaLZ87lPNZ3V1TCE57tF5_haqbl\
+            spnJ7DpWutc96LoihWqWnG9bmUgrTejAFrXBT3s2foUC16JlPXcMLwtNglBXTc9rh8NpL4xpSinbEyOKTlGe\
+            KHcLVlFvUsz1Y6B231u5PUoL8zZNpl6DExZ9tBfTsyQFjOd1cp9ewVNP1LM-fY-OFfBdCYZbp5lHU6JfTuMI\
+            yud4uw6ZB8emb4J8Vum2pn1MQTSvZoibeVunU6ew-QhDmqpF57L568S5Hy-W60SaDYIQchjNgR_9NUVgLHUb\
+            9Unli-N4DxVhBDiAD7t6ywezh15eJdoKBbRCaATiPzaScUhZx92oYxB3Z3thR-DrCaHCugFNGrxwIOnbpxB4\
+            oCrj5y_UP61TxtKkBk2fVFVaES4a9vpbtnjulrjS0dXBixoXfRvWBT3TP_UpLBLrz9WjigpCTo9H9GPyc_Fc\
+            wh5q48ty7BzEyYdpZ3kfxb4-TzXcqSs4rTIKPdzogquGfUe56jwicaDlm_JzHYqpdQRHRBnVVuVbi0u44gzD\
+            7HDN5i5CJmivzkNmlQ3J71yrcd7oOisWpFstGDLGZfn9wcKYBmcL0VaXRrBc2J67QcfawimZtPltYEwWgpSg\
+            NnllJhTr2m74JegPrUfLbytuHPgUkBJLjXZVUV7dHX7qvDyibi1vjwNMDc7c5r6JgP-KA6L31kWqpCYtD2v1\
+            8YK13IJCKYaS261JfDn0Nimn_S9Yx0Ekwpwg1_Vy6IUYwzhjliLe5GjAp2v8c0xOglq33B2TZ-aUDXwwbcSH\
+            GbEpkpIidUJ4txqRp5HhhJcvJjKeBZ9r07u791RM0Z1zUnL3FHbTa2q6lfpUpmsyD13Usw0bXjAK97W2ffkB\
+            JJRaocfyA8Q4qBMd8kgnQiqT21E3khAR1zrn1SOhj4vLPyhad7_QCr1WWCzqVAcHr6smWYVGgT3T-ZpdqIV7\
+            _rnueofZhK0R3CbsJqNsqsR6o_jxwIn5fmCe3qHxERJg0cbW04qda-Bzziz-3B_a5kEjKMNHNFrX7DltK7Fd\
+            G7hSE_xZ4GlHgfkO_8AhqeIKg--ZiBvgNndYge7LJ3jMEOc6EYf8OB7MT14WOuhEdNYJFGtUjm7qmddOATbj\
+            2mgcYeoFErP6g6_U5n27UtZ2iQ_iT6P8lkhlExPBUTjsaW3clUDfWFrm_Mwby6xfpGnpIwlfRAmHrkj9Qt3V\
+            o_W6qF0NGRdZfzYeRzZE1EE";
+        assert_eq!(
+            decrypt_cek(&dk, &params(Algorithm::MlKem768), ek, &[]).unwrap(),
+            hex!("db43b2d3e1b4f5339749d1c8194941f48cbaecdf7bd57cf922f8fa685e3d0cde")
+        );
+
+        let ek =
+            "zF077qJ1eMQfChKYQNR-iSBomQM8mGygBseLmQRWDLJF8XMyk6yVG3pgJwVRLd3dWl3aWIwCizMR2_rCQgJW\
+            8uUSJBUWH5qluoGEEegnz6TAHDipksJ6m8kgAlU2zM2BaLFdRBbeIrwxvFV-gUnGy59ZNvD1AoYSC5mQ9NEY\
+            h6OgNHj-2g-WCJPi3rqRYa67tbiwGUpdFde6SbBuPF9n4m3lMvBo7HSP2qXuaYuMFOC347ZuNQAdMyC6JF6r\
+            va3O2YTO6FQQWHk9NtlV8MXymL2beJqyJjY7m4GyT0-YGg4gPJUN9-c8mbFlKRPdUY-HB2vBdUCCumh503Qq\
+            Vb4CY6DB0ypvzwEBj9mzXdPH-c-KU6QcMqvD6oz15SHE72IF9VrJIlKInI2FE2ue5tiL-cjme6x2G0-vcBMd\
+            rzH7sczKYsd3h4f7T-dtKuBlieC2nmOV6gSLPlhDnvqlSt35pwWf6uqAjzsXkghKBSWMMSaA1eKbarr5MNTN\
+            a0RHT_JisZs7j2Tj_jnXlqz3XcJF15Kg75YK_6IJ5xLXddgH6yTDiTAcGTz65cgN86lCf7K6TA4YVdDCvdgJ\
+            Tak92ou_YWVZKeoQrQxjcp7-Dm91pwiIHbqFNFcXBE7jmvJnq_LeFpAOXeM8euhDx0E1-fgkKG2M4gXwzDM2\
+            RlDCBjHVWyPcqu7t-SJA6RCz6rYOV4pHw2tpZMTRYPP6FQ4oOorM34RJvjLydu_06rRHWOUHvf1IKpUJZ3mo\
+            khHu5Q8LM80gCuqct2Lvizx9YvLdr5RPVwMnlnOb-SvHyfk32sT8vHADo02pcJOWBSS7zZVJZ4c-wlz6U3Ql\
+            mnddgSRg0AVRQ66Ad3YoAu0RlANF8PczH9kng4DXoP1HnU8QYZVLhrgjrLwEg17Ebc9OlmAtZPQrQavdODhc\
+            a2k0RuNi9sxQ1vaYd8cCnDWSUmZK6SrT54j0T3GhlqyFvH1S8Avbuzhht4SCe4VJRnIt0nCuy-HaedbYAgXC\
+            Iap3CAD6CWZYrCP4tOxRSdBD6r6Vj7z5EX1LAB6Yu_V0fhN1c-2_XdQXNZdkWneQmNhuMCVTG6H2bmbXwA-l\
+            v9EinpMX-PwrzhGK1d-mtcPK-JMXd4Kwt5hH6GkfVCQNBZ70Ie7G-BHKomnIWh7-Zq40u1YZ2Wtf00W20mcX\
+            Ks2Etv-Zc8rocmeFB4rlpg4Z1mR1z_5czqa7pyhQ9n9ElNr5qtpSqJZiULeCqpN6uwGqBpKRVB1msCMcjxtO\
+            9vDTAYUlRZg75Gl_-V1BODNc5OHxsLhm8g8b2zAN9xt0rFw6S8r8iyVlQCyg8fBQPq95ES8-YQimoYBph1Ro\
+            iR-zzorbAMSZrg-Sbc31V4jgJM9s17tnGUh1HTZLnCtivfvZKbpKLmtwMFIN7hoIfJ5DdrjntcVHyEbaF00h\
+            ImM5T-jZvuvQdSdxt2-cZP4";
+        let encrypted_key = hex!(
+            "8a3bd289f7116219aa1d4c1aecbde0cabf26dc02be00ff3929bd191c44cc09c42fd821c461f296a8"
+        );
+        assert_eq!(
+            decrypt_cek(&dk, &params(Algorithm::MlKem768A192Kw), ek, &encrypted_key).unwrap(),
+            hex!("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f")
+        );
+    }
+
+    #[test]
+    fn jwe() {
+        use Algorithm::*;
+        jwe_test::<MlKem512Params>([MlKem512, MlKem512A128Kw]);
+        jwe_test::<MlKem768Params>([MlKem768, MlKem768A192Kw]);
+        jwe_test::<MlKem1024Params>([MlKem1024, MlKem1024A256Kw]);
+    }
+}
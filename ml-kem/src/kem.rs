@@ -1,3 +1,4 @@
+use core::fmt::{self, Write};
 use core::marker::PhantomData;
 use hybrid_array::typenum::U32;
 use rand_core::CryptoRngCore;
@@ -8,6 +9,9 @@ use crate::pke::{DecryptionKey, EncryptionKey};
 use crate::util::B32;
 use crate::{Encoded, EncodedSizeUser};
 
+#[cfg(feature = "alloc")]
+use {crate::pke::MatrixCache, alloc::vec::Vec};
+
 /// A shared key resulting from an ML-KEM transaction
 pub(crate) type SharedKey = B32;
 
@@ -18,9 +22,9 @@ pub struct DecapsulationKey<P>
 where
     P: KemParams,
 {
-    dk_pke: DecryptionKey<P>,
-    ek: EncapsulationKey<P>,
-    z: B32,
+    pub(crate) dk_pke: DecryptionKey<P>,
+    pub(crate) ek: EncapsulationKey<P>,
+    pub(crate) z: B32,
 }
 
 impl<P> EncodedSizeUser for DecapsulationKey<P>
@@ -34,8 +38,8 @@ where
         let (dk_pke, ek_pke, h, z) = P::split_dk(enc);
         let ek_pke = EncryptionKey::from_bytes(ek_pke);
 
-        // XXX(RLB): The encoding here is redundant, since `h` can be computed from `ek_pke`.
-        // Should we verify that the provided `h` value is valid?
+        // The encoding here is redundant, since `h` can be computed from `ek_pke`.  The provided
+        // `h` value is only verified by `from_bytes_checked`.
 
         Self {
             dk_pke: DecryptionKey::from_bytes(dk_pke),
@@ -55,7 +59,7 @@ where
 }
 
 // 0xff if x == y, 0x00 otherwise
-fn constant_time_eq(x: u8, y: u8) -> u8 {
+pub(crate) fn constant_time_eq(x: u8, y: u8) -> u8 {
     let diff = x ^ y;
     let is_zero = !diff & diff.wrapping_sub(1);
     0u8.wrapping_sub(is_zero >> 7)
@@ -71,28 +75,78 @@ where
     type Error = ();
 
     fn decapsulate(&self, encapsulated_key: &EncodedCiphertext<P>) -> Result<SharedKey, ()> {
-        let mp = self.dk_pke.decrypt(encapsulated_key);
-        let (Kp, rp) = G(&[&mp, &self.ek.h]);
-        let Kbar = J(&[self.z.as_slice(), encapsulated_key.as_ref()]);
-        let cp = self.ek.ek_pke.encrypt(&mp, &rp);
-
-        // Constant-time version of:
-        //
-        // if cp == *ct {
-        //     Kp
-        // } else {
-        //     Kbar
-        // }
-        let equal = cp
-            .iter()
-            .zip(encapsulated_key.iter())
-            .map(|(&x, &y)| constant_time_eq(x, y))
-            .fold(0xff, |x, y| x & y);
-        Ok(Kp
-            .iter()
-            .zip(Kbar.iter())
-            .map(|(x, y)| (equal & x) | (!equal & y))
-            .collect())
+        let (Kp, Kbar, equal) = self.decapsulate_inner(encapsulated_key);
+        Ok(select(&Kp, &Kbar, equal))
+    }
+}
+
+// Constant-time version of:
+//
+// if cp == *ct {
+//     Kp
+// } else {
+//     Kbar
+// }
+pub(crate) fn select(Kp: &B32, Kbar: &B32, equal: u8) -> SharedKey {
+    Kp.iter()
+        .zip(Kbar.iter())
+        .map(|(x, y)| (equal & x) | (!equal & y))
+        .collect()
+}
+
+// Encapsulation through the `Encapsulate` trait, whose `Result` is always `Ok` for ML-KEM.  This
+// is used by the protocol modules, so it is dead code when none of them are enabled.
+#[allow(dead_code)]
+pub(crate) fn encapsulate<EK, CT, SS>(ek: &EK, rng: &mut impl CryptoRngCore) -> (CT, SS)
+where
+    EK: ::kem::Encapsulate<CT, SS>,
+{
+    let Ok(encapsulated) = ek.encapsulate(rng) else {
+        unreachable!("ML-KEM encapsulation is infallible")
+    };
+    encapsulated
+}
+
+// Decapsulation through the `Decapsulate` trait, whose `Result` is always `Ok` for ML-KEM
+#[allow(dead_code)]
+pub(crate) fn decapsulate<DK, CT, SS>(dk: &DK, ct: &CT) -> SS
+where
+    DK: ::kem::Decapsulate<CT, SS>,
+{
+    let Ok(k) = dk.decapsulate(ct) else {
+        unreachable!("ML-KEM decapsulation is infallible")
+    };
+    k
+}
+
+/// An error indicating that a ciphertext failed the re-encryption check during decapsulation,
+/// i.e., that it was not produced by encapsulating to the corresponding encapsulation key.
+#[cfg(feature = "explicit-rejection")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecapsulationFailure;
+
+#[cfg(feature = "explicit-rejection")]
+impl fmt::Display for DecapsulationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ciphertext failed the ML-KEM re-encryption check")
+    }
+}
+
+// Counters of explicit-rejection outcomes, so that tests can verify which path was taken.  These
+// are thread-local so that they are not disturbed by tests running in parallel.
+#[cfg(all(test, feature = "explicit-rejection"))]
+pub(crate) mod counters {
+    extern crate std;
+
+    use core::cell::Cell;
+
+    std::thread_local! {
+        pub static ACCEPTED: Cell<usize> = const { Cell::new(0) };
+        pub static REJECTED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    pub fn get() -> (usize, usize) {
+        (ACCEPTED.with(Cell::get), REJECTED.with(Cell::get))
     }
 }
 
@@ -106,10 +160,136 @@ where
         Self::generate_deterministic(&d, &z)
     }
 
-    pub(crate) fn encapsulation_key(&self) -> &EncapsulationKey<P> {
+    /// Decode a stored decapsulation key, performing the hash check of FIPS 203 Section 7.3.
+    /// Returns `None` if the embedded `H(ek)` is not the hash of the embedded encapsulation key, or
+    /// if that key fails the modulus check of [`EncapsulationKey::from_bytes_checked`].
+    #[must_use]
+    pub fn from_bytes_checked(enc: &Encoded<Self>) -> Option<Self> {
+        let (_, ek, h, _) = P::split_dk(enc);
+        let ek = EncapsulationKey::<P>::from_bytes_checked(ek)?;
+        (ek.h == *h).then(|| Self::from_bytes(enc))
+    }
+
+    /// The encapsulation key corresponding to this decapsulation key, which is embedded in its
+    /// encoding
+    #[must_use]
+    pub fn encapsulation_key(&self) -> &EncapsulationKey<P> {
         &self.ek
     }
 
+    /// The identifier of the corresponding encapsulation key.  For a key parsed with
+    /// [`EncodedSizeUser::from_bytes`], this is the `H(ek)` value stored in the encoded key.
+    pub fn key_id(&self) -> KeyId {
+        self.ek.key_id()
+    }
+
+    /// Decapsulate with *explicit* rejection: Return an error if the ciphertext fails the
+    /// re-encryption check, instead of the pseudo-random implicit rejection key.
+    ///
+    /// **This method is intended only for diagnosing interoperability failures, and must not be
+    /// used in production.**  Revealing whether decapsulation failed, and doing so in variable
+    /// time, gives an attacker a decryption oracle and voids the IND-CCA security of ML-KEM.  When
+    /// the ciphertext is valid, the returned key is identical to the one returned by
+    /// [`Decapsulate::decapsulate`][::kem::Decapsulate::decapsulate].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecapsulationFailure`] if re-encrypting the decrypted message does not reproduce
+    /// the provided ciphertext.
+    #[cfg(feature = "explicit-rejection")]
+    pub fn decapsulate_checked(
+        &self,
+        encapsulated_key: &EncodedCiphertext<P>,
+    ) -> Result<SharedKey, DecapsulationFailure> {
+        let (Kp, _Kbar, equal) = self.decapsulate_inner(encapsulated_key);
+        let accepted = equal == 0xff;
+
+        #[cfg(test)]
+        {
+            let counter = if accepted {
+                &counters::ACCEPTED
+            } else {
+                &counters::REJECTED
+            };
+            counter.with(|c| c.set(c.get() + 1));
+        }
+
+        if accepted {
+            Ok(Kp)
+        } else {
+            Err(DecapsulationFailure)
+        }
+    }
+
+    // Algorithm 17. ML-KEM.Decaps(c, dk), up to the final selection.  Returns the key `K'` derived
+    // from the decrypted message, the implicit rejection key `Kbar`, and a mask that is 0xff if the
+    // ciphertext passed the re-encryption check and 0x00 otherwise.
+    fn decapsulate_inner(&self, encapsulated_key: &EncodedCiphertext<P>) -> (B32, B32, u8) {
+        self.decapsulate_inner_with(encapsulated_key, |m, r| self.ek.ek_pke.encrypt(m, r))
+    }
+
+    // As above, with the re-encryption step `K-PKE.Encrypt(ek, m', r')` supplied by the caller
+    fn decapsulate_inner_with(
+        &self,
+        encapsulated_key: &EncodedCiphertext<P>,
+        encrypt: impl FnOnce(&B32, &B32) -> EncodedCiphertext<P>,
+    ) -> (B32, B32, u8) {
+        let mp = self.dk_pke.decrypt(encapsulated_key);
+        let (Kp, rp) = G(&[&mp, &self.ek.h]);
+        let Kbar = J(&[self.z.as_slice(), encapsulated_key.as_ref()]);
+        let cp = encrypt(&mp, &rp);
+
+        let equal = cp
+            .iter()
+            .zip(encapsulated_key.iter())
+            .map(|(&x, &y)| constant_time_eq(x, y))
+            .fold(0xff, |x, y| x & y);
+        (Kp, Kbar, equal)
+    }
+
+    /// Decapsulate each of the `ciphertexts`, returning the shared keys in the same order.  The
+    /// output is identical to calling `decapsulate` on each ciphertext in turn, including the
+    /// implicit rejection of invalid ciphertexts.
+    ///
+    /// The re-encryption check in decapsulation needs the matrix `A_hat` of the encapsulation
+    /// key.  It is expanded once for the whole batch, rather than once per ciphertext.
+    #[cfg(feature = "alloc")]
+    #[must_use]
+    pub fn decapsulate_batch(&self, ciphertexts: &[EncodedCiphertext<P>]) -> Vec<SharedKey> {
+        let mut cache = MatrixCache::default();
+        ciphertexts
+            .iter()
+            .map(|c| self.decapsulate_cached(&mut cache, c))
+            .collect()
+    }
+
+    /// Like [`DecapsulationKey::decapsulate_batch`], but decapsulates the ciphertexts in parallel
+    /// on the rayon thread pool.  Each worker thread expands the matrix `A_hat` at most once.
+    #[cfg(feature = "rayon")]
+    #[must_use]
+    pub fn decapsulate_batch_par(&self, ciphertexts: &[EncodedCiphertext<P>]) -> Vec<SharedKey> {
+        use rayon::prelude::*;
+
+        ciphertexts
+            .par_iter()
+            .map_init(MatrixCache::default, |cache, c| {
+                self.decapsulate_cached(cache, c)
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "alloc")]
+    fn decapsulate_cached(
+        &self,
+        cache: &mut MatrixCache<P::K>,
+        encapsulated_key: &EncodedCiphertext<P>,
+    ) -> SharedKey {
+        let (Kp, Kbar, equal) = self.decapsulate_inner_with(encapsulated_key, |m, r| {
+            self.ek.ek_pke.encrypt_cached(cache, m, r)
+        });
+        select(&Kp, &Kbar, equal)
+    }
+
     #[must_use]
     #[allow(clippy::similar_names)] // allow dk_pke, ek_pke, following the spec
     pub(crate) fn generate_deterministic(d: &B32, z: &B32) -> Self {
@@ -127,19 +307,34 @@ pub struct EncapsulationKey<P>
 where
     P: KemParams,
 {
-    ek_pke: EncryptionKey<P>,
-    h: B32,
+    pub(crate) ek_pke: EncryptionKey<P>,
+    pub(crate) h: B32,
 }
 
 impl<P> EncapsulationKey<P>
 where
     P: KemParams,
 {
-    fn new(ek_pke: EncryptionKey<P>) -> Self {
+    pub(crate) fn new(ek_pke: EncryptionKey<P>) -> Self {
         let h = H(ek_pke.as_bytes());
         Self { ek_pke, h }
     }
 
+    /// Decode an encapsulation key received from a peer, performing the modulus check of FIPS
+    /// 203.  Returns `None` if any coefficient of the encoded vector is not reduced mod q.
+    #[must_use]
+    pub fn from_bytes_checked(enc: &Encoded<Self>) -> Option<Self> {
+        // Decoding reduces each coefficient mod q, so a key with unreduced coefficients does not
+        // encode to the same bytes
+        let ek = Self::from_bytes(enc);
+        (ek.as_bytes() == *enc).then_some(ek)
+    }
+
+    /// The identifier of this encapsulation key, i.e., the hash `H(ek)` of its encoding
+    pub fn key_id(&self) -> KeyId {
+        KeyId(self.h)
+    }
+
     fn encapsulate_deterministic_inner(&self, m: &B32) -> (EncodedCiphertext<P>, SharedKey) {
         let (K, r) = G(&[m, &self.h]);
         let c = self.ek_pke.encrypt(m, &r);
@@ -147,6 +342,15 @@ where
     }
 }
 
+impl<P> From<&DecapsulationKey<P>> for EncapsulationKey<P>
+where
+    P: KemParams,
+{
+    fn from(dk: &DecapsulationKey<P>) -> Self {
+        dk.encapsulation_key().clone()
+    }
+}
+
 impl<P> EncodedSizeUser for EncapsulationKey<P>
 where
     P: KemParams,
@@ -195,6 +399,98 @@ where
     }
 }
 
+/// A `KeyId` is a fingerprint of an encapsulation key, namely the hash `H(ek)` that ML-KEM computes
+/// over the encoded key.  It can be used to index keys or to record which key a ciphertext was
+/// encapsulated to, without revealing the key itself.
+///
+/// The `Display` implementation renders the identifier as lowercase hex; [`KeyId::base32`] provides
+/// a shorter unpadded base32 rendering.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct KeyId(B32);
+
+impl KeyId {
+    /// The raw bytes of the identifier
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        self.0.as_ref()
+    }
+
+    /// Render the identifier in the lowercase RFC 4648 base32 alphabet, without padding
+    #[must_use]
+    pub fn base32(&self) -> impl fmt::Display + '_ {
+        Base32(self)
+    }
+}
+
+impl From<[u8; 32]> for KeyId {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes.into())
+    }
+}
+
+impl From<KeyId> for [u8; 32] {
+    fn from(id: KeyId) -> Self {
+        id.0.into()
+    }
+}
+
+impl AsRef<[u8]> for KeyId {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+impl fmt::LowerHex for KeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{b:02x}"))
+    }
+}
+
+impl fmt::UpperHex for KeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{b:02X}"))
+    }
+}
+
+impl fmt::Display for KeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(self, f)
+    }
+}
+
+impl fmt::Debug for KeyId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "KeyId({self:x})")
+    }
+}
+
+struct Base32<'a>(&'a KeyId);
+
+impl fmt::Display for Base32<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+        // Feed bytes into a bit buffer and emit a character for every five bits
+        let Base32(KeyId(bytes)) = self;
+        let mut buffer = 0u16;
+        let mut bits = 0;
+        for &b in bytes {
+            buffer = (buffer << 8) | u16::from(b);
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                f.write_char(ALPHABET[usize::from((buffer >> bits) & 0x1f)].into())?;
+            }
+        }
+
+        if bits > 0 {
+            f.write_char(ALPHABET[usize::from((buffer << (5 - bits)) & 0x1f)].into())?;
+        }
+
+        Ok(())
+    }
+}
+
 /// An implementation of overall ML-KEM functionality.  Generic over parameter sets, but then ties
 /// together all of the other related types and sizes.
 pub struct Kem<P>
@@ -216,8 +512,7 @@ where
     /// Generate a new (decapsulation, encapsulation) key pair
     fn generate(rng: &mut impl CryptoRngCore) -> (Self::DecapsulationKey, Self::EncapsulationKey) {
         let dk = Self::DecapsulationKey::generate(rng);
-        let ek = dk.encapsulation_key().clone();
-        (dk, ek)
+        Self::keypair_from_decapsulation_key(dk)
     }
 
     #[cfg(feature = "deterministic")]
@@ -226,7 +521,13 @@ where
         z: &B32,
     ) -> (Self::DecapsulationKey, Self::EncapsulationKey) {
         let dk = Self::DecapsulationKey::generate_deterministic(d, z);
-        let ek = dk.encapsulation_key().clone();
+        Self::keypair_from_decapsulation_key(dk)
+    }
+
+    fn keypair_from_decapsulation_key(
+        dk: Self::DecapsulationKey,
+    ) -> (Self::DecapsulationKey, Self::EncapsulationKey) {
+        let ek = EncapsulationKey::from(&dk);
         (dk, ek)
     }
 }
@@ -234,8 +535,11 @@ where
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::param::EncodedEncryptionKey;
+    use crate::util::Truncate;
     use crate::{MlKem1024Params, MlKem512Params, MlKem768Params};
     use ::kem::{Decapsulate, Encapsulate};
+    use hybrid_array::typenum::Unsigned;
 
     fn round_trip_test<P>()
     where
@@ -273,6 +577,36 @@ mod test {
         let ek_encoded = ek_original.as_bytes();
         let ek_decoded = EncapsulationKey::from_bytes(&ek_encoded);
         assert_eq!(ek_original, ek_decoded);
+
+        // The encapsulation key can be recovered from a decoded decapsulation key
+        assert_eq!(EncapsulationKey::from(&dk_decoded), ek_original);
+        assert_eq!(dk_decoded.encapsulation_key().as_bytes(), ek_encoded);
+
+        // The modulus check rejects a coefficient of at least q, which would otherwise be reduced
+        assert_eq!(
+            EncapsulationKey::<P>::from_bytes_checked(&ek_encoded),
+            Some(ek_original)
+        );
+        let mut ek_unreduced = ek_encoded;
+        ek_unreduced[0] = 0xff;
+        ek_unreduced[1] |= 0x0f;
+        assert_eq!(
+            EncapsulationKey::<P>::from_bytes_checked(&ek_unreduced),
+            None
+        );
+
+        // The hash check rejects a decapsulation key whose `H(ek)` does not match its `ek`
+        assert_eq!(
+            DecapsulationKey::<P>::from_bytes_checked(&dk_encoded),
+            Some(dk_decoded)
+        );
+        let mut dk_wrong_hash = dk_encoded;
+        let len = dk_wrong_hash.len();
+        dk_wrong_hash[len - 64] ^= 1;
+        assert_eq!(
+            DecapsulationKey::<P>::from_bytes_checked(&dk_wrong_hash),
+            None
+        );
     }
 
     #[test]
@@ -281,4 +615,214 @@ mod test {
         codec_test::<MlKem768Params>();
         codec_test::<MlKem1024Params>();
     }
+
+    // Overwrite the `i`-th 12-bit coefficient of an encoded vector with `x`, which need not be
+    // reduced mod q
+    fn set_coefficient(enc: &mut [u8], i: usize, x: u16) {
+        let j = 3 * (i / 2);
+        if i % 2 == 0 {
+            let [lo, hi] = x.to_le_bytes();
+            enc[j] = lo;
+            enc[j + 1] = (enc[j + 1] & 0xf0) | hi;
+        } else {
+            let [lo, hi] = (x << 4).to_le_bytes();
+            enc[j + 1] = (enc[j + 1] & 0x0f) | lo;
+            enc[j + 2] = hi;
+        }
+    }
+
+    // The input checks of FIPS 203, Sections 7.2 and 7.3.  Encoded keys of the wrong length are
+    // ruled out by their types.
+    fn key_check_test<P>()
+    where
+        P: KemParams,
+    {
+        const Q: u16 = 3329;
+
+        let mut rng = rand::thread_rng();
+        let dk = DecapsulationKey::<P>::generate(&mut rng);
+        let ek = dk.encapsulation_key().as_bytes();
+        let n = 256 * P::K::USIZE;
+
+        // Modulus check on encapsulation keys
+        let ek_check = |i: usize, x: u16| {
+            let mut enc = ek.clone();
+            set_coefficient(&mut enc, i, x);
+            EncapsulationKey::<P>::from_bytes_checked(&enc).is_some()
+        };
+        assert!(EncapsulationKey::<P>::from_bytes_checked(&ek).is_some());
+        assert!(ek_check(0, Q - 1));
+        assert!(!ek_check(0, Q));
+        assert!(!ek_check(n - 1, 0xfff));
+        assert!(!ek_check(n - 256, Q + 1));
+
+        // Hash check on decapsulation keys
+        let dk_encoded = dk.as_bytes();
+        let (dk_pke, _, h, z) = P::split_dk(&dk_encoded);
+        let dk_check = |ek: EncodedEncryptionKey<P>, h: B32| {
+            let enc = P::concat_dk(dk_pke.clone(), ek, h, z.clone());
+            DecapsulationKey::<P>::from_bytes_checked(&enc).is_some()
+        };
+        assert!(dk_check(ek.clone(), h.clone()));
+
+        let mut h_first = h.clone();
+        h_first[0] ^= 1;
+        assert!(!dk_check(ek.clone(), h_first));
+        let mut h_last = h.clone();
+        h_last[31] ^= 0x80;
+        assert!(!dk_check(ek.clone(), h_last));
+        assert!(!dk_check(ek.clone(), B32::default()));
+
+        // A modified embedded key fails the hash check, unless the hash is recomputed...
+        let mut ek_modified = ek.clone();
+        let len = ek_modified.len();
+        ek_modified[len - 1] ^= 1;
+        assert!(!dk_check(ek_modified.clone(), h.clone()));
+        assert!(dk_check(ek_modified.clone(), H(&ek_modified)));
+
+        // ... and then it must still pass the modulus check
+        let mut ek_unreduced = ek;
+        set_coefficient(&mut ek_unreduced, 1, Q);
+        assert!(!dk_check(ek_unreduced.clone(), H(&ek_unreduced)));
+    }
+
+    #[test]
+    fn key_check() {
+        key_check_test::<MlKem512Params>();
+        key_check_test::<MlKem768Params>();
+        key_check_test::<MlKem1024Params>();
+    }
+
+    fn key_id_test<P>()
+    where
+        P: KemParams,
+    {
+        let mut rng = rand::thread_rng();
+        let dk = DecapsulationKey::<P>::generate(&mut rng);
+        let ek = dk.encapsulation_key();
+
+        // The identifier is H(ek), and is shared by both halves of the key pair
+        let id = ek.key_id();
+        assert_eq!(id.as_bytes(), H(ek.as_bytes()).as_slice());
+        assert_eq!(dk.key_id(), id);
+        assert_eq!(
+            DecapsulationKey::<P>::from_bytes(&dk.as_bytes()).key_id(),
+            id
+        );
+        assert_eq!(
+            EncapsulationKey::<P>::from_bytes(&ek.as_bytes()).key_id(),
+            id
+        );
+
+        // Different keys have different identifiers
+        let other = DecapsulationKey::<P>::generate(&mut rng);
+        assert_ne!(other.key_id(), id);
+    }
+
+    #[test]
+    fn key_id() {
+        key_id_test::<MlKem512Params>();
+        key_id_test::<MlKem768Params>();
+        key_id_test::<MlKem1024Params>();
+    }
+
+    #[test]
+    fn key_id_format() {
+        extern crate std;
+        use std::format;
+
+        let bytes: [u8; 32] = core::array::from_fn(Truncate::truncate);
+        let id = KeyId::from(bytes);
+        assert_eq!(<[u8; 32]>::from(id), bytes);
+
+        let hex = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+        assert_eq!(format!("{id}"), hex);
+        assert_eq!(format!("{id:x}"), hex);
+        assert_eq!(format!("{id:X}"), hex.to_uppercase());
+        assert_eq!(format!("{id:?}"), format!("KeyId({hex})"));
+
+        // Known answers from Python's `base64.b32encode`, with padding removed
+        let base32 = "aaaqeayeaudaocajbifqydiob4ibceqtcqkrmfyydenbwha5dypq";
+        assert_eq!(format!("{}", id.base32()), base32);
+        let base32 = "777777777777777777777777777777777777777777777777777q";
+        assert_eq!(format!("{}", KeyId::from([0xff; 32]).base32()), base32);
+
+        // Identifiers are ordered by their bytes
+        assert!(KeyId::from([0; 32]) < id);
+        assert!(id < KeyId::from([0xff; 32]));
+    }
+
+    #[cfg(feature = "explicit-rejection")]
+    fn explicit_rejection_test<P>()
+    where
+        P: KemParams,
+    {
+        let mut rng = rand::thread_rng();
+
+        let dk = DecapsulationKey::<P>::generate(&mut rng);
+        let ek = dk.encapsulation_key();
+        let (ct, k_send) = ek.encapsulate(&mut rng).unwrap();
+        let (accepted, rejected) = counters::get();
+
+        // A valid ciphertext yields the same key as implicit rejection would
+        assert_eq!(dk.decapsulate_checked(&ct), Ok(k_send));
+        assert_eq!(counters::get(), (accepted + 1, rejected));
+
+        // A modified ciphertext is reported as a failure
+        let mut ct_bad = ct.clone();
+        ct_bad[0] ^= 1;
+        assert_eq!(dk.decapsulate_checked(&ct_bad), Err(DecapsulationFailure));
+        assert_eq!(counters::get(), (accepted + 1, rejected + 1));
+
+        // The infallible interface does not touch the counters
+        let k_reject = dk.decapsulate(&ct_bad).unwrap();
+        assert_ne!(k_reject, k_send);
+        assert_eq!(counters::get(), (accepted + 1, rejected + 1));
+    }
+
+    #[test]
+    #[cfg(feature = "explicit-rejection")]
+    fn explicit_rejection() {
+        explicit_rejection_test::<MlKem512Params>();
+        explicit_rejection_test::<MlKem768Params>();
+        explicit_rejection_test::<MlKem1024Params>();
+    }
+
+    // Decapsulate a batch of valid and corrupted ciphertexts, and check the result against a
+    // sequential loop
+    #[cfg(feature = "alloc")]
+    fn batch_test<P>()
+    where
+        P: KemParams,
+    {
+        let mut rng = rand::thread_rng();
+        let dk = DecapsulationKey::<P>::generate(&mut rng);
+        let ek = dk.encapsulation_key();
+
+        let cts: Vec<_> = (0..6)
+            .map(|i| {
+                let (mut ct, _) = ek.encapsulate(&mut rng).unwrap();
+                if i % 3 == 2 {
+                    ct[i] ^= 0x01;
+                }
+                ct
+            })
+            .collect();
+
+        let expected: Vec<_> = cts.iter().map(|ct| dk.decapsulate(ct).unwrap()).collect();
+
+        assert_eq!(dk.decapsulate_batch(&cts), expected);
+        #[cfg(feature = "rayon")]
+        assert_eq!(dk.decapsulate_batch_par(&cts), expected);
+
+        assert!(dk.decapsulate_batch(&[]).is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn batch() {
+        batch_test::<MlKem512Params>();
+        batch_test::<MlKem768Params>();
+        batch_test::<MlKem1024Params>();
+    }
 }
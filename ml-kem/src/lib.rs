@@ -38,6 +38,9 @@
 //!
 //! [RFC 9180]: https://www.rfc-editor.org/info/rfc9180
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 /// The inevitable utility module
 mod util;
 
@@ -64,6 +67,50 @@ pub mod kem;
 /// Section 7. Parameter Sets
 mod param;
 
+/// Low-level building blocks of ML-KEM, for building variants of ML-KEM and for protocol research
+#[cfg(feature = "hazmat")]
+pub mod hazmat;
+
+/// An experimental multi-recipient KEM (mKEM), encapsulating one shared key to many recipients
+#[cfg(feature = "mkem")]
+pub mod mkem;
+
+/// Public-key encryption of arbitrary messages to an ML-KEM encapsulation key
+#[cfg(feature = "seal")]
+pub mod seal;
+
+/// A mutually authenticated key exchange built from three KEM operations
+#[cfg(feature = "ake")]
+pub mod ake;
+
+/// The `mlkem768x25519-sha256` key exchange method for SSH
+#[cfg(feature = "ssh")]
+pub mod ssh;
+
+/// Helpers for Signal's PQXDH key agreement, with ML-KEM-1024 as the post-quantum KEM
+#[cfg(feature = "pqxdh")]
+pub mod pqxdh;
+
+/// `KEMRecipientInfo` for the Cryptographic Message Syntax, as specified in RFC 9629
+#[cfg(feature = "cms")]
+pub mod cms;
+
+/// JOSE support for ML-KEM: JWK encoding of keys, and JWE key management with ML-KEM
+#[cfg(feature = "jose")]
+pub mod jose;
+
+/// COSE support for ML-KEM: `COSE_Key` encoding of keys, and `COSE_recipient` structures
+#[cfg(feature = "cose")]
+pub mod cose;
+
+/// The composite ML-KEM public-key algorithms of draft-ietf-openpgp-pqc
+#[cfg(feature = "openpgp")]
+pub mod openpgp;
+
+/// Helpers for ML-KEM as an additional key exchange in IKE version 2 (RFC 9370)
+#[cfg(feature = "ikev2")]
+pub mod ikev2;
+
 use ::kem::{Decapsulate, Encapsulate};
 use core::fmt::Debug;
 use hybrid_array::{
@@ -72,7 +119,7 @@ use hybrid_array::{
 };
 use rand_core::CryptoRngCore;
 
-#[cfg(feature = "deterministic")]
+#[cfg(any(feature = "deterministic", feature = "mkem"))]
 pub use util::B32;
 
 pub use param::{ArraySize, ParameterSet};
@@ -143,6 +190,12 @@ pub trait KemCore {
     #[cfg(feature = "deterministic")]
     fn generate_deterministic(d: &B32, z: &B32)
         -> (Self::DecapsulationKey, Self::EncapsulationKey);
+
+    /// Recover the full (decapsulation, encapsulation) key pair from a decapsulation key, e.g.,
+    /// one that was loaded from storage, so that the encapsulation key can be republished
+    fn keypair_from_decapsulation_key(
+        dk: Self::DecapsulationKey,
+    ) -> (Self::DecapsulationKey, Self::EncapsulationKey);
 }
 
 /// `MlKem512` is the parameter set for security category 1, corresponding to key search on a block
@@ -225,4 +278,30 @@ mod test {
         round_trip_test::<MlKem768>();
         round_trip_test::<MlKem1024>();
     }
+
+    fn keypair_from_decapsulation_key_test<K>()
+    where
+        K: KemCore,
+    {
+        let mut rng = rand::thread_rng();
+
+        // Store only the decapsulation key, then restore the key pair from it
+        let (dk, ek) = K::generate(&mut rng);
+        let dk_bytes = dk.as_bytes();
+        let (dk_restored, ek_restored) =
+            K::keypair_from_decapsulation_key(K::DecapsulationKey::from_bytes(&dk_bytes));
+        assert_eq!(dk_restored, dk);
+        assert_eq!(ek_restored, ek);
+
+        let (ct, k_send) = ek_restored.encapsulate(&mut rng).unwrap();
+        let k_recv = dk.decapsulate(&ct).unwrap();
+        assert_eq!(k_send, k_recv);
+    }
+
+    #[test]
+    fn keypair_from_decapsulation_key() {
+        keypair_from_decapsulation_key_test::<MlKem512>();
+        keypair_from_decapsulation_key_test::<MlKem768>();
+        keypair_from_decapsulation_key_test::<MlKem1024>();
+    }
 }
@@ -0,0 +1,243 @@
+//! In ML-KEM, the first component `u` of a ciphertext depends on the encapsulation key only
+//! through the seed `rho` of the public matrix.  If a group of recipients generate their keys
+//! under a common seed `rho`, then the sender can compute `u` once and send it to all of them,
+//! together with one short component `v_i` per recipient.  For ML-KEM-768, for example, a
+//! ciphertext to `n` recipients takes `960 + 128 n` bytes, instead of the `1088 n` bytes of `n`
+//! separate ML-KEM ciphertexts.  This construction follows "Scalable Ciphertext Compression
+//! Techniques for Post-Quantum KEMs and their Applications" by Katsumata, Kwiatkowski, Pintore,
+//! and Prest (ASIACRYPT 2020).
+//!
+//! ```
+//! # use ml_kem::{mkem, B32, MlKem768Params};
+//! # use rand::RngCore;
+//! let mut rng = rand::thread_rng();
+//!
+//! // The group agrees on a uniformly random seed, and each recipient generates a key pair
+//! let mut rho = B32::default();
+//! rng.fill_bytes(&mut rho);
+//! let (dks, eks): (Vec<_>, Vec<_>) = (0..3)
+//!     .map(|_| mkem::generate::<MlKem768Params>(&rho, &mut rng))
+//!     .unzip();
+//!
+//! // The sender encapsulates one shared key to all of the recipients
+//! let (ct, k_send) = mkem::encapsulate(&eks, &mut rng).unwrap();
+//!
+//! // Each recipient decapsulates with the shared `u` and its own `v`
+//! for (dk, v) in dks.iter().zip(ct.v.iter()) {
+//!     let k_recv = mkem::decapsulate(dk, &ct.u, v);
+//!     assert_eq!(k_send, k_recv);
+//! }
+//! ```
+//!
+//! # ⚠️ Security Warning
+//!
+//! This module is **not** ML-KEM as specified in FIPS 203, and does not interoperate with it.  It
+//! has not been reviewed to the same standard as the rest of this crate.  In particular:
+//!
+//! * The shared seed `rho` must be sampled uniformly at random, by a party that all recipients
+//!   trust.  A seed chosen by an adversary could yield a matrix with a trapdoor.
+//! * All recipients learn the same shared key.  Any one of them can therefore read messages
+//!   protected with it, and can impersonate the sender to the others.  Like ML-KEM itself, the
+//!   mKEM does not authenticate the sender.
+//! * The shared key is bound to the seed `rho`, but not to the set of recipients, and the
+//!   ciphertext does not hide how many recipients there are.
+//! * Key pairs generated by [`generate`] should only be used with this module.  Using them with
+//!   plain ML-KEM as well is a cross-protocol use of the same key, which is not analyzed.
+//!
+//! [`generate`]: crate::mkem::generate
+
+use alloc::vec::Vec;
+use core::fmt;
+use rand_core::CryptoRngCore;
+
+use crate::crypto::{rand, G, J};
+use crate::kem::{constant_time_eq, select, DecapsulationKey, EncapsulationKey, SharedKey};
+use crate::param::{KemParams, PkeParams};
+use crate::pke::DecryptionKey;
+use crate::util::B32;
+
+pub use crate::param::{EncodedU, EncodedV};
+
+/// A multi-recipient ciphertext: the component `u` that is shared by all recipients, and one
+/// component `v` for each recipient, in the same order as the keys passed to [`encapsulate`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ciphertext<P>
+where
+    P: PkeParams,
+{
+    /// The shared component `u`
+    pub u: EncodedU<P>,
+
+    /// The per-recipient components `v`
+    pub v: Vec<EncodedV<P>>,
+}
+
+/// An error in multi-recipient encapsulation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// No encapsulation keys were provided
+    NoRecipients,
+
+    /// The encapsulation keys were not all generated with the same seed `rho`
+    SeedMismatch,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NoRecipients => "no recipients for multi-recipient encapsulation",
+            Self::SeedMismatch => "recipient keys do not share the same seed rho",
+        })
+    }
+}
+
+/// Generate a new (decapsulation, encapsulation) key pair whose public matrix is derived from
+/// the shared seed `rho`.  All recipients of a multi-recipient ciphertext must use the same seed.
+pub fn generate<P>(
+    rho: &B32,
+    rng: &mut impl CryptoRngCore,
+) -> (DecapsulationKey<P>, EncapsulationKey<P>)
+where
+    P: KemParams,
+{
+    let sigma: B32 = rand(rng);
+    let z: B32 = rand(rng);
+
+    let (dk_pke, ek_pke) = DecryptionKey::generate_from_seeds(rho.clone(), &sigma);
+    let ek = EncapsulationKey::new(ek_pke);
+    let dk = DecapsulationKey {
+        dk_pke,
+        ek: ek.clone(),
+        z,
+    };
+    (dk, ek)
+}
+
+/// Encapsulate a fresh shared key to all of the `keys`, which must share the same seed `rho`.
+///
+/// # Errors
+///
+/// Returns [`Error::NoRecipients`] if `keys` is empty, and [`Error::SeedMismatch`] if the keys
+/// were not all generated with the same seed.
+pub fn encapsulate<P>(
+    keys: &[EncapsulationKey<P>],
+    rng: &mut impl CryptoRngCore,
+) -> Result<(Ciphertext<P>, SharedKey), Error>
+where
+    P: KemParams,
+{
+    let rho = keys.first().ok_or(Error::NoRecipients)?.ek_pke.rho();
+    if keys.iter().any(|ek| ek.ek_pke.rho() != rho) {
+        return Err(Error::SeedMismatch);
+    }
+
+    let m: B32 = rand(rng);
+    let (K, r) = G(&[&m, rho]);
+
+    let (r_hat, u) = keys[0].ek_pke.encrypt_r_u(&r);
+    let v = keys
+        .iter()
+        .map(|ek| ek.ek_pke.encrypt_v(&r_hat, &m, &noise_seed(&r, ek)))
+        .collect();
+
+    Ok((Ciphertext { u, v }, K))
+}
+
+/// Decapsulate the shared key from the shared component `u` of a multi-recipient ciphertext and
+/// this recipient's component `v`.  As in ML-KEM, an invalid ciphertext results in an
+/// unpredictable key, rather than an error.
+pub fn decapsulate<P>(dk: &DecapsulationKey<P>, u: &EncodedU<P>, v: &EncodedV<P>) -> SharedKey
+where
+    P: KemParams,
+{
+    let ek = &dk.ek;
+    let c = P::concat_ct(u.clone(), v.clone());
+
+    let mp = dk.dk_pke.decrypt(&c);
+    let (Kp, rp) = G(&[&mp, ek.ek_pke.rho()]);
+    let Kbar = J(&[dk.z.as_slice(), c.as_ref()]);
+
+    let (r_hat, up) = ek.ek_pke.encrypt_r_u(&rp);
+    let vp = ek.ek_pke.encrypt_v(&r_hat, &mp, &noise_seed(&rp, ek));
+
+    let equal = up
+        .iter()
+        .chain(vp.iter())
+        .zip(c.iter())
+        .map(|(&x, &y)| constant_time_eq(x, y))
+        .fold(0xff, |x, y| x & y);
+    select(&Kp, &Kbar, equal)
+}
+
+// The seed from which the error `e2` in a recipient's component `v` is sampled.  The errors must
+// be independent across recipients: Otherwise, the difference of two components `v` would reveal
+// the noiseless value `(t_hat_1 - t_hat_2)^T * r_hat`.
+fn noise_seed<P>(r: &B32, ek: &EncapsulationKey<P>) -> B32
+where
+    P: KemParams,
+{
+    J(&[r, &ek.h])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{MlKem1024Params, MlKem512Params, MlKem768Params};
+
+    fn round_trip_test<P>()
+    where
+        P: KemParams,
+    {
+        let mut rng = rand::thread_rng();
+        let rho: B32 = rand(&mut rng);
+
+        let (dks, eks): (Vec<_>, Vec<_>) = (0..4).map(|_| generate::<P>(&rho, &mut rng)).unzip();
+        let (ct, k_send) = encapsulate(&eks, &mut rng).unwrap();
+        assert_eq!(ct.v.len(), eks.len());
+
+        for (dk, v) in dks.iter().zip(ct.v.iter()) {
+            assert_eq!(decapsulate(dk, &ct.u, v), k_send);
+        }
+
+        // The components `v` are distinct, even though they share `r`
+        assert_ne!(ct.v[0], ct.v[1]);
+
+        // A component `v` is only valid for its own recipient
+        let Kbar = J(&[
+            dks[0].z.as_slice(),
+            P::concat_ct(ct.u.clone(), ct.v[1].clone()).as_ref(),
+        ]);
+        assert_eq!(decapsulate(&dks[0], &ct.u, &ct.v[1]), Kbar);
+
+        // Modifying the shared component results in implicit rejection for every recipient
+        let mut u = ct.u.clone();
+        u[0] ^= 0x01;
+        for (dk, v) in dks.iter().zip(ct.v.iter()) {
+            let Kbar = J(&[dk.z.as_slice(), P::concat_ct(u.clone(), v.clone()).as_ref()]);
+            assert_eq!(decapsulate(dk, &u, v), Kbar);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        round_trip_test::<MlKem512Params>();
+        round_trip_test::<MlKem768Params>();
+        round_trip_test::<MlKem1024Params>();
+    }
+
+    #[test]
+    fn errors() {
+        let mut rng = rand::thread_rng();
+        let rho1: B32 = rand(&mut rng);
+        let rho2: B32 = rand(&mut rng);
+
+        let (_, ek1) = generate::<MlKem768Params>(&rho1, &mut rng);
+        let (_, ek2) = generate::<MlKem768Params>(&rho2, &mut rng);
+
+        assert_eq!(
+            encapsulate::<MlKem768Params>(&[], &mut rng),
+            Err(Error::NoRecipients)
+        );
+        assert_eq!(encapsulate(&[ek1, ek2], &mut rng), Err(Error::SeedMismatch));
+    }
+}
@@ -0,0 +1,851 @@
+//! There are two such algorithms: ML-KEM-768+X25519 and ML-KEM-1024+X448.
+//!
+//! A session key is encrypted to a composite key by encapsulating to both component keys,
+//! combining the two shared secrets into a key-encryption key (KEK), and wrapping the session key
+//! with AES-256 key wrap.  The result is carried in a version 6 Public-Key Encrypted Session Key
+//! (PKESK) packet.
+//!
+//! ```
+//! # use ml_kem::openpgp::{Algorithm, Fingerprint, Pkesk, PublicKey, SecretKey};
+//! let mut rng = rand::thread_rng();
+//! let secret_key = SecretKey::generate(Algorithm::MlKem768X25519, &mut rng);
+//!
+//! // The key material of the public key packet
+//! let key_material = secret_key.public_key().to_bytes();
+//!
+//! let public_key = PublicKey::from_bytes(Algorithm::MlKem768X25519, &key_material).unwrap();
+//! let session_key = [0x42; 32];
+//! let recipient = Some(Fingerprint::V6([0x17; 32]));
+//! let pkesk = Pkesk::encrypt(&public_key, recipient, &session_key, &mut rng).unwrap();
+//! let body = pkesk.to_bytes();
+//!
+//! let pkesk = Pkesk::from_bytes(&body).unwrap();
+//! assert_eq!(pkesk.recipient, recipient);
+//! assert_eq!(pkesk.decrypt(&secret_key).unwrap(), session_key);
+//! ```
+//!
+//! # Key material
+//!
+//! The public key material is the ECDH public key followed by the ML-KEM encapsulation key, and
+//! the secret key material is the ECDH secret key followed by the 64-byte ML-KEM seed `d || z`.
+//! None of the fields has a length prefix, since the algorithm determines their lengths.
+//!
+//! # Key combiner
+//!
+//! The KEK is
+//!
+//! ```text
+//! KEK = SHA3-256(mlkemKeyShare || ecdhKeyShare || ecdhCipherText || ecdhPublicKey || algId
+//!                || "OpenPGPCompositeKDFv1" || 21)
+//! ```
+//!
+//! where `ecdhKeyShare` is the raw X25519 or X448 shared secret, `ecdhCipherText` is the
+//! sender's ephemeral ECDH public key, `ecdhPublicKey` is the recipient's ECDH public key, and
+//! `algId` is the one-byte algorithm identifier.
+//!
+//! The algorithm identifiers and the key combiner follow a draft specification, and may change
+//! before it is published.
+
+use aes_kw::KekAes256;
+use alloc::vec::Vec;
+use core::fmt;
+use rand_core::CryptoRngCore;
+use sha3::{Digest, Sha3_256};
+
+use crate::kem;
+use crate::param::EncodedCiphertext;
+use crate::util::B32;
+use crate::{Encoded, EncodedSizeUser, MlKem1024Params, MlKem768Params};
+
+/// The version of the PKESK packets produced by this module
+pub const PKESK_VERSION: u8 = 6;
+
+// The domain separator of the key combiner
+const DOMAIN_SEPARATOR: &[u8] = b"OpenPGPCompositeKDFv1";
+
+// The length of the ML-KEM seed `d || z`
+const SEED_LEN: usize = 64;
+
+// The length of the AES key wrap integrity check value
+const KW_IV_LEN: usize = 8;
+
+/// A composite ML-KEM public-key algorithm
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    /// ML-KEM-768+X25519
+    MlKem768X25519,
+
+    /// ML-KEM-1024+X448
+    MlKem1024X448,
+}
+
+impl Algorithm {
+    /// The public-key algorithm identifier
+    #[must_use]
+    pub const fn id(self) -> u8 {
+        match self {
+            Self::MlKem768X25519 => 35,
+            Self::MlKem1024X448 => 36,
+        }
+    }
+
+    /// The algorithm with the given public-key algorithm identifier
+    #[must_use]
+    pub const fn from_id(id: u8) -> Option<Self> {
+        match id {
+            35 => Some(Self::MlKem768X25519),
+            36 => Some(Self::MlKem1024X448),
+            _ => None,
+        }
+    }
+
+    /// The length of an ECDH public key, secret key, or ciphertext
+    #[must_use]
+    pub const fn ecdh_len(self) -> usize {
+        match self {
+            Self::MlKem768X25519 => 32,
+            Self::MlKem1024X448 => 56,
+        }
+    }
+
+    /// The length of an ML-KEM encapsulation key
+    #[must_use]
+    pub const fn mlkem_key_len(self) -> usize {
+        match self {
+            Self::MlKem768X25519 => 1184,
+            Self::MlKem1024X448 => 1568,
+        }
+    }
+
+    /// The length of an ML-KEM ciphertext
+    #[must_use]
+    pub const fn mlkem_ciphertext_len(self) -> usize {
+        match self {
+            Self::MlKem768X25519 => 1088,
+            Self::MlKem1024X448 => 1568,
+        }
+    }
+
+    /// The length of the public key material
+    #[must_use]
+    pub const fn public_key_len(self) -> usize {
+        self.ecdh_len() + self.mlkem_key_len()
+    }
+
+    /// The length of the secret key material
+    #[must_use]
+    pub const fn secret_key_len(self) -> usize {
+        self.ecdh_len() + SEED_LEN
+    }
+}
+
+/// The public key material of a composite key
+#[derive(Clone, Debug, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+pub enum PublicKey {
+    /// An ML-KEM-768+X25519 key
+    MlKem768X25519 {
+        /// The X25519 public key
+        x25519: x25519_dalek::PublicKey,
+
+        /// The ML-KEM-768 encapsulation key
+        mlkem: kem::EncapsulationKey<MlKem768Params>,
+    },
+
+    /// An ML-KEM-1024+X448 key
+    MlKem1024X448 {
+        /// The X448 public key
+        x448: [u8; 56],
+
+        /// The ML-KEM-1024 encapsulation key
+        mlkem: kem::EncapsulationKey<MlKem1024Params>,
+    },
+}
+
+impl PublicKey {
+    /// Decode the public key material of a key with algorithm `alg`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the encoding has the wrong length, if the ML-KEM encapsulation key
+    /// fails the modulus check of FIPS 203, or if the X448 public key has low order.
+    pub fn from_bytes(alg: Algorithm, bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != alg.public_key_len() {
+            return Err(Error::Length);
+        }
+        let (ecdh, mlkem) = bytes.split_at(alg.ecdh_len());
+
+        Ok(match alg {
+            Algorithm::MlKem768X25519 => Self::MlKem768X25519 {
+                x25519: x25519_dalek::PublicKey::from(array::<32>(ecdh)?),
+                mlkem: encapsulation_key(mlkem)?,
+            },
+            Algorithm::MlKem1024X448 => {
+                x448::PublicKey::from_bytes(ecdh).ok_or(Error::InvalidKey)?;
+                Self::MlKem1024X448 {
+                    x448: array(ecdh)?,
+                    mlkem: encapsulation_key(mlkem)?,
+                }
+            }
+        })
+    }
+
+    /// Encode the public key material
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.algorithm().public_key_len());
+        match self {
+            Self::MlKem768X25519 { x25519, mlkem } => {
+                bytes.extend_from_slice(x25519.as_bytes());
+                bytes.extend_from_slice(&mlkem.as_bytes());
+            }
+            Self::MlKem1024X448 {
+                x448: public,
+                mlkem,
+            } => {
+                bytes.extend_from_slice(public);
+                bytes.extend_from_slice(&mlkem.as_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// The algorithm of the key
+    #[must_use]
+    pub fn algorithm(&self) -> Algorithm {
+        match self {
+            Self::MlKem768X25519 { .. } => Algorithm::MlKem768X25519,
+            Self::MlKem1024X448 { .. } => Algorithm::MlKem1024X448,
+        }
+    }
+}
+
+fn encapsulation_key<P: crate::param::KemParams>(
+    bytes: &[u8],
+) -> Result<kem::EncapsulationKey<P>, Error> {
+    let bytes = Encoded::<kem::EncapsulationKey<P>>::try_from(bytes).map_err(|_| Error::Length)?;
+    kem::EncapsulationKey::from_bytes_checked(&bytes).ok_or(Error::InvalidKey)
+}
+
+fn array<const N: usize>(bytes: &[u8]) -> Result<[u8; N], Error> {
+    bytes.try_into().map_err(|_| Error::Length)
+}
+
+/// The secret key material of a composite key
+#[derive(Clone)]
+pub struct SecretKey {
+    alg: Algorithm,
+
+    // The ECDH secret key, in the first `alg.ecdh_len()` bytes
+    ecdh: [u8; 56],
+
+    seed: [u8; SEED_LEN],
+}
+
+impl SecretKey {
+    /// Generate a new key with algorithm `alg`.
+    pub fn generate(alg: Algorithm, rng: &mut impl CryptoRngCore) -> Self {
+        let mut ecdh = [0; 56];
+        rng.fill_bytes(&mut ecdh[..alg.ecdh_len()]);
+        let mut seed = [0; SEED_LEN];
+        rng.fill_bytes(&mut seed);
+        Self { alg, ecdh, seed }
+    }
+
+    /// Decode the secret key material of a key with algorithm `alg`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the encoding has the wrong length.
+    pub fn from_bytes(alg: Algorithm, bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != alg.secret_key_len() {
+            return Err(Error::Length);
+        }
+        let (secret, seed) = bytes.split_at(alg.ecdh_len());
+
+        let mut ecdh = [0; 56];
+        ecdh[..secret.len()].copy_from_slice(secret);
+        Ok(Self {
+            alg,
+            ecdh,
+            seed: array(seed)?,
+        })
+    }
+
+    /// Encode the secret key material
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.alg.secret_key_len());
+        bytes.extend_from_slice(&self.ecdh[..self.alg.ecdh_len()]);
+        bytes.extend_from_slice(&self.seed);
+        bytes
+    }
+
+    /// The algorithm of the key
+    #[must_use]
+    pub fn algorithm(&self) -> Algorithm {
+        self.alg
+    }
+
+    /// The corresponding public key
+    #[must_use]
+    pub fn public_key(&self) -> PublicKey {
+        match self.alg {
+            Algorithm::MlKem768X25519 => PublicKey::MlKem768X25519 {
+                x25519: x25519_dalek::PublicKey::from(&self.x25519()),
+                mlkem: self
+                    .decapsulation_key::<MlKem768Params>()
+                    .encapsulation_key()
+                    .clone(),
+            },
+            Algorithm::MlKem1024X448 => PublicKey::MlKem1024X448 {
+                x448: x448::x448_unchecked(self.ecdh, x448::X448_BASEPOINT_BYTES),
+                mlkem: self
+                    .decapsulation_key::<MlKem1024Params>()
+                    .encapsulation_key()
+                    .clone(),
+            },
+        }
+    }
+
+    fn x25519(&self) -> x25519_dalek::StaticSecret {
+        let mut secret = [0; 32];
+        secret.copy_from_slice(&self.ecdh[..32]);
+        x25519_dalek::StaticSecret::from(secret)
+    }
+
+    fn decapsulation_key<P: crate::param::KemParams>(&self) -> kem::DecapsulationKey<P> {
+        let d = B32::from_fn(|i| self.seed[i]);
+        let z = B32::from_fn(|i| self.seed[32 + i]);
+        kem::DecapsulationKey::generate_deterministic(&d, &z)
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretKey")
+            .field("alg", &self.alg)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The fingerprint of the recipient's key, which identifies it in a PKESK packet
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Fingerprint {
+    /// The fingerprint of a version 4 key
+    V4([u8; 20]),
+
+    /// The fingerprint of a version 6 key
+    V6([u8; 32]),
+}
+
+/// A version 6 Public-Key Encrypted Session Key packet for a composite ML-KEM key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pkesk {
+    /// The fingerprint of the recipient's key, or `None` for an anonymous recipient
+    pub recipient: Option<Fingerprint>,
+
+    /// The public-key algorithm
+    pub alg: Algorithm,
+
+    /// The sender's ephemeral ECDH public key
+    pub ecdh_ciphertext: Vec<u8>,
+
+    /// The ML-KEM ciphertext
+    pub mlkem_ciphertext: Vec<u8>,
+
+    /// The session key, wrapped with AES-256 key wrap
+    pub wrapped_session_key: Vec<u8>,
+}
+
+impl Pkesk {
+    /// Encrypt `session_key` to `public_key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SessionKey`] if the session key cannot be wrapped with AES key wrap, i.e.,
+    /// if its length is not a multiple of 8 between 16 and 248 bytes, and [`Error::InvalidKey`]
+    /// if the X25519 public key has low order.
+    pub fn encrypt(
+        public_key: &PublicKey,
+        recipient: Option<Fingerprint>,
+        session_key: &[u8],
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<Self, Error> {
+        if session_key.len() < 2 * KW_IV_LEN
+            || session_key.len() % KW_IV_LEN != 0
+            || session_key.len() + KW_IV_LEN > 0xff
+        {
+            return Err(Error::SessionKey);
+        }
+
+        let alg = public_key.algorithm();
+        let (ecdh_ciphertext, mlkem_ciphertext, kek) = match public_key {
+            PublicKey::MlKem768X25519 { x25519, mlkem } => {
+                let ephemeral = x25519_dalek::EphemeralSecret::random_from_rng(&mut *rng);
+                let ecdh_ciphertext = x25519_dalek::PublicKey::from(&ephemeral);
+                let ecdh_share = ephemeral.diffie_hellman(x25519);
+                if !ecdh_share.was_contributory() {
+                    return Err(Error::InvalidKey);
+                }
+
+                let (ct, ss) = kem::encapsulate(mlkem, rng);
+                let kek = combine(
+                    alg,
+                    &ss,
+                    ecdh_share.as_bytes(),
+                    ecdh_ciphertext.as_bytes(),
+                    x25519.as_bytes(),
+                );
+                (ecdh_ciphertext.as_bytes().to_vec(), ct.to_vec(), kek)
+            }
+            PublicKey::MlKem1024X448 {
+                x448: public,
+                mlkem,
+            } => {
+                let mut ephemeral = [0; 56];
+                rng.fill_bytes(&mut ephemeral);
+                let ecdh_ciphertext = x448::x448_unchecked(ephemeral, x448::X448_BASEPOINT_BYTES);
+                let ecdh_share = x448::x448(ephemeral, *public).ok_or(Error::InvalidKey)?;
+
+                let (ct, ss) = kem::encapsulate(mlkem, rng);
+                let kek = combine(alg, &ss, &ecdh_share, &ecdh_ciphertext, public);
+                (ecdh_ciphertext.to_vec(), ct.to_vec(), kek)
+            }
+        };
+
+        let wrapped_session_key = KekAes256::from(kek)
+            .wrap_vec(session_key)
+            .map_err(|_| Error::SessionKey)?;
+        Ok(Self {
+            recipient,
+            alg,
+            ecdh_ciphertext,
+            mlkem_ciphertext,
+            wrapped_session_key,
+        })
+    }
+
+    /// Decrypt the session key with `secret_key`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key is for a different algorithm, if a ciphertext has the wrong
+    /// length or the ECDH ciphertext has low order, or if the session key does not unwrap, e.g.,
+    /// because it was encrypted to a different key.
+    pub fn decrypt(&self, secret_key: &SecretKey) -> Result<Vec<u8>, Error> {
+        if secret_key.alg != self.alg {
+            return Err(Error::Algorithm);
+        }
+
+        let kek = match self.alg {
+            Algorithm::MlKem768X25519 => {
+                let secret = secret_key.x25519();
+                let ecdh_ciphertext =
+                    x25519_dalek::PublicKey::from(array::<32>(&self.ecdh_ciphertext)?);
+                let ecdh_share = secret.diffie_hellman(&ecdh_ciphertext);
+                if !ecdh_share.was_contributory() {
+                    return Err(Error::InvalidKey);
+                }
+
+                let ct = EncodedCiphertext::<MlKem768Params>::try_from(&self.mlkem_ciphertext[..])
+                    .map_err(|_| Error::Length)?;
+                let dk = secret_key.decapsulation_key::<MlKem768Params>();
+                let ss = kem::decapsulate(&dk, &ct);
+                combine(
+                    self.alg,
+                    &ss,
+                    ecdh_share.as_bytes(),
+                    ecdh_ciphertext.as_bytes(),
+                    x25519_dalek::PublicKey::from(&secret).as_bytes(),
+                )
+            }
+            Algorithm::MlKem1024X448 => {
+                let ecdh_ciphertext = array::<56>(&self.ecdh_ciphertext)?;
+                let ecdh_share =
+                    x448::x448(secret_key.ecdh, ecdh_ciphertext).ok_or(Error::InvalidKey)?;
+                let ecdh_public = x448::x448_unchecked(secret_key.ecdh, x448::X448_BASEPOINT_BYTES);
+
+                let ct = EncodedCiphertext::<MlKem1024Params>::try_from(&self.mlkem_ciphertext[..])
+                    .map_err(|_| Error::Length)?;
+                let dk = secret_key.decapsulation_key::<MlKem1024Params>();
+                let ss = kem::decapsulate(&dk, &ct);
+                combine(self.alg, &ss, &ecdh_share, &ecdh_ciphertext, &ecdh_public)
+            }
+        };
+
+        KekAes256::from(kek)
+            .unwrap_vec(&self.wrapped_session_key)
+            .map_err(|_| Error::SessionKey)
+    }
+
+    /// Encode the body of the packet, whose tag is 1
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            3 + 32
+                + self.ecdh_ciphertext.len()
+                + self.mlkem_ciphertext.len()
+                + 1
+                + self.wrapped_session_key.len(),
+        );
+        bytes.push(PKESK_VERSION);
+        match &self.recipient {
+            None => bytes.push(0),
+            Some(Fingerprint::V4(fingerprint)) => {
+                bytes.extend_from_slice(&[21, 4]);
+                bytes.extend_from_slice(fingerprint);
+            }
+            Some(Fingerprint::V6(fingerprint)) => {
+                bytes.extend_from_slice(&[33, 6]);
+                bytes.extend_from_slice(fingerprint);
+            }
+        }
+        bytes.push(self.alg.id());
+        bytes.extend_from_slice(&self.ecdh_ciphertext);
+        bytes.extend_from_slice(&self.mlkem_ciphertext);
+        // The length is checked when the session key is wrapped, so this never truncates
+        #[allow(clippy::cast_possible_truncation)]
+        bytes.push(self.wrapped_session_key.len() as u8);
+        bytes.extend_from_slice(&self.wrapped_session_key);
+        bytes
+    }
+
+    /// Decode the body of a packet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the packet is not a version 6 PKESK packet for a composite ML-KEM key,
+    /// or if it is malformed.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader(bytes);
+        if reader.byte()? != PKESK_VERSION {
+            return Err(Error::Version);
+        }
+
+        let recipient = match reader.byte()? {
+            0 => None,
+            21 if reader.byte()? == 4 => Some(Fingerprint::V4(array(reader.take(20)?)?)),
+            33 if reader.byte()? == 6 => Some(Fingerprint::V6(array(reader.take(32)?)?)),
+            _ => return Err(Error::Version),
+        };
+
+        let alg = Algorithm::from_id(reader.byte()?).ok_or(Error::Algorithm)?;
+        let ecdh_ciphertext = reader.take(alg.ecdh_len())?.to_vec();
+        let mlkem_ciphertext = reader.take(alg.mlkem_ciphertext_len())?.to_vec();
+        let len = reader.byte()?;
+        let wrapped_session_key = reader.take(len.into())?.to_vec();
+        if !reader.0.is_empty() {
+            return Err(Error::Length);
+        }
+
+        Ok(Self {
+            recipient,
+            alg,
+            ecdh_ciphertext,
+            mlkem_ciphertext,
+            wrapped_session_key,
+        })
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(Error::Length);
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        self.take(1).map(|b| b[0])
+    }
+}
+
+// The KEM combiner, which derives the KEK from the component shared secrets
+fn combine(
+    alg: Algorithm,
+    mlkem_share: &[u8],
+    ecdh_share: &[u8],
+    ecdh_ciphertext: &[u8],
+    ecdh_public_key: &[u8],
+) -> [u8; 32] {
+    // The domain separator is a short constant, so its length fits in a byte
+    #[allow(clippy::cast_possible_truncation)]
+    let domain_separator_len = DOMAIN_SEPARATOR.len() as u8;
+
+    Sha3_256::new()
+        .chain_update(mlkem_share)
+        .chain_update(ecdh_share)
+        .chain_update(ecdh_ciphertext)
+        .chain_update(ecdh_public_key)
+        .chain_update([alg.id()])
+        .chain_update(DOMAIN_SEPARATOR)
+        .chain_update([domain_separator_len])
+        .finalize()
+        .into()
+}
+
+/// An error in decoding keys or packets, or in encrypting or decrypting a session key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// An encoding has the wrong length
+    Length,
+
+    /// The packet version or key version is not supported
+    Version,
+
+    /// The algorithm is not a composite ML-KEM algorithm, or does not match the key
+    Algorithm,
+
+    /// A key or ECDH ciphertext is invalid
+    InvalidKey,
+
+    /// The session key could not be wrapped or unwrapped
+    SessionKey,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Length => "encoding has the wrong length",
+            Self::Version => "unsupported version",
+            Self::Algorithm => "unsupported or mismatched algorithm",
+            Self::InvalidKey => "invalid key",
+            Self::SessionKey => "session key could not be wrapped or unwrapped",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hex_literal::hex;
+
+    const ALGORITHMS: [Algorithm; 2] = [Algorithm::MlKem768X25519, Algorithm::MlKem1024X448];
+
+    #[test]
+    fn lengths() {
+        assert_eq!(Algorithm::MlKem768X25519.public_key_len(), 32 + 1184);
+        assert_eq!(Algorithm::MlKem1024X448.public_key_len(), 56 + 1568);
+        assert_eq!(Algorithm::MlKem768X25519.secret_key_len(), 32 + 64);
+        assert_eq!(Algorithm::MlKem1024X448.secret_key_len(), 56 + 64);
+
+        for alg in ALGORITHMS {
+            assert_eq!(Algorithm::from_id(alg.id()), Some(alg));
+        }
+        assert_eq!(Algorithm::from_id(18), None);
+    }
+
+    #[test]
+    fn keys() {
+        let mut rng = rand::thread_rng();
+        for alg in ALGORITHMS {
+            let secret_key = SecretKey::generate(alg, &mut rng);
+            let public_key = secret_key.public_key();
+            assert_eq!(public_key.algorithm(), alg);
+
+            let bytes = secret_key.to_bytes();
+            assert_eq!(bytes.len(), alg.secret_key_len());
+            let decoded = SecretKey::from_bytes(alg, &bytes).unwrap();
+            assert_eq!(decoded.public_key(), public_key);
+
+            let bytes = public_key.to_bytes();
+            assert_eq!(bytes.len(), alg.public_key_len());
+            assert_eq!(PublicKey::from_bytes(alg, &bytes), Ok(public_key));
+
+            assert_eq!(PublicKey::from_bytes(alg, &bytes[1..]), Err(Error::Length));
+            assert_eq!(
+                SecretKey::from_bytes(alg, &bytes).map(|_| ()),
+                Err(Error::Length)
+            );
+
+            // An ML-KEM encapsulation key that fails the modulus check
+            let mut invalid = bytes.clone();
+            invalid[alg.ecdh_len()..alg.ecdh_len() + 2].fill(0xff);
+            assert_eq!(PublicKey::from_bytes(alg, &invalid), Err(Error::InvalidKey));
+        }
+
+        // A low-order X448 public key
+        let mut bytes = SecretKey::generate(Algorithm::MlKem1024X448, &mut rng)
+            .public_key()
+            .to_bytes();
+        bytes[..56].fill(0);
+        assert_eq!(
+            PublicKey::from_bytes(Algorithm::MlKem1024X448, &bytes),
+            Err(Error::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn x448() {
+        // RFC 7748, Section 6.2
+        let mut bytes = [0u8; 56 + 64];
+        bytes[..56].copy_from_slice(&hex!(
+            "9a8f4925d1519f5775cf46b04b5800d4ee9ee8bae8bc5565d498c28d"
+            "d9c9baf574a9419744897391006382a6f127ab1d9ac2d8c0a598726b"
+        ));
+        let secret_key = SecretKey::from_bytes(Algorithm::MlKem1024X448, &bytes).unwrap();
+        let PublicKey::MlKem1024X448 { x448, .. } = secret_key.public_key() else {
+            panic!("wrong algorithm");
+        };
+        assert_eq!(
+            x448,
+            hex!(
+                "9b08f7cc31b7e3e67d22d5aea121074a273bd2b83de09c63faa73d2c"
+                "22c5d9bbc836647241d953d40c5b12da88120d53177f80e532c41fa0"
+            )
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = rand::thread_rng();
+        let recipients = [
+            None,
+            Some(Fingerprint::V4([4; 20])),
+            Some(Fingerprint::V6([6; 32])),
+        ];
+        for alg in ALGORITHMS {
+            let secret_key = SecretKey::generate(alg, &mut rng);
+            let other_key = SecretKey::generate(alg, &mut rng);
+            for (recipient, session_key) in recipients.into_iter().zip([16, 24, 32]) {
+                let session_key = &[0x42; 32][..session_key];
+                let pkesk =
+                    Pkesk::encrypt(&secret_key.public_key(), recipient, session_key, &mut rng)
+                        .unwrap();
+                assert_eq!(pkesk.ecdh_ciphertext.len(), alg.ecdh_len());
+                assert_eq!(pkesk.mlkem_ciphertext.len(), alg.mlkem_ciphertext_len());
+                assert_eq!(pkesk.wrapped_session_key.len(), session_key.len() + 8);
+
+                let bytes = pkesk.to_bytes();
+                let decoded = Pkesk::from_bytes(&bytes).unwrap();
+                assert_eq!(decoded, pkesk);
+                assert_eq!(decoded.decrypt(&secret_key).unwrap(), session_key);
+
+                // The wrong key gets an implicitly rejected ML-KEM shared key, and so a wrong KEK
+                assert_eq!(decoded.decrypt(&other_key), Err(Error::SessionKey));
+
+                // So does a modified ML-KEM ciphertext
+                let mut modified = pkesk.clone();
+                modified.mlkem_ciphertext[0] ^= 1;
+                assert_eq!(modified.decrypt(&secret_key), Err(Error::SessionKey));
+
+                // Truncation and trailing data
+                assert_eq!(
+                    Pkesk::from_bytes(&bytes[..bytes.len() - 1]),
+                    Err(Error::Length)
+                );
+                let mut extended = bytes.clone();
+                extended.push(0);
+                assert_eq!(Pkesk::from_bytes(&extended), Err(Error::Length));
+            }
+        }
+
+        let x25519 = SecretKey::generate(Algorithm::MlKem768X25519, &mut rng);
+        let x448 = SecretKey::generate(Algorithm::MlKem1024X448, &mut rng);
+        let pkesk = Pkesk::encrypt(&x25519.public_key(), None, &[0; 32], &mut rng).unwrap();
+        assert_eq!(pkesk.decrypt(&x448), Err(Error::Algorithm));
+    }
+
+    #[test]
+    fn encoding() {
+        let pkesk = Pkesk {
+            recipient: Some(Fingerprint::V6([0xaa; 32])),
+            alg: Algorithm::MlKem768X25519,
+            ecdh_ciphertext: [0xbb; 32].to_vec(),
+            mlkem_ciphertext: [0xcc; 1088].to_vec(),
+            wrapped_session_key: [0xdd; 40].to_vec(),
+        };
+        let bytes = pkesk.to_bytes();
+        assert_eq!(bytes.len(), 3 + 32 + 1 + 32 + 1088 + 1 + 40);
+        assert_eq!(bytes[..3], [6, 33, 6]);
+        assert_eq!(bytes[34..36], [0xaa, 35]);
+        assert_eq!(bytes[35 + 32 + 1088..][..2], [0xcc, 40]);
+
+        // Unsupported versions and algorithms
+        let mut modified = bytes.clone();
+        modified[0] = 3;
+        assert_eq!(Pkesk::from_bytes(&modified), Err(Error::Version));
+        let mut modified = bytes.clone();
+        modified[2] = 4;
+        assert_eq!(Pkesk::from_bytes(&modified), Err(Error::Version));
+        let mut modified = bytes.clone();
+        modified[35] = 18;
+        assert_eq!(Pkesk::from_bytes(&modified), Err(Error::Algorithm));
+
+        // Session keys that AES key wrap cannot handle
+        let mut rng = rand::thread_rng();
+        let public_key = SecretKey::generate(Algorithm::MlKem768X25519, &mut rng).public_key();
+        for len in [8, 20, 256] {
+            assert_eq!(
+                Pkesk::encrypt(&public_key, None, &[0; 256][..len], &mut rng),
+                Err(Error::SessionKey)
+            );
+        }
+    }
+
+    /// The packet was produced independently of this crate, with OpenSSL's
+    /// ML-KEM and X25519 (via Python `cryptography`) and `hashlib`'s SHA3-256.
+    #[test]
+    fn pkesk_known_answer() {
+        let secret_key = SecretKey::from_bytes(
+            Algorithm::MlKem768X25519,
+            &[[0x11; 32], [0x01; 32], [0x02; 32]].concat(),
+        )
+        .unwrap();
+        let bytes = hex!(
+            "0621061717171717171717171717171717171717171717171717171717171717171717230faa684ed28867b97f4a6a2d"
+            "ee5df8ce974e76b7018e3f22a1c4cf2678570f2098a95566b2c35b18403566c72288e89b8d6d6bfe2d5336dedf4da883"
+            "17964f2c5bd6d7bbef7137c13a3d5226471d2006e0131774e434e4d3787a8bf476fd3ae4e38f889369452310f53f73d1"
+            "fde2a60a3ff7ea048e0a7c2da12cb1e85649b531cf5f7fd7adf0fa7371a8286d3581033e18cfcbd1371e7850947ca9fb"
+            "0d77f58f307a60826f59a701a1792c2dea2fd9714f41adb58bb1b1551d95b520b1991883d9ceb80a36a5bc24cdc7c93a"
+            "7719bb0b1882bee16f0da0fd485b44960341bd5bc1cf6b1b1c853de962fc7cabb56367f373cc83bbf3a391de172e77bb"
+            "fc4395e375c2126ebd9bdc8bfb889a90e785cc89934579f2c1f707c01964febb3fe865c4e021b835bb1ec629bae3d46a"
+            "41a08290b803c9153a8ed049baf51f247ffde7360706967967ffbd67e99b6edf286724b3da1c0e27ed0254bb2a23c085"
+            "c9032850f87d181aad4eb68dc40df772a867e6e667e2b92c86a87ab81b11e99df99a18a4e4ecae0b7e97fd97c1cfc3b0"
+            "e064b8de4968ca07e29ee864e31c831493bdcc02bf2a6b4e9800c4c5ca4bd3ce450fca856369d3efd55dc8d1d1f0eb19"
+            "ba1666da99d519af9f7943d69648cafeae6a4bb139ecf520d9444bccc90a926b208c761884602b70e8825f6fffcb5e40"
+            "0bd8819f3093d5a8074ff88956712206c72cbf0047764badad0141cff58d931f2f2c7a660c7d644f0d8ee8d88a94d4fe"
+            "dd48104638b24d825d6b3acf1a1272238c7f644066eb67da438277a36919106cfd7d0dbc1cc3c5d2ad043e186d45f702"
+            "d301bef8e2e777d9bc180611ba5c00014728f8bd435c28e86011d6e156369a7656160ca165c02a2098d9e9f15bc06e0e"
+            "84a6f7eddace01aefae024b79463718cc47f1d1e62e3edd1101c7dec87a3be64ba42bdf6f78614972fc960a99a13c149"
+            "8b972a26d001db8b93441333ff9b819c176447e1a715c9f325b2192ae0ff8cc83d791c1474d575121ec489bb0d5d241b"
+            "2e36636472c12e59d69ae6977bab490e0ea002a383bbc8cb91d519bdf6adfd074e1b9fc387afffe7a50e88e4a57088bf"
+            "b17fa507aabb4ee34eac69304f74ff3ef0d404834dd4c8a47ffba83fccfc68a232b4edba11b8e8adc84992c302decede"
+            "a4497253c0106515f6773e36868e469def3759bc1defddae709edd70982d7a21c4ebcc5cea57eebfc468ccfc759d2b3a"
+            "ca6ac81fc83a97aa9582f8c8f2f6db58c464735f50745b5eafb453fbae462008a5eaae6ec0c16774aad94151e2d2679c"
+            "11bf1e994a0b3ad6ffd5234f3b2919d0ad3fd2e16dc8a7a9b07efd49ea82824d742ff5896594b1d19e78f4a04d5605f8"
+            "5dabdea0cd2db9d3a10d6c62378a37d05485106d9519ee7b11e1ad61c014f639e43b4660e504223f1024ac3f3fa944a7"
+            "e3b5f42e37279d1a26cea06397031f638aeaf5486a4ba0e946e6fbe419c0e3b3035f3989ed8ff103372e422d73184004"
+            "fab6895593afd581bda2ce5ec72acca954394b32ef14be487d401523079c6c910080bb66ad08c7f4d506ef23e8f7df94"
+            "5ec69a3f280ae927fcd8a63cfc650bdbd68634614dfe96d355cef8d78127118277ec81ee2c970817e1321f7c7b"
+        );
+
+        let pkesk = Pkesk::from_bytes(&bytes).unwrap();
+        assert_eq!(pkesk.recipient, Some(Fingerprint::V6([0x17; 32])));
+        assert_eq!(pkesk.to_bytes(), bytes);
+        let session_key = hex!("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f");
+        assert_eq!(pkesk.decrypt(&secret_key).unwrap(), session_key);
+    }
+
+    #[test]
+    fn combiner() {
+        let kek = combine(
+            Algorithm::MlKem768X25519,
+            &[1; 32],
+            &[2; 32],
+            &[3; 32],
+            &[4; 32],
+        );
+        let mut input = [[1; 32], [2; 32], [3; 32], [4; 32]].concat();
+        input.push(35);
+        input.extend_from_slice(b"OpenPGPCompositeKDFv1");
+        input.push(21);
+        assert_eq!(kek, <[u8; 32]>::from(Sha3_256::digest(&input)));
+    }
+}
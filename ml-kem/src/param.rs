@@ -25,6 +25,17 @@ use hybrid_array::{
 use crate::algebra::{FieldElement, NttVector};
 use crate::encode::Encode;
 use crate::util::{Flatten, Unflatten, B32};
+#[cfg(any(
+    feature = "seal",
+    feature = "ikev2",
+    feature = "jose",
+    feature = "cose",
+    feature = "cms"
+))]
+use {
+    crate::{MlKem1024Params, MlKem512Params, MlKem768Params},
+    hybrid_array::typenum::Unsigned,
+};
 
 /// An array length with other useful properties
 pub trait ArraySize: hybrid_array::ArraySize + PartialEq + Debug {}
@@ -33,14 +44,22 @@ impl<T> ArraySize for T where T: hybrid_array::ArraySize + PartialEq + Debug {}
 
 /// An integer that can be used as a length for encoded values.
 pub trait EncodingSize: ArraySize {
+    /// The size of an encoded polynomial, `32 * D` bytes
     type EncodedPolynomialSize: ArraySize;
+
+    /// The number of values that fit in a whole number of bytes
     type ValueStep: ArraySize;
+
+    /// The number of bytes that hold `ValueStep` values
     type ByteStep: ArraySize;
 }
 
 type EncodingUnit<D> = Quot<Prod<D, U8>, Gcf<D, U8>>;
 
+/// The size of a polynomial encoded with `D` bits per coefficient
 pub type EncodedPolynomialSize<D> = <D as EncodingSize>::EncodedPolynomialSize;
+
+/// A polynomial encoded with `D` bits per coefficient
 pub type EncodedPolynomial<D> = Array<u8, EncodedPolynomialSize<D>>;
 
 impl<D> EncodingSize for D
@@ -62,14 +81,21 @@ pub trait VectorEncodingSize<K>: EncodingSize
 where
     K: ArraySize,
 {
+    /// The size of a vector of `K` polynomials encoded with `Self` bits per coefficient
     type EncodedPolynomialVectorSize: ArraySize;
 
+    /// Concatenate `K` encoded polynomials into an encoded vector
     fn flatten(polys: Array<EncodedPolynomial<Self>, K>) -> EncodedPolynomialVector<Self, K>;
+
+    /// Split an encoded vector into its `K` encoded polynomials
     fn unflatten(vec: &EncodedPolynomialVector<Self, K>) -> Array<&EncodedPolynomial<Self>, K>;
 }
 
+/// The size of a vector of `K` polynomials encoded with `D` bits per coefficient
 pub type EncodedPolynomialVectorSize<D, K> =
     <D as VectorEncodingSize<K>>::EncodedPolynomialVectorSize;
+
+/// A vector of `K` polynomials encoded with `D` bits per coefficient
 pub type EncodedPolynomialVector<D, K> = Array<u8, EncodedPolynomialVectorSize<D, K>>;
 
 impl<D, K> VectorEncodingSize<K> for D
@@ -93,8 +119,13 @@ where
 
 /// An integer that describes a bit length to be used in CBD sampling
 pub trait CbdSamplingSize: ArraySize {
+    /// The number of bits of PRF output consumed per coefficient, `2 * eta`
     type SampleSize: EncodingSize;
+
+    /// The number of possible samples, `2^(2 * eta)`
     type OnesSize: ArraySize;
+
+    /// The coefficient value corresponding to each possible sample
     const ONES: Array<FieldElement, Self::OnesSize>;
 }
 
@@ -163,31 +194,101 @@ pub trait ParameterSet: Default + Clone + Debug + PartialEq {
     type Dv: EncodingSize;
 }
 
+/// The standard parameter sets of FIPS 203, which protocols identify by name
+#[cfg(any(
+    feature = "seal",
+    feature = "ikev2",
+    feature = "jose",
+    feature = "cose",
+    feature = "cms"
+))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum StandardParams {
+    MlKem512,
+    MlKem768,
+    MlKem1024,
+}
+
+#[cfg(any(
+    feature = "seal",
+    feature = "ikev2",
+    feature = "jose",
+    feature = "cose",
+    feature = "cms"
+))]
+impl StandardParams {
+    /// The standard parameter set that `P` is, if any.  Every parameter is compared, so that a
+    /// custom parameter set with the same `k` as a standard one is not mistaken for it.
+    pub(crate) fn of<P: ParameterSet>() -> Option<Self> {
+        if Self::same::<P, MlKem512Params>() {
+            Some(Self::MlKem512)
+        } else if Self::same::<P, MlKem768Params>() {
+            Some(Self::MlKem768)
+        } else if Self::same::<P, MlKem1024Params>() {
+            Some(Self::MlKem1024)
+        } else {
+            None
+        }
+    }
+
+    fn same<P: ParameterSet, Q: ParameterSet>() -> bool {
+        P::K::USIZE == Q::K::USIZE
+            && P::Eta1::USIZE == Q::Eta1::USIZE
+            && P::Eta2::USIZE == Q::Eta2::USIZE
+            && P::Du::USIZE == Q::Du::USIZE
+            && P::Dv::USIZE == Q::Dv::USIZE
+    }
+}
+
 type EncodedUSize<P> = EncodedPolynomialVectorSize<<P as ParameterSet>::Du, <P as ParameterSet>::K>;
 type EncodedVSize<P> = EncodedPolynomialSize<<P as ParameterSet>::Dv>;
 
-type EncodedU<P> = Array<u8, EncodedUSize<P>>;
-type EncodedV<P> = Array<u8, EncodedVSize<P>>;
+/// The encoded `u` component of a ciphertext, `c1`
+pub type EncodedU<P> = Array<u8, EncodedUSize<P>>;
+
+/// The encoded `v` component of a ciphertext, `c2`
+pub type EncodedV<P> = Array<u8, EncodedVSize<P>>;
 
 /// Derived parameter relevant to K-PKE
 pub trait PkeParams: ParameterSet {
+    /// The size of an NTT vector encoded with 12 bits per coefficient
     type NttVectorSize: ArraySize;
+
+    /// The size of an encoded encryption key, `(t_hat || rho)`
     type EncryptionKeySize: ArraySize;
+
+    /// The size of an encoded ciphertext, `(c1 || c2)`
     type CiphertextSize: ArraySize;
 
+    /// Encode an NTT vector with 12 bits per coefficient
     fn encode_u12(p: &NttVector<Self::K>) -> EncodedNttVector<Self>;
+
+    /// Decode an NTT vector with 12 bits per coefficient, reducing each coefficient mod q
     fn decode_u12(v: &EncodedNttVector<Self>) -> NttVector<Self::K>;
 
+    /// Assemble a ciphertext from the encoded `u` and `v` components
     fn concat_ct(u: EncodedU<Self>, v: EncodedV<Self>) -> EncodedCiphertext<Self>;
+
+    /// Split a ciphertext into the encoded `u` and `v` components
     fn split_ct(ct: &EncodedCiphertext<Self>) -> (&EncodedU<Self>, &EncodedV<Self>);
 
+    /// Assemble an encoded encryption key from the encoded `t_hat` and `rho`
     fn concat_ek(t_hat: EncodedNttVector<Self>, rho: B32) -> EncodedEncryptionKey<Self>;
+
+    /// Split an encoded encryption key into the encoded `t_hat` and `rho`
     fn split_ek(ek: &EncodedEncryptionKey<Self>) -> (&EncodedNttVector<Self>, &B32);
 }
 
+/// An NTT vector encoded with 12 bits per coefficient
 pub type EncodedNttVector<P> = Array<u8, <P as PkeParams>::NttVectorSize>;
+
+/// An encoded K-PKE decryption key, `s_hat`
 pub type EncodedDecryptionKey<P> = Array<u8, <P as PkeParams>::NttVectorSize>;
+
+/// An encoded K-PKE encryption key, `(t_hat || rho)`
 pub type EncodedEncryptionKey<P> = Array<u8, <P as PkeParams>::EncryptionKeySize>;
+
+/// An encoded K-PKE ciphertext, `(c1 || c2)`
 pub type EncodedCiphertext<P> = Array<u8, <P as PkeParams>::CiphertextSize>;
 
 impl<P> PkeParams for P
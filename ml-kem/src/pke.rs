@@ -1,12 +1,29 @@
 use hybrid_array::typenum::{Unsigned, U1};
 
-use crate::algebra::{NttMatrix, NttVector, Polynomial, PolynomialVector};
+use crate::algebra::{NttVector, Polynomial, PolynomialVector};
 use crate::compress::Compress;
 use crate::crypto::{G, PRF};
 use crate::encode::Encode;
-use crate::param::{EncodedCiphertext, EncodedDecryptionKey, EncodedEncryptionKey, PkeParams};
+use crate::param::{
+    EncodedCiphertext, EncodedDecryptionKey, EncodedEncryptionKey, EncodedU, EncodedV, PkeParams,
+};
 use crate::util::B32;
 
+#[cfg(feature = "alloc")]
+use crate::param::ArraySize;
+
+#[cfg(any(test, not(feature = "low-memory")))]
+use crate::algebra::NttMatrix;
+
+#[cfg(any(test, feature = "low-memory"))]
+use {
+    crate::algebra::NttPolynomial,
+    crate::crypto::XOF,
+    crate::param::{EncodedPolynomial, VectorEncodingSize},
+    crate::util::Truncate,
+    hybrid_array::Array,
+};
+
 /// A `DecryptionKey` provides the ability to generate a new key pair, and decrypt an
 /// encrypted value.
 #[derive(Clone, Default, Debug, PartialEq)]
@@ -23,21 +40,38 @@ where
 {
     /// Generate a new random decryption key according to the `K-PKE.KeyGen` procedure.
     // Algorithm 12. K-PKE.KeyGen()
+    #[must_use]
     pub fn generate(d: &B32) -> (Self, EncryptionKey<P>) {
         // Generate random seeds
         let (rho, sigma) = G(&[d]);
+        Self::generate_from_seeds(rho, &sigma)
+    }
 
-        // Sample pseudo-random matrix and vectors
-        let A_hat: NttMatrix<P::K> = NttMatrix::sample_uniform(&rho, false);
-        let s: PolynomialVector<P::K> = PolynomialVector::sample_cbd::<P::Eta1>(&sigma, 0);
-        let e: PolynomialVector<P::K> = PolynomialVector::sample_cbd::<P::Eta1>(&sigma, P::K::U8);
+    // The remainder of Algorithm 12, given the seed `rho` of the public matrix and the seed
+    // `sigma` of the secret and error vectors
+    pub(crate) fn generate_from_seeds(rho: B32, sigma: &B32) -> (Self, EncryptionKey<P>) {
+        // Sample pseudo-random vectors
+        let s: PolynomialVector<P::K> = PolynomialVector::sample_cbd::<P::Eta1>(sigma, 0);
+        let e: PolynomialVector<P::K> = PolynomialVector::sample_cbd::<P::Eta1>(sigma, P::K::U8);
 
         // NTT the vectors
         let s_hat = s.ntt();
         let e_hat = e.ntt();
 
-        // Compute the public value
-        let t_hat = &(&A_hat * &s_hat) + &e_hat;
+        // Compute the public value, with the pseudo-random matrix `A_hat`
+        #[cfg(not(feature = "low-memory"))]
+        let mut t_hat = {
+            let A_hat: NttMatrix<P::K> = NttMatrix::sample_uniform(&rho, false);
+            &A_hat * &s_hat
+        };
+
+        // Sample `A_hat` one row at a time, so that the whole matrix is never materialized
+        #[cfg(feature = "low-memory")]
+        let mut t_hat = NttVector::<P::K>::new(Array::from_fn(|i| {
+            &NttVector::<P::K>::sample_uniform(&rho, i, false) * &s_hat
+        }));
+
+        t_hat += &e_hat;
 
         // Assemble the keys
         let dk = DecryptionKey { s_hat };
@@ -47,6 +81,7 @@ where
 
     /// Decrypt ciphertext to obtain the encrypted value, according to the K-PKE.Decrypt procedure.
     // Algorithm 14. kK-PKE.Decrypt(dk_PKE, c)
+    #[must_use]
     pub fn decrypt(&self, ciphertext: &EncodedCiphertext<P>) -> B32 {
         let (c1, c2) = P::split_ct(ciphertext);
 
@@ -57,21 +92,41 @@ where
         v.decompress::<P::Dv>();
 
         let u_hat = u.ntt();
-        let sTu = (&self.s_hat * &u_hat).ntt_inverse();
-        let mut w = &v - &sTu;
+        let mut sTu_hat = &self.s_hat * &u_hat;
+        let mut sTu = Polynomial::default();
+        sTu_hat.ntt_inverse_into(&mut sTu);
+
+        let mut w = v;
+        w -= &sTu;
         Encode::<U1>::encode(w.compress::<U1>())
     }
 
     /// Represent this decryption key as a byte array `(s_hat)`
+    #[must_use]
     pub fn as_bytes(&self) -> EncodedDecryptionKey<P> {
         P::encode_u12(&self.s_hat)
     }
 
     /// Parse an decryption key from a byte array `(s_hat)`
+    #[must_use]
     pub fn from_bytes(enc: &EncodedDecryptionKey<P>) -> Self {
         let s_hat = P::decode_u12(enc);
         Self { s_hat }
     }
+
+    /// Assemble a decryption key from the secret vector `s_hat`
+    #[cfg(feature = "hazmat")]
+    #[must_use]
+    pub fn from_parts(s_hat: NttVector<P::K>) -> Self {
+        Self { s_hat }
+    }
+
+    /// The secret vector `s_hat`
+    #[cfg(feature = "hazmat")]
+    #[must_use]
+    pub fn s_hat(&self) -> &NttVector<P::K> {
+        &self.s_hat
+    }
 }
 
 /// An `EncryptionKey` provides the ability to encrypt a value so that it can only be
@@ -91,36 +146,136 @@ where
 {
     /// Encrypt the specified message for the holder of the corresponding decryption key, using the
     /// provided randomness, according the `K-PKE.Encrypt` procedure.
+    #[must_use]
     pub fn encrypt(&self, message: &B32, randomness: &B32) -> EncodedCiphertext<P> {
+        let (r_hat, c1) = self.encrypt_r_u(randomness);
+        let c2 = self.encrypt_v(&r_hat, message, randomness);
+        P::concat_ct(c1, c2)
+    }
+
+    // Sample `r` and compute the encoded `u`.  Note that `u` depends on the key only through the
+    // seed `rho`, so it is the same for all keys that share a seed.
+    pub(crate) fn encrypt_r_u(&self, randomness: &B32) -> (NttVector<P::K>, EncodedU<P>) {
         let r = PolynomialVector::<P::K>::sample_cbd::<P::Eta1>(randomness, 0);
+        let r_hat: NttVector<P::K> = r.ntt();
+
+        #[cfg(not(feature = "low-memory"))]
+        let c1 = self.encrypt_u(&r_hat, randomness);
+        #[cfg(feature = "low-memory")]
+        let c1 = self.encrypt_u_streaming(&r_hat, randomness);
+
+        (r_hat, c1)
+    }
+
+    /// Encrypt as in [`EncryptionKey::encrypt`], but reuse the transposed matrix `A_hat^T` held
+    /// in `cache` when it was expanded from this key's seed `rho`.  With the `low-memory` feature,
+    /// this is the same as [`EncryptionKey::encrypt`].
+    #[cfg(feature = "alloc")]
+    pub(crate) fn encrypt_cached(
+        &self,
+        cache: &mut MatrixCache<P::K>,
+        message: &B32,
+        randomness: &B32,
+    ) -> EncodedCiphertext<P> {
+        #[cfg(not(feature = "low-memory"))]
+        {
+            let r = PolynomialVector::<P::K>::sample_cbd::<P::Eta1>(randomness, 0);
+            let r_hat: NttVector<P::K> = r.ntt();
+
+            let A_hat_t = cache.get(&self.rho);
+            let c1 = Self::encrypt_u_with_matrix(A_hat_t, &r_hat, randomness);
+            let c2 = self.encrypt_v(&r_hat, message, randomness);
+            P::concat_ct(c1, c2)
+        }
+
+        // The cache holds nothing, so that the matrix is never materialized
+        #[cfg(feature = "low-memory")]
+        {
+            let MatrixCache(_) = cache;
+            self.encrypt(message, randomness)
+        }
+    }
+
+    // Compute and encode `u = NTT^-1(A_hat^T * r_hat) + e1`, with the whole matrix in memory
+    #[cfg(any(test, not(feature = "low-memory")))]
+    fn encrypt_u(&self, r_hat: &NttVector<P::K>, randomness: &B32) -> EncodedU<P> {
+        let A_hat_t = NttMatrix::<P::K>::sample_uniform(&self.rho, true);
+        Self::encrypt_u_with_matrix(&A_hat_t, r_hat, randomness)
+    }
+
+    #[cfg(any(test, not(feature = "low-memory")))]
+    fn encrypt_u_with_matrix(
+        A_hat_t: &NttMatrix<P::K>,
+        r_hat: &NttVector<P::K>,
+        randomness: &B32,
+    ) -> EncodedU<P> {
         let e1 = PolynomialVector::<P::K>::sample_cbd::<P::Eta2>(randomness, P::K::U8);
 
+        let mut ATr_hat = A_hat_t * r_hat;
+        let mut u = PolynomialVector::<P::K>::default();
+        ATr_hat.ntt_inverse_into(&mut u);
+        u += &e1;
+
+        Encode::<P::Du>::encode(u.compress::<P::Du>())
+    }
+
+    // Compute and encode `u` one entry at a time, sampling each entry of `A_hat^T` from the XOF
+    // only when it is needed.  At most one polynomial of the matrix is live at any point, instead
+    // of all K*K of them.
+    #[cfg(any(test, feature = "low-memory"))]
+    fn encrypt_u_streaming(&self, r_hat: &NttVector<P::K>, randomness: &B32) -> EncodedU<P> {
+        let u = Array::<EncodedPolynomial<P::Du>, P::K>::from_fn(|i| {
+            let mut ATr_hat_i = NttPolynomial::default();
+            for (j, r_hat_j) in r_hat.0.iter().enumerate() {
+                let mut xof = XOF(&self.rho, i.truncate(), j.truncate());
+                let A_hat_t_ij = NttPolynomial::sample_uniform(&mut xof);
+                ATr_hat_i += &(&A_hat_t_ij * r_hat_j);
+            }
+
+            let i: u8 = i.truncate();
+            let prf_output = PRF::<P::Eta2>(randomness, P::K::U8 + i);
+            let e1_i = Polynomial::sample_cbd::<P::Eta2>(&prf_output);
+
+            let mut u_i = Polynomial::default();
+            ATr_hat_i.ntt_inverse_into(&mut u_i);
+            u_i += &e1_i;
+            Encode::<P::Du>::encode(u_i.compress::<P::Du>())
+        });
+
+        P::Du::flatten(u)
+    }
+
+    // Compute and encode `v = NTT^-1(t_hat^T * r_hat) + e2 + mu`
+    pub(crate) fn encrypt_v(
+        &self,
+        r_hat: &NttVector<P::K>,
+        message: &B32,
+        randomness: &B32,
+    ) -> EncodedV<P> {
         let prf_output = PRF::<P::Eta2>(randomness, 2 * P::K::U8);
         let e2: Polynomial = Polynomial::sample_cbd::<P::Eta2>(&prf_output);
 
-        let A_hat_t = NttMatrix::<P::K>::sample_uniform(&self.rho, true);
-        let r_hat: NttVector<P::K> = r.ntt();
-        let ATr: PolynomialVector<P::K> = (&A_hat_t * &r_hat).ntt_inverse();
-        let mut u = ATr + e1;
-
         let mut mu: Polynomial = Encode::<U1>::decode(message);
         mu.decompress::<U1>();
 
-        let tTr: Polynomial = (&self.t_hat * &r_hat).ntt_inverse();
-        let mut v = &(&tTr + &e2) + &mu;
+        let mut tTr_hat = &self.t_hat * r_hat;
+        let mut v = Polynomial::default();
+        tTr_hat.ntt_inverse_into(&mut v);
+        v += &e2;
+        v += &mu;
 
-        let c1 = Encode::<P::Du>::encode(u.compress::<P::Du>());
-        let c2 = Encode::<P::Dv>::encode(v.compress::<P::Dv>());
-        P::concat_ct(c1, c2)
+        Encode::<P::Dv>::encode(v.compress::<P::Dv>())
     }
 
     /// Represent this encryption key as a byte array `(t_hat || rho)`
+    #[must_use]
     pub fn as_bytes(&self) -> EncodedEncryptionKey<P> {
         let t_hat = P::encode_u12(&self.t_hat);
         P::concat_ek(t_hat, self.rho.clone())
     }
 
     /// Parse an encryption key from a byte array `(t_hat || rho)`
+    #[must_use]
     pub fn from_bytes(enc: &EncodedEncryptionKey<P>) -> Self {
         let (t_hat, rho) = P::split_ek(enc);
         let t_hat = P::decode_u12(t_hat);
@@ -129,6 +284,49 @@ where
             rho: rho.clone(),
         }
     }
+
+    /// Assemble an encryption key from the public vector `t_hat` and the matrix seed `rho`
+    #[cfg(feature = "hazmat")]
+    #[must_use]
+    pub fn from_parts(t_hat: NttVector<P::K>, rho: B32) -> Self {
+        Self { t_hat, rho }
+    }
+
+    /// The public vector `t_hat`
+    #[cfg(feature = "hazmat")]
+    #[must_use]
+    pub fn t_hat(&self) -> &NttVector<P::K> {
+        &self.t_hat
+    }
+
+    /// The seed `rho` from which the matrix `A_hat` is sampled
+    #[cfg(any(feature = "hazmat", feature = "mkem"))]
+    #[must_use]
+    pub fn rho(&self) -> &B32 {
+        &self.rho
+    }
+}
+
+/// A `MatrixCache` holds the transposed matrix `A_hat^T` for the most recently used seed `rho`,
+/// so that the re-encryptions of batch decapsulation only sample it from the XOF once.  With the
+/// `low-memory` feature, it holds nothing.
+#[cfg(feature = "alloc")]
+#[derive(Default)]
+pub(crate) struct MatrixCache<K: ArraySize>(
+    #[cfg(not(feature = "low-memory"))] Option<(B32, NttMatrix<K>)>,
+    #[cfg(feature = "low-memory")] core::marker::PhantomData<K>,
+);
+
+#[cfg(all(feature = "alloc", not(feature = "low-memory")))]
+impl<K: ArraySize> MatrixCache<K> {
+    fn get(&mut self, rho: &B32) -> &NttMatrix<K> {
+        match &mut self.0 {
+            Some((cached, _)) if cached == rho => {}
+            entry => *entry = Some((rho.clone(), NttMatrix::sample_uniform(rho, true))),
+        }
+
+        &self.0.as_ref().unwrap().1
+    }
 }
 
 #[cfg(test)]
@@ -182,4 +380,57 @@ mod test {
         codec_test::<MlKem768Params>();
         codec_test::<MlKem1024Params>();
     }
+
+    fn streaming_test<P>()
+    where
+        P: PkeParams,
+    {
+        let mut rng = rand::thread_rng();
+        let d: B32 = rand(&mut rng);
+        let randomness: B32 = rand(&mut rng);
+        let (_, ek) = DecryptionKey::<P>::generate(&d);
+
+        let r = PolynomialVector::<P::K>::sample_cbd::<P::Eta1>(&randomness, 0);
+        let r_hat = r.ntt();
+        assert_eq!(
+            ek.encrypt_u(&r_hat, &randomness),
+            ek.encrypt_u_streaming(&r_hat, &randomness)
+        );
+    }
+
+    #[test]
+    fn streaming() {
+        streaming_test::<MlKem512Params>();
+        streaming_test::<MlKem768Params>();
+        streaming_test::<MlKem1024Params>();
+    }
+
+    // With the `low-memory` feature, a whole ML-KEM-1024 transaction fits in a stack that is too
+    // small for the same operations with the matrix in memory.  The sizes were measured on x86_64,
+    // where the peak usage without the feature is about 161 KiB (63 KiB optimized), and with it
+    // about 116 KiB (52 KiB optimized).
+    #[test]
+    #[cfg(feature = "low-memory")]
+    fn streaming_stack_size() {
+        extern crate std;
+        use crate::{KemCore, MlKem1024};
+        use ::kem::{Decapsulate, Encapsulate};
+
+        const STACK_SIZE: usize = if cfg!(debug_assertions) { 140 } else { 58 } * 1024;
+
+        let transaction = || {
+            let mut rng = rand::thread_rng();
+            let (dk, ek) = MlKem1024::generate(&mut rng);
+            let (ct, k_send) = ek.encapsulate(&mut rng).unwrap();
+            let k_recv = dk.decapsulate(&ct).unwrap();
+            assert_eq!(k_send, k_recv);
+        };
+
+        std::thread::Builder::new()
+            .stack_size(STACK_SIZE)
+            .spawn(transaction)
+            .unwrap()
+            .join()
+            .unwrap();
+    }
 }
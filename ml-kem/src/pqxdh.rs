@@ -0,0 +1,603 @@
+//! PQXDH extends X3DH with a post-quantum prekey: The responder (Bob) publishes a prekey bundle
+//! containing an identity key, a signed X25519 prekey, a signed ML-KEM-1024 prekey, and
+//! optionally a one-time X25519 prekey.  The initiator (Alice) verifies the prekey signatures,
+//! performs three or four X25519 operations and one encapsulation, and sends her identity key,
+//! ephemeral key, and the ML-KEM ciphertext in her initial message.
+//!
+//! ```
+//! # use ml_kem::pqxdh::{self, Hash, Parameters, PrekeyBundle, ResponderKeys};
+//! # use ml_kem::{KemCore, MlKem1024};
+//! # use x25519_dalek::{PublicKey, StaticSecret};
+//! let mut rng = rand::thread_rng();
+//! let params = Parameters {
+//!     info: b"MyProtocol_CURVE25519_SHA-256_ML-KEM-1024",
+//!     hash: Hash::Sha256,
+//! };
+//!
+//! // Bob's keys
+//! let identity_key = StaticSecret::random_from_rng(&mut rng);
+//! let signed_prekey = StaticSecret::random_from_rng(&mut rng);
+//! let (pq_prekey, pq_prekey_public) = MlKem1024::generate(&mut rng);
+//! let bundle = PrekeyBundle {
+//!     identity_key: PublicKey::from(&identity_key),
+//!     signed_prekey: PublicKey::from(&signed_prekey),
+//!     pq_prekey: pq_prekey_public,
+//!     one_time_prekey: None,
+//! };
+//!
+//! // Alice, having verified the prekey signatures
+//! let alice = StaticSecret::random_from_rng(&mut rng);
+//! let (message, sk_alice) = pqxdh::initiate(&params, &alice, &bundle, &mut rng);
+//!
+//! let keys = ResponderKeys {
+//!     identity_key: &identity_key,
+//!     signed_prekey: &signed_prekey,
+//!     pq_prekey: &pq_prekey,
+//!     one_time_prekey: None,
+//! };
+//! let sk_bob = pqxdh::respond(&params, &keys, &message);
+//! assert_eq!(sk_alice, sk_bob);
+//! ```
+//!
+//! # Key derivation
+//!
+//! With `IK` the identity keys, `SPK_B` the signed prekey, `OPK_B` the one-time prekey, and
+//! `EK_A` Alice's ephemeral key,
+//!
+//! ```text
+//! DH1 = DH(IK_A, SPK_B)
+//! DH2 = DH(EK_A, IK_B)
+//! DH3 = DH(EK_A, SPK_B)
+//! DH4 = DH(EK_A, OPK_B)
+//! SK  = HKDF(salt = 0^n, ikm = 0xFF^32 || DH1 || DH2 || DH3 [|| DH4] || SS, info)
+//! ```
+//!
+//! where `SS` is the ML-KEM shared key, `n` is the output length of the hash, and `DH4` is
+//! omitted if the bundle has no one-time prekey.
+//!
+//! # Encodings
+//!
+//! Public keys are encoded with a one-byte type prefix, as Signal does when signing prekeys and
+//! sending them over the wire: [`CURVE25519_KEY_TYPE`] for X25519 keys, and
+//! [`ML_KEM_1024_KEY_TYPE`] for ML-KEM-1024 keys.  Signal prefixes KEM ciphertexts with the same
+//! type byte as the key.  The type byte `0x08` denotes the Round 3 Kyber1024, which is not
+//! compatible with ML-KEM-1024 and is rejected.
+//!
+//! This module does not verify prekey signatures; the initiator must do so before calling
+//! [`initiate`].  The initial message should also be bound to both identities, by using
+//! [`associated_data`] as the associated data of the first AEAD message.
+//!
+//! [`CURVE25519_KEY_TYPE`]: crate::pqxdh::CURVE25519_KEY_TYPE
+//! [`ML_KEM_1024_KEY_TYPE`]: crate::pqxdh::ML_KEM_1024_KEY_TYPE
+//! [`initiate`]: crate::pqxdh::initiate
+//! [`associated_data`]: crate::pqxdh::associated_data
+
+use core::fmt;
+use hkdf::Hkdf;
+use rand_core::CryptoRngCore;
+use sha2::{Sha256, Sha512};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::kem;
+use crate::param::EncodedCiphertext;
+use crate::util::B32;
+use crate::{Encoded, EncodedSizeUser, MlKem1024Params};
+
+/// An ML-KEM-1024 encapsulation key, used as a post-quantum prekey
+pub type EncapsulationKey = kem::EncapsulationKey<MlKem1024Params>;
+
+/// An ML-KEM-1024 decapsulation key, the private half of a post-quantum prekey
+pub type DecapsulationKey = kem::DecapsulationKey<MlKem1024Params>;
+
+/// An ML-KEM-1024 ciphertext
+pub type Ciphertext = EncodedCiphertext<MlKem1024Params>;
+
+/// The session key `SK` established by the key agreement
+pub type SessionKey = B32;
+
+/// The type byte that prefixes encoded X25519 public keys
+pub const CURVE25519_KEY_TYPE: u8 = 0x05;
+
+/// The type byte that prefixes encoded ML-KEM-1024 encapsulation keys and ciphertexts
+pub const ML_KEM_1024_KEY_TYPE: u8 = 0x0a;
+
+/// The length of an encoded X25519 public key
+pub const ENCODED_EC_LEN: usize = 1 + 32;
+
+/// The length of an encoded ML-KEM-1024 encapsulation key
+pub const ENCODED_KEM_LEN: usize = 1 + 1568;
+
+/// The length of an encoded ML-KEM-1024 ciphertext
+pub const ENCODED_CIPHERTEXT_LEN: usize = 1 + 1568;
+
+// The 32 0xFF bytes that are prepended to the key material for X25519
+const F: [u8; 32] = [0xff; 32];
+
+/// The hash function used by the KDF
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Hash {
+    /// SHA-256
+    Sha256,
+
+    /// SHA-512
+    Sha512,
+}
+
+/// The protocol parameters, which both parties must agree on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parameters<'a> {
+    /// The application-specific info string for the KDF
+    pub info: &'a [u8],
+
+    /// The hash function for the KDF
+    pub hash: Hash,
+}
+
+/// The public keys in a responder's prekey bundle.  The signatures on the prekeys are not
+/// included, since the initiator must verify them before using the bundle.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrekeyBundle {
+    /// The responder's identity key `IK_B`
+    pub identity_key: PublicKey,
+
+    /// The signed X25519 prekey `SPK_B`
+    pub signed_prekey: PublicKey,
+
+    /// The signed ML-KEM-1024 prekey `PQPK_B`, either one-time or last-resort
+    pub pq_prekey: EncapsulationKey,
+
+    /// The one-time X25519 prekey `OPK_B`, if one was available
+    pub one_time_prekey: Option<PublicKey>,
+}
+
+/// The key agreement values in the initiator's initial message.  The message must also
+/// identify which of the responder's prekeys were used.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InitialMessage {
+    /// The initiator's identity key `IK_A`
+    pub identity_key: PublicKey,
+
+    /// The initiator's ephemeral key `EK_A`
+    pub ephemeral_key: PublicKey,
+
+    /// The ML-KEM-1024 ciphertext `CT`, encapsulated to the post-quantum prekey
+    pub ciphertext: Ciphertext,
+}
+
+/// The responder's private keys for the prekeys that an initial message used
+pub struct ResponderKeys<'a> {
+    /// The responder's identity key
+    pub identity_key: &'a StaticSecret,
+
+    /// The signed X25519 prekey
+    pub signed_prekey: &'a StaticSecret,
+
+    /// The ML-KEM-1024 prekey
+    pub pq_prekey: &'a DecapsulationKey,
+
+    /// The one-time X25519 prekey, if the initiator used one
+    pub one_time_prekey: Option<&'a StaticSecret>,
+}
+
+/// Perform the initiator's side of the key agreement with a responder's prekey bundle, whose
+/// signatures have already been verified.  Returns the initial message and the session key.
+pub fn initiate(
+    params: &Parameters<'_>,
+    identity_key: &StaticSecret,
+    bundle: &PrekeyBundle,
+    rng: &mut impl CryptoRngCore,
+) -> (InitialMessage, SessionKey) {
+    // The ephemeral key is used in several operations, so it cannot be an `EphemeralSecret`
+    let ephemeral_key = StaticSecret::random_from_rng(&mut *rng);
+    let (ciphertext, ss) = kem::encapsulate(&bundle.pq_prekey, rng);
+
+    let mut km = KeyMaterial::default();
+    km.push(
+        identity_key
+            .diffie_hellman(&bundle.signed_prekey)
+            .as_bytes(),
+    );
+    km.push(
+        ephemeral_key
+            .diffie_hellman(&bundle.identity_key)
+            .as_bytes(),
+    );
+    km.push(
+        ephemeral_key
+            .diffie_hellman(&bundle.signed_prekey)
+            .as_bytes(),
+    );
+    if let Some(one_time_prekey) = &bundle.one_time_prekey {
+        km.push(ephemeral_key.diffie_hellman(one_time_prekey).as_bytes());
+    }
+    km.push(&ss);
+
+    let message = InitialMessage {
+        identity_key: PublicKey::from(identity_key),
+        ephemeral_key: PublicKey::from(&ephemeral_key),
+        ciphertext,
+    };
+    (message, km.derive(params))
+}
+
+/// Perform the responder's side of the key agreement, returning the session key.  If the
+/// initiator used different prekeys than those provided, the session keys will not match, and
+/// the initiator's first AEAD message will fail to decrypt.
+#[must_use]
+pub fn respond(
+    params: &Parameters<'_>,
+    keys: &ResponderKeys<'_>,
+    message: &InitialMessage,
+) -> SessionKey {
+    let ss = kem::decapsulate(keys.pq_prekey, &message.ciphertext);
+
+    let mut km = KeyMaterial::default();
+    km.push(
+        keys.signed_prekey
+            .diffie_hellman(&message.identity_key)
+            .as_bytes(),
+    );
+    km.push(
+        keys.identity_key
+            .diffie_hellman(&message.ephemeral_key)
+            .as_bytes(),
+    );
+    km.push(
+        keys.signed_prekey
+            .diffie_hellman(&message.ephemeral_key)
+            .as_bytes(),
+    );
+    if let Some(one_time_prekey) = keys.one_time_prekey {
+        km.push(
+            one_time_prekey
+                .diffie_hellman(&message.ephemeral_key)
+                .as_bytes(),
+        );
+    }
+    km.push(&ss);
+
+    km.derive(params)
+}
+
+/// The associated data `AD = EncodeEC(IK_A) || EncodeEC(IK_B)` for the initial message
+#[must_use]
+pub fn associated_data(initiator: &PublicKey, responder: &PublicKey) -> [u8; 2 * ENCODED_EC_LEN] {
+    let mut ad = [0u8; 2 * ENCODED_EC_LEN];
+    ad[..ENCODED_EC_LEN].copy_from_slice(&encode_ec(initiator));
+    ad[ENCODED_EC_LEN..].copy_from_slice(&encode_ec(responder));
+    ad
+}
+
+/// Encode an X25519 public key as `EncodeEC`, with the [`CURVE25519_KEY_TYPE`] prefix
+#[must_use]
+pub fn encode_ec(key: &PublicKey) -> [u8; ENCODED_EC_LEN] {
+    let mut bytes = [0u8; ENCODED_EC_LEN];
+    bytes[0] = CURVE25519_KEY_TYPE;
+    bytes[1..].copy_from_slice(key.as_bytes());
+    bytes
+}
+
+/// Decode an X25519 public key encoded with [`encode_ec`].
+///
+/// # Errors
+///
+/// Returns an error if the encoding has the wrong length or type byte.
+pub fn decode_ec(bytes: &[u8]) -> Result<PublicKey, Error> {
+    let key = strip_type(bytes, CURVE25519_KEY_TYPE, ENCODED_EC_LEN)?;
+    let key = <[u8; 32]>::try_from(key).map_err(|_| Error::Length)?;
+    Ok(PublicKey::from(key))
+}
+
+/// Encode an ML-KEM-1024 encapsulation key as `EncodeKEM`, with the [`ML_KEM_1024_KEY_TYPE`]
+/// prefix
+#[must_use]
+pub fn encode_kem(key: &EncapsulationKey) -> [u8; ENCODED_KEM_LEN] {
+    let mut bytes = [0u8; ENCODED_KEM_LEN];
+    bytes[0] = ML_KEM_1024_KEY_TYPE;
+    bytes[1..].copy_from_slice(&key.as_bytes());
+    bytes
+}
+
+/// Decode an ML-KEM-1024 encapsulation key encoded with [`encode_kem`].
+///
+/// # Errors
+///
+/// Returns an error if the encoding has the wrong length or type byte, or if the key fails the
+/// modulus check of FIPS 203.
+pub fn decode_kem(bytes: &[u8]) -> Result<EncapsulationKey, Error> {
+    let key = strip_type(bytes, ML_KEM_1024_KEY_TYPE, ENCODED_KEM_LEN)?;
+    let key = Encoded::<EncapsulationKey>::try_from(key).map_err(|_| Error::Length)?;
+    EncapsulationKey::from_bytes_checked(&key).ok_or(Error::InvalidKey)
+}
+
+/// Encode an ML-KEM-1024 ciphertext, with the [`ML_KEM_1024_KEY_TYPE`] prefix
+#[must_use]
+pub fn encode_ciphertext(ct: &Ciphertext) -> [u8; ENCODED_CIPHERTEXT_LEN] {
+    let mut bytes = [0u8; ENCODED_CIPHERTEXT_LEN];
+    bytes[0] = ML_KEM_1024_KEY_TYPE;
+    bytes[1..].copy_from_slice(ct);
+    bytes
+}
+
+/// Decode an ML-KEM-1024 ciphertext encoded with [`encode_ciphertext`].
+///
+/// # Errors
+///
+/// Returns an error if the encoding has the wrong length or type byte.
+pub fn decode_ciphertext(bytes: &[u8]) -> Result<Ciphertext, Error> {
+    let ct = strip_type(bytes, ML_KEM_1024_KEY_TYPE, ENCODED_CIPHERTEXT_LEN)?;
+    Ciphertext::try_from(ct).map_err(|_| Error::Length)
+}
+
+fn strip_type(bytes: &[u8], key_type: u8, len: usize) -> Result<&[u8], Error> {
+    if bytes.len() != len {
+        return Err(Error::Length);
+    }
+    if bytes[0] != key_type {
+        return Err(Error::KeyType);
+    }
+    Ok(&bytes[1..])
+}
+
+/// An error in decoding a key or ciphertext
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The encoding has the wrong length
+    Length,
+
+    /// The encoding has an unexpected type byte
+    KeyType,
+
+    /// The ML-KEM encapsulation key is not a valid encoding
+    InvalidKey,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Length => "encoding has the wrong length",
+            Self::KeyType => "unexpected key type",
+            Self::InvalidKey => "invalid ML-KEM encapsulation key",
+        })
+    }
+}
+
+// The input key material `F || DH1 || DH2 || DH3 [|| DH4] || SS`
+const MAX_KM_LEN: usize = F.len() + 4 * 32 + 32;
+
+struct KeyMaterial {
+    bytes: [u8; MAX_KM_LEN],
+    len: usize,
+}
+
+impl Default for KeyMaterial {
+    fn default() -> Self {
+        let mut bytes = [0u8; MAX_KM_LEN];
+        bytes[..F.len()].copy_from_slice(&F);
+        Self {
+            bytes,
+            len: F.len(),
+        }
+    }
+}
+
+impl KeyMaterial {
+    fn push(&mut self, value: &[u8]) {
+        self.bytes[self.len..][..value.len()].copy_from_slice(value);
+        self.len += value.len();
+    }
+
+    fn derive(&self, params: &Parameters<'_>) -> SessionKey {
+        let ikm = &self.bytes[..self.len];
+        let mut sk = SessionKey::default();
+        let result = match params.hash {
+            Hash::Sha256 => Hkdf::<Sha256>::new(Some(&[0; 32]), ikm).expand(params.info, &mut sk),
+            Hash::Sha512 => Hkdf::<Sha512>::new(Some(&[0; 64]), ikm).expand(params.info, &mut sk),
+        };
+        result.expect("32 bytes is a valid HKDF output length");
+        sk
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kem::Kem;
+    use crate::KemCore;
+    use hex_literal::hex;
+
+    const PARAMS: Parameters<'static> = Parameters {
+        info: b"Test_CURVE25519_SHA-256_ML-KEM-1024",
+        hash: Hash::Sha256,
+    };
+
+    struct Bob {
+        identity_key: StaticSecret,
+        signed_prekey: StaticSecret,
+        pq_prekey: DecapsulationKey,
+        one_time_prekey: StaticSecret,
+    }
+
+    impl Bob {
+        fn new(rng: &mut impl CryptoRngCore) -> Self {
+            Self {
+                identity_key: StaticSecret::random_from_rng(&mut *rng),
+                signed_prekey: StaticSecret::random_from_rng(&mut *rng),
+                pq_prekey: Kem::<MlKem1024Params>::generate(rng).0,
+                one_time_prekey: StaticSecret::random_from_rng(&mut *rng),
+            }
+        }
+
+        fn bundle(&self, one_time: bool) -> PrekeyBundle {
+            PrekeyBundle {
+                identity_key: PublicKey::from(&self.identity_key),
+                signed_prekey: PublicKey::from(&self.signed_prekey),
+                pq_prekey: self.pq_prekey.encapsulation_key().clone(),
+                one_time_prekey: one_time.then(|| PublicKey::from(&self.one_time_prekey)),
+            }
+        }
+
+        fn keys(&self, one_time: bool) -> ResponderKeys<'_> {
+            ResponderKeys {
+                identity_key: &self.identity_key,
+                signed_prekey: &self.signed_prekey,
+                pq_prekey: &self.pq_prekey,
+                one_time_prekey: one_time.then_some(&self.one_time_prekey),
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = rand::thread_rng();
+        let bob = Bob::new(&mut rng);
+        let alice = StaticSecret::random_from_rng(&mut rng);
+
+        for hash in [Hash::Sha256, Hash::Sha512] {
+            let params = Parameters { hash, ..PARAMS };
+            for one_time in [false, true] {
+                let (message, sk_alice) =
+                    initiate(&params, &alice, &bob.bundle(one_time), &mut rng);
+                assert_eq!(message.identity_key, PublicKey::from(&alice));
+                assert_eq!(respond(&params, &bob.keys(one_time), &message), sk_alice);
+
+                // Omitting or adding the one-time prekey, or using other parameters, yields a
+                // different key
+                assert_ne!(respond(&params, &bob.keys(!one_time), &message), sk_alice);
+                let other = Parameters {
+                    info: b"Other",
+                    ..params
+                };
+                assert_ne!(respond(&other, &bob.keys(one_time), &message), sk_alice);
+            }
+        }
+    }
+
+    // A known answer for the responder, with fixed keys.  The ciphertext was encapsulated to the
+    // post-quantum prekey with the ML-KEM of OpenSSL, through Python's `cryptography`, and the
+    // session keys were derived with HKDF built from Python's `hmac` and `hashlib`.
+    #[test]
+    fn key_derivation() {
+        let key = |byte| StaticSecret::from([byte; 32]);
+        let identity_key = key(0x11);
+        let signed_prekey = key(0x22);
+        let one_time_prekey = key(0x33);
+        let pq_prekey =
+            DecapsulationKey::generate_deterministic(&[0x66; 32].into(), &[0x77; 32].into());
+
+        let message = InitialMessage {
+            identity_key: PublicKey::from(&key(0x44)),
+            ephemeral_key: PublicKey::from(&key(0x55)),
+            ciphertext: Ciphertext::from(hex!(
+                "329a5a76f32e33ac79be54f1bc442618dd4202545f344d2dcc489b9ee05c4841"
+                "60075f661f692bf4e0168fbc8a83a467a1945cdd2f87beeacfc30cedcc121de4"
+                "c20d0a189b006e525f51c4bb674ba89797c6c557e04f58844dadcbead602d6d7"
+                "1f9c4b5d558a86ee1d0f2ec1a9c3d3e5dfa7e44777b5f3842a0d23b28707b7d7"
+                "6f8f1bfdeab72bff332b3fcea39d30ae25400c27161379aadc052066b82013f2"
+                "94fe0e1b420550753b8c485a4ffe49cb02b37f427259bf3c657f87c5e7304a88"
+                "ba2dc71f1abc3e8fe8ca44d86fc6c3684c3c8f84e91fbe5fe16cb638b0ebf50a"
+                "e77277c9d4e258f46a202c1b21a6ef7218ee16e69e0f66988cd97042d8681c6b"
+                "17afaeb1ae89850d482a0ebd172eabae42ce15d65eddb244d2b097a7451503d3"
+                "4a042ee1bd8ecaada2270e7fe873d2ceabd08b5ee0bc3aa8e8094d7e2b333d03"
+                "aca6a3474b65547e193ccc1f58982dac5fcc2fae6f50c1ee5d227f7dc4408ab5"
+                "763c8530d029e2da2e74e4326ca820ce184ee5ed7a9ffce30d3a72aab96c9a7d"
+                "e4f4812a694e1fb387b2e4d7cef62206f9ec2ada92049bce65afa2b1a5a4911a"
+                "1357b30e7f08b297fcda8eb7c2d65474caf95228abc4ba740f52b25ee6ca1487"
+                "2686d6b6cbbc223078d0ff4b8d39382eb814683652af90af378d14170a4259c3"
+                "d951c7755c8fef16f144dc49c5b522d500d37cdf24eebd1b6af88d1a69d301ce"
+                "0545192ce13b1f5cf3aaa47b49ee9539c1500166cc1bafa61a5bd41b06741494"
+                "442af93912746b8be71ccd5e2dc2dea87dd0bb93d60895ddca1c11c799ceba1b"
+                "5b8536641bdb5edd6866bedde94fafa620b82136899739fe67c8395353a96607"
+                "8c5b77776eeaeb6ca6826d9e968b2183e6ff69f55a15e13a138263a7c8ae8c17"
+                "c63363c86fd561db3be971254fd7325faa1b381cace12cefc75d418877d62174"
+                "07b74d2fee46e5a94045c9579164f4f6a1a8434258819a3c1eef55291689f2d5"
+                "d9009bc8e1561e3d75f11035e89be8d924e62ec59edf5bbc941bed02a03c4d08"
+                "f1a168872252ffb17b69fd7a37b92a7beccc31f1542d18601107625921130d26"
+                "7b4f12aa8f6dd1d6a51ef4cb8f0987991d433ad311011bae5044598abb766750"
+                "f5cd233edeaea51d3210bf439ffff221d271229956a118fccf4d03d3a58ba823"
+                "21e92adf8c91019390fa17ce921bb92aea7c38912472f22036ddfb160f8ada89"
+                "662748db5cb8f1aaaa45a1d52f7a8027e4969ae99794d999c8a91fd9cbbd8981"
+                "c6e68ac25b388ac596a8904c52a42475052bc353a2d1c4836b97202c8473793f"
+                "6716c8067585e2e6c7a789bf36450f2b893dd9709df7565443e11725b3d28584"
+                "923ae440b1e3adab91e7c6c6c08bb26ecd563bdb7d1077df66595cc6024f1e48"
+                "b1b9c1f63d0f2188bbaeb40b62ab866ac49d892a69198d36864c3a43e6c1cd82"
+                "8e6d145340f7e1a521e63fe24430e2f39b529dab0a2a61afbcf8f007efb219c3"
+                "ec250ace3996e28b7cd7a27897c5859634252ba2706bf1942c381c2ca872acdc"
+                "bc159f443918537cff1d38bde28e86e981ab04f2478bab06ac6f17f3b6390fbb"
+                "394504e442bf7e8c2283374e88515548c270e35a8cf91e35574aad462c8814c7"
+                "632b9e3979cc1b48de92f47b8f0fe19e4ba3ba15ecd11fc45d4d046baf1e8249"
+                "43c65621c9af64b66b647ee593622fc32d28cfba26dc3a5118b26dc2cf14a4f2"
+                "a0a3b291474844482842a8a67ec756f905fac122bfee76b8b54557c438a387df"
+                "f39ccee9e788ab128f50abd62d14896388dad7727c33e275ffa9e1650169c2d5"
+                "ef9919ad71e537a704e5b06dfad119a96c21a0ab3f585930f238a18b6fe73e7a"
+                "aa77019bd8dafe4f054819f08590e20fc86ce891c6a3e0269e43373be4740452"
+                "429d522a92524651edc6c52c6dca3adb84f50b702cdfd0b62bbd64f3d5b4eb23"
+                "75adf0847804c86e9df0541f216dcc896422e2283917b72a042de2cddc46592f"
+                "24b5904b0ad3370e39dc04187c32a4a0423523225630666b915c269f8fc7b0cb"
+                "25851341fbb1d30a0fb8fbb23ec36f6730ce7578aa86899fd2e685968a570a09"
+                "8c3a597ce542434d774b21d63aa90fd94360050f5565c1a83727c28d67e6a456"
+                "83041491bd6bc98ea6496b92638d05384260fd891cbc5774240f004c36e2320f"
+                "3cd9441d60027385523377cd31947e5441c6bf55a2842114487cc41e5b7df776"
+            )),
+        };
+
+        let mut keys = ResponderKeys {
+            identity_key: &identity_key,
+            signed_prekey: &signed_prekey,
+            pq_prekey: &pq_prekey,
+            one_time_prekey: None,
+        };
+        assert_eq!(
+            respond(&PARAMS, &keys, &message).as_slice(),
+            hex!("0fa0aa60ed80a02878bfb2ab233a6b79e693df411a94f64ff818a4b07de6ed4f")
+        );
+
+        keys.one_time_prekey = Some(&one_time_prekey);
+        assert_eq!(
+            respond(&PARAMS, &keys, &message).as_slice(),
+            hex!("a07b8ad72d95e35c31aeb26c40b0f87b6187acf8921fd64d827e2985e63300d9")
+        );
+    }
+
+    #[test]
+    fn encodings() {
+        let mut rng = rand::thread_rng();
+        let bob = Bob::new(&mut rng);
+        let bundle = bob.bundle(true);
+
+        let ec = encode_ec(&bundle.identity_key);
+        assert_eq!(ec[0], 0x05);
+        assert_eq!(decode_ec(&ec), Ok(bundle.identity_key));
+
+        let kem = encode_kem(&bundle.pq_prekey);
+        assert_eq!(kem[0], 0x0a);
+        assert_eq!(decode_kem(&kem), Ok(bundle.pq_prekey.clone()));
+
+        let (message, _) = initiate(&PARAMS, &bob.identity_key, &bundle, &mut rng);
+        let ct = encode_ciphertext(&message.ciphertext);
+        assert_eq!(decode_ciphertext(&ct), Ok(message.ciphertext));
+
+        let ad = associated_data(&message.identity_key, &bundle.identity_key);
+        assert_eq!(ad[..ENCODED_EC_LEN], encode_ec(&message.identity_key));
+        assert_eq!(ad[ENCODED_EC_LEN..], ec);
+
+        // Wrong lengths and type bytes
+        assert_eq!(decode_ec(&ec[1..]), Err(Error::Length));
+        assert_eq!(decode_ec(&kem), Err(Error::Length));
+        assert_eq!(decode_kem(&ct[..100]), Err(Error::Length));
+        assert_eq!(decode_ciphertext(&ec), Err(Error::Length));
+
+        let mut bad = kem;
+        bad[0] = 0x08; // Kyber1024
+        assert_eq!(decode_kem(&bad), Err(Error::KeyType));
+        let mut bad = ec;
+        bad[0] = 0x0a;
+        assert_eq!(decode_ec(&bad), Err(Error::KeyType));
+
+        // An encapsulation key coefficient of at least q
+        let mut bad = kem;
+        bad[1] = 0xff;
+        bad[2] |= 0x0f;
+        assert_eq!(decode_kem(&bad), Err(Error::InvalidKey));
+    }
+}
@@ -0,0 +1,416 @@
+//! Messages are encrypted in the KEM-DEM style: ML-KEM establishes a fresh shared key, from which
+//! a key and nonce for an AEAD are derived.
+//!
+//! ```
+//! # use ml_kem::{seal::{open, seal, AeadId}, KemCore, MlKem768};
+//! let mut rng = rand::thread_rng();
+//! let (dk, ek) = MlKem768::generate(&mut rng);
+//!
+//! let envelope = seal(&ek, AeadId::Aes256Gcm, b"context", b"hello", &mut rng).unwrap();
+//! let plaintext = open(&dk, b"context", &envelope).unwrap();
+//! assert_eq!(plaintext, b"hello");
+//! ```
+//!
+//! # Envelope format
+//!
+//! A sealed envelope is self-describing, so that a recipient can tell which key and algorithms
+//! it needs before attempting to open it.  The fields are:
+//!
+//! | Offset | Length   | Field                                                  |
+//! |--------|----------|--------------------------------------------------------|
+//! | 0      | 1        | Format version, currently `0x01`                       |
+//! | 1      | 1        | ML-KEM parameter set, as a [`KemId`]                   |
+//! | 2      | 1        | AEAD algorithm, as an [`AeadId`]                       |
+//! | 3      | 32       | [`KeyId`] of the recipient's encapsulation key         |
+//! | 35     | variable | ML-KEM ciphertext, of the size fixed by the parameters |
+//! | ...    | variable | AEAD ciphertext, including the 16-byte tag             |
+//!
+//! The AEAD key and nonce are derived as the first 32 and next 12 bytes of
+//!
+//! ```text
+//! SHAKE256("ML-KEM seal v1" || K || header || ciphertext)
+//! ```
+//!
+//! where `K` is the ML-KEM shared key, `header` is the first 35 bytes of the envelope, and
+//! `ciphertext` is the ML-KEM ciphertext.  Since every envelope has a fresh shared key, a
+//! derived nonce is never reused.  The caller's associated data is authenticated by the AEAD,
+//! but is not included in the envelope.
+//!
+//! [`KemId`]: crate::seal::KemId
+//! [`AeadId`]: crate::seal::AeadId
+//! [`KeyId`]: crate::kem::KeyId
+
+use aes_gcm::Aes256Gcm;
+use alloc::vec::Vec;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305,
+};
+use core::fmt;
+use hybrid_array::typenum::Unsigned;
+use rand_core::CryptoRngCore;
+use sha3::{
+    digest::{ExtendableOutput, Update, XofReader},
+    Shake256,
+};
+
+use crate::kem::{decapsulate, encapsulate, DecapsulationKey, EncapsulationKey, KeyId, SharedKey};
+use crate::param::{EncodedCiphertext, KemParams, StandardParams};
+
+/// The version of the envelope format produced by [`seal`]
+pub const VERSION: u8 = 0x01;
+
+const LABEL: &[u8] = b"ML-KEM seal v1";
+const HEADER_LEN: usize = 35;
+const TAG_LEN: usize = 16;
+
+/// An identifier for an ML-KEM parameter set in an envelope
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum KemId {
+    /// ML-KEM-512
+    MlKem512 = 0x01,
+
+    /// ML-KEM-768
+    MlKem768 = 0x02,
+
+    /// ML-KEM-1024
+    MlKem1024 = 0x03,
+}
+
+impl KemId {
+    // The identifier for the parameter set `P`, if it is one of the standard ones
+    fn of<P: KemParams>() -> Option<Self> {
+        Some(match StandardParams::of::<P>()? {
+            StandardParams::MlKem512 => Self::MlKem512,
+            StandardParams::MlKem768 => Self::MlKem768,
+            StandardParams::MlKem1024 => Self::MlKem1024,
+        })
+    }
+}
+
+impl TryFrom<u8> for KemId {
+    type Error = Error;
+
+    fn try_from(id: u8) -> Result<Self, Error> {
+        match id {
+            0x01 => Ok(Self::MlKem512),
+            0x02 => Ok(Self::MlKem768),
+            0x03 => Ok(Self::MlKem1024),
+            _ => Err(Error::UnsupportedKem),
+        }
+    }
+}
+
+/// An identifier for an AEAD algorithm in an envelope
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum AeadId {
+    /// AES-256-GCM
+    Aes256Gcm = 0x01,
+
+    /// ChaCha20-Poly1305
+    ChaCha20Poly1305 = 0x02,
+}
+
+impl TryFrom<u8> for AeadId {
+    type Error = Error;
+
+    fn try_from(id: u8) -> Result<Self, Error> {
+        match id {
+            0x01 => Ok(Self::Aes256Gcm),
+            0x02 => Ok(Self::ChaCha20Poly1305),
+            _ => Err(Error::UnsupportedAead),
+        }
+    }
+}
+
+/// The header of an envelope, which describes how to open it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Header {
+    /// The ML-KEM parameter set of the recipient's key
+    pub kem: KemId,
+
+    /// The AEAD that protects the message
+    pub aead: AeadId,
+
+    /// The identifier of the recipient's encapsulation key
+    pub key_id: KeyId,
+}
+
+impl Header {
+    /// Parse the header at the start of an envelope, e.g., to select the decapsulation key that
+    /// will open it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the envelope is too short to contain a header, or if it uses an
+    /// unsupported version or algorithm.
+    pub fn parse(envelope: &[u8]) -> Result<Self, Error> {
+        let header = envelope.get(..HEADER_LEN).ok_or(Error::Malformed)?;
+        if header[0] != VERSION {
+            return Err(Error::UnsupportedVersion);
+        }
+
+        let mut key_id = [0u8; 32];
+        key_id.copy_from_slice(&header[3..]);
+
+        Ok(Self {
+            kem: KemId::try_from(header[1])?,
+            aead: AeadId::try_from(header[2])?,
+            key_id: key_id.into(),
+        })
+    }
+
+    fn to_bytes(self) -> [u8; HEADER_LEN] {
+        let mut header = [0u8; HEADER_LEN];
+        header[0] = VERSION;
+        header[1] = self.kem as u8;
+        header[2] = self.aead as u8;
+        header[3..].copy_from_slice(self.key_id.as_bytes());
+        header
+    }
+}
+
+/// An error in sealing or opening an envelope
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The envelope is too short to be valid
+    Malformed,
+
+    /// The envelope uses a format version other than [`VERSION`]
+    UnsupportedVersion,
+
+    /// The envelope names an unknown ML-KEM parameter set, or the key is not of a standard
+    /// parameter set
+    UnsupportedKem,
+
+    /// The envelope names an unknown AEAD
+    UnsupportedAead,
+
+    /// The envelope was sealed to a key of a different parameter set
+    WrongParameterSet,
+
+    /// The envelope was sealed to a different key
+    WrongKey,
+
+    /// The AEAD failed to encrypt or decrypt the message.  When opening, this means that the
+    /// envelope or the associated data has been modified.
+    Aead,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Malformed => "envelope is too short",
+            Self::UnsupportedVersion => "unsupported envelope version",
+            Self::UnsupportedKem => "unsupported ML-KEM parameter set",
+            Self::UnsupportedAead => "unsupported AEAD",
+            Self::WrongParameterSet => "envelope was sealed to a different parameter set",
+            Self::WrongKey => "envelope was sealed to a different key",
+            Self::Aead => "AEAD encryption or decryption failed",
+        })
+    }
+}
+
+/// Encrypt `plaintext` to the holder of the decapsulation key corresponding to `ek`, using the
+/// AEAD `aead`.  The associated data `aad` is authenticated but not included in the envelope; the
+/// same value must be provided to [`open`].
+///
+/// # Errors
+///
+/// Returns [`Error::UnsupportedKem`] if `ek` is not of a standard ML-KEM parameter set, and
+/// [`Error::Aead`] if the AEAD cannot encrypt a message of this length.
+#[allow(clippy::similar_names)] // allow aead, aad
+pub fn seal<P>(
+    ek: &EncapsulationKey<P>,
+    aead: AeadId,
+    aad: &[u8],
+    plaintext: &[u8],
+    rng: &mut impl CryptoRngCore,
+) -> Result<Vec<u8>, Error>
+where
+    P: KemParams,
+{
+    let header = Header {
+        kem: KemId::of::<P>().ok_or(Error::UnsupportedKem)?,
+        aead,
+        key_id: ek.key_id(),
+    }
+    .to_bytes();
+
+    let (ct, K) = encapsulate(ek, rng);
+    let payload = Payload {
+        msg: plaintext,
+        aad,
+    };
+    let sealed = match aead {
+        AeadId::Aes256Gcm => {
+            derive_and::<Aes256Gcm, _>(&K, &header, &ct, |c, n| c.encrypt(n.into(), payload))
+        }
+        AeadId::ChaCha20Poly1305 => {
+            derive_and::<ChaCha20Poly1305, _>(&K, &header, &ct, |c, n| c.encrypt(n.into(), payload))
+        }
+    }
+    .map_err(|_| Error::Aead)?;
+
+    let mut envelope = Vec::with_capacity(HEADER_LEN + ct.len() + sealed.len());
+    envelope.extend_from_slice(&header);
+    envelope.extend_from_slice(&ct);
+    envelope.extend_from_slice(&sealed);
+    Ok(envelope)
+}
+
+/// Decrypt an envelope produced by [`seal`], with the associated data `aad` that was provided when
+/// it was sealed.
+///
+/// # Errors
+///
+/// Returns an error if the envelope is malformed, was sealed to a different key, or does not
+/// authenticate.
+pub fn open<P>(dk: &DecapsulationKey<P>, aad: &[u8], envelope: &[u8]) -> Result<Vec<u8>, Error>
+where
+    P: KemParams,
+{
+    let header = Header::parse(envelope)?;
+    if KemId::of::<P>() != Some(header.kem) {
+        return Err(Error::WrongParameterSet);
+    }
+    if header.key_id != dk.key_id() {
+        return Err(Error::WrongKey);
+    }
+
+    let ct_len = P::CiphertextSize::USIZE;
+    let (header_bytes, rest) = envelope.split_at(HEADER_LEN);
+    if rest.len() < ct_len + TAG_LEN {
+        return Err(Error::Malformed);
+    }
+    let (ct, sealed) = rest.split_at(ct_len);
+    let ct = EncodedCiphertext::<P>::try_from(ct).map_err(|_| Error::Malformed)?;
+
+    let K = decapsulate(dk, &ct);
+    let payload = Payload { msg: sealed, aad };
+    match header.aead {
+        AeadId::Aes256Gcm => {
+            derive_and::<Aes256Gcm, _>(&K, header_bytes, &ct, |c, n| c.decrypt(n.into(), payload))
+        }
+        AeadId::ChaCha20Poly1305 => {
+            derive_and::<ChaCha20Poly1305, _>(&K, header_bytes, &ct, |c, n| {
+                c.decrypt(n.into(), payload)
+            })
+        }
+    }
+    .map_err(|_| Error::Aead)
+}
+
+// Derive the AEAD key and nonce from the shared key and the public parts of the envelope, and
+// apply `f` to the resulting cipher and nonce
+fn derive_and<C, T>(
+    K: &SharedKey,
+    header: &[u8],
+    ct: &[u8],
+    f: impl FnOnce(&C, &[u8; 12]) -> T,
+) -> T
+where
+    C: KeyInit,
+{
+    let mut kdf = Shake256::default();
+    kdf.update(LABEL);
+    kdf.update(K);
+    kdf.update(header);
+    kdf.update(ct);
+
+    let mut key = [0u8; 32];
+    let mut nonce = [0u8; 12];
+    let mut reader = kdf.finalize_xof();
+    reader.read(&mut key);
+    reader.read(&mut nonce);
+
+    let cipher = C::new_from_slice(&key).expect("AEAD keys are 32 bytes");
+    f(&cipher, &nonce)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kem::Kem;
+    use crate::{KemCore, MlKem1024Params, MlKem512Params, MlKem768Params, ParameterSet};
+    use hybrid_array::typenum::{U10, U2, U3, U4};
+
+    fn round_trip_test<P>()
+    where
+        P: KemParams,
+    {
+        let mut rng = rand::thread_rng();
+        let (dk, ek) = Kem::<P>::generate(&mut rng);
+        let (dk2, _) = Kem::<P>::generate(&mut rng);
+
+        for aead in [AeadId::Aes256Gcm, AeadId::ChaCha20Poly1305] {
+            let envelope = seal(&ek, aead, b"aad", b"attack at dawn", &mut rng).unwrap();
+            assert_eq!(open(&dk, b"aad", &envelope).unwrap(), b"attack at dawn");
+
+            let header = Header::parse(&envelope).unwrap();
+            assert_eq!(header.key_id, dk.key_id());
+            assert_eq!(header.aead, aead);
+
+            // Wrong key or associated data
+            assert_eq!(open(&dk2, b"aad", &envelope), Err(Error::WrongKey));
+            assert_eq!(open(&dk, b"bad", &envelope), Err(Error::Aead));
+
+            // Any modification of the ML-KEM or AEAD ciphertexts is detected
+            for i in [HEADER_LEN, HEADER_LEN + 100, envelope.len() - 1] {
+                let mut bad = envelope.clone();
+                bad[i] ^= 0x01;
+                assert_eq!(open(&dk, b"aad", &bad), Err(Error::Aead));
+            }
+
+            // So is switching the AEAD
+            let mut bad = envelope.clone();
+            bad[2] ^= 0x03;
+            assert_eq!(open(&dk, b"aad", &bad), Err(Error::Aead));
+
+            // Truncation
+            assert_eq!(
+                open(&dk, b"aad", &envelope[..envelope.len() - 15 - 14]),
+                Err(Error::Malformed)
+            );
+        }
+
+        let envelope = seal(&ek, AeadId::Aes256Gcm, b"", b"", &mut rng).unwrap();
+        assert!(open(&dk, b"", &envelope).unwrap().is_empty());
+    }
+
+    #[test]
+    fn round_trip() {
+        round_trip_test::<MlKem512Params>();
+        round_trip_test::<MlKem768Params>();
+        round_trip_test::<MlKem1024Params>();
+    }
+
+    // A parameter set that has no identifier in the envelope format, although it shares `k` with
+    // ML-KEM-768
+    #[derive(Default, Clone, Debug, PartialEq)]
+    struct NonstandardParams;
+
+    impl ParameterSet for NonstandardParams {
+        type K = U3;
+        type Eta1 = U3;
+        type Eta2 = U2;
+        type Du = U10;
+        type Dv = U4;
+    }
+
+    #[test]
+    fn nonstandard_parameter_set() {
+        let mut rng = rand::thread_rng();
+        let (dk, ek) = Kem::<NonstandardParams>::generate(&mut rng);
+        assert_eq!(
+            seal(&ek, AeadId::Aes256Gcm, b"", b"", &mut rng),
+            Err(Error::UnsupportedKem)
+        );
+
+        let (_, ek) = Kem::<MlKem1024Params>::generate(&mut rng);
+        let envelope = seal(&ek, AeadId::Aes256Gcm, b"", b"", &mut rng).unwrap();
+        assert_eq!(open(&dk, b"", &envelope), Err(Error::WrongParameterSet));
+    }
+}
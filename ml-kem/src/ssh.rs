@@ -0,0 +1,377 @@
+//! This method combines ML-KEM-768 with X25519 as specified in draft-ietf-sshm-mlkem-hybrid-kex.
+//!
+//! ```
+//! # use ml_kem::ssh::{self, Client, ClientInit, ServerReply};
+//! let mut rng = rand::thread_rng();
+//!
+//! // The client sends `C_INIT` in `SSH_MSG_KEX_HYBRID_INIT`
+//! let (client, init) = Client::new(&mut rng);
+//! let c_init = init.to_bytes();
+//!
+//! // The server replies with `S_REPLY` in `SSH_MSG_KEX_HYBRID_REPLY`
+//! let init = ClientInit::from_bytes(&c_init).unwrap();
+//! let (reply, k_server) = ssh::reply(&init, &mut rng).unwrap();
+//! let s_reply = reply.to_bytes();
+//!
+//! let reply = ServerReply::from_bytes(&s_reply).unwrap();
+//! let k_client = client.finish(&reply).unwrap();
+//! assert_eq!(k_client, k_server);
+//! ```
+//!
+//! # Messages
+//!
+//! `C_INIT` is the client's ML-KEM-768 encapsulation key followed by its ephemeral X25519 public
+//! key, and `S_REPLY` is the ML-KEM-768 ciphertext followed by the server's ephemeral X25519
+//! public key:
+//!
+//! ```text
+//! C_INIT  = C_PK2 || C_PK1    (1184 + 32 bytes)
+//! S_REPLY = S_CT2 || S_PK1    (1088 + 32 bytes)
+//! ```
+//!
+//! Both are sent as SSH `string`s, and are hashed as such into the exchange hash in place of
+//! `Q_C` and `Q_S`.
+//!
+//! # Shared secret
+//!
+//! The shared secret is
+//!
+//! ```text
+//! K = SHA-256(K_PQ || K_CL)
+//! ```
+//!
+//! where `K_PQ` is the ML-KEM shared key and `K_CL` is the X25519 shared secret.  Unlike the
+//! shared secrets of the Diffie-Hellman methods, which are encoded as `mpint`s, `K` is encoded
+//! as a `string` wherever it is used, as given by [`SharedSecret::to_ssh_string`].
+//!
+//! [`SharedSecret::to_ssh_string`]: crate::ssh::SharedSecret::to_ssh_string
+
+use core::fmt;
+use rand_core::CryptoRngCore;
+use sha2::{Digest, Sha256};
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use crate::kem::{decapsulate, encapsulate, DecapsulationKey, EncapsulationKey, Kem};
+use crate::param::EncodedCiphertext;
+use crate::{Encoded, EncodedSizeUser, KemCore, MlKem768Params};
+
+/// The name of the key exchange method
+pub const KEX_NAME: &str = "mlkem768x25519-sha256";
+
+/// The length of `C_INIT`, the client's message
+pub const C_INIT_LEN: usize = EK_LEN + X25519_LEN;
+
+/// The length of `S_REPLY`, the server's message
+pub const S_REPLY_LEN: usize = CT_LEN + X25519_LEN;
+
+const EK_LEN: usize = 1184;
+const CT_LEN: usize = 1088;
+const X25519_LEN: usize = 32;
+
+/// The client's message `C_INIT`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientInit {
+    ek: EncapsulationKey<MlKem768Params>,
+    x25519: PublicKey,
+}
+
+impl ClientInit {
+    /// Parse `C_INIT`, as received by the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Length`] if the message is not [`C_INIT_LEN`] bytes long, and
+    /// [`Error::InvalidKey`] if the encapsulation key fails the modulus check of FIPS 203.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != C_INIT_LEN {
+            return Err(Error::Length);
+        }
+        let (ek_bytes, x25519) = bytes.split_at(EK_LEN);
+
+        let ek_bytes = Encoded::<EncapsulationKey<MlKem768Params>>::try_from(ek_bytes)
+            .map_err(|_| Error::Length)?;
+        let ek = EncapsulationKey::from_bytes_checked(&ek_bytes).ok_or(Error::InvalidKey)?;
+
+        Ok(Self {
+            ek,
+            x25519: x25519_public_key(x25519),
+        })
+    }
+
+    /// Encode `C_INIT`, to be sent to the server
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; C_INIT_LEN] {
+        let mut bytes = [0u8; C_INIT_LEN];
+        let (ek, x25519) = bytes.split_at_mut(EK_LEN);
+        ek.copy_from_slice(&self.ek.as_bytes());
+        x25519.copy_from_slice(self.x25519.as_bytes());
+        bytes
+    }
+}
+
+/// The server's message `S_REPLY`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServerReply {
+    ct: EncodedCiphertext<MlKem768Params>,
+    x25519: PublicKey,
+}
+
+impl ServerReply {
+    /// Parse `S_REPLY`, as received by the client.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Length`] if the message is not [`S_REPLY_LEN`] bytes long.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != S_REPLY_LEN {
+            return Err(Error::Length);
+        }
+        let (ct, x25519) = bytes.split_at(CT_LEN);
+
+        Ok(Self {
+            ct: EncodedCiphertext::<MlKem768Params>::try_from(ct).map_err(|_| Error::Length)?,
+            x25519: x25519_public_key(x25519),
+        })
+    }
+
+    /// Encode `S_REPLY`, to be sent to the client
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; S_REPLY_LEN] {
+        let mut bytes = [0u8; S_REPLY_LEN];
+        let (ct, x25519) = bytes.split_at_mut(CT_LEN);
+        ct.copy_from_slice(&self.ct);
+        x25519.copy_from_slice(self.x25519.as_bytes());
+        bytes
+    }
+}
+
+/// The shared secret `K` of the key exchange
+#[derive(Clone, PartialEq, Eq)]
+pub struct SharedSecret([u8; 32]);
+
+impl SharedSecret {
+    /// The raw 32-byte value of `K`
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// `K` encoded as an SSH `string`, as it is hashed into the exchange hash and used to derive
+    /// keys
+    #[must_use]
+    pub fn to_ssh_string(&self) -> [u8; 36] {
+        let mut string = [0u8; 36];
+        string[..4].copy_from_slice(&32u32.to_be_bytes());
+        string[4..].copy_from_slice(&self.0);
+        string
+    }
+
+    fn derive(k_pq: &[u8], k_cl: &x25519_dalek::SharedSecret) -> Result<Self, Error> {
+        if !k_cl.was_contributory() {
+            return Err(Error::X25519);
+        }
+
+        let mut hash = Sha256::new();
+        hash.update(k_pq);
+        hash.update(k_cl.as_bytes());
+        Ok(Self(hash.finalize().into()))
+    }
+}
+
+impl fmt::Debug for SharedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedSecret(..)")
+    }
+}
+
+/// The client's state while it waits for `S_REPLY`
+pub struct Client {
+    dk: DecapsulationKey<MlKem768Params>,
+    x25519: EphemeralSecret,
+}
+
+impl Client {
+    /// Generate the client's ephemeral keys, returning the state and the `C_INIT` message
+    pub fn new(rng: &mut impl CryptoRngCore) -> (Self, ClientInit) {
+        let (dk, ek) = Kem::<MlKem768Params>::generate(rng);
+        let x25519 = EphemeralSecret::random_from_rng(&mut *rng);
+        let init = ClientInit {
+            ek,
+            x25519: PublicKey::from(&x25519),
+        };
+        (Self { dk, x25519 }, init)
+    }
+
+    /// Compute the shared secret from the server's `S_REPLY`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::X25519`] if the server's X25519 public key yields an all-zero shared
+    /// secret.
+    pub fn finish(self, reply: &ServerReply) -> Result<SharedSecret, Error> {
+        let k_pq = decapsulate(&self.dk, &reply.ct);
+        let k_cl = self.x25519.diffie_hellman(&reply.x25519);
+        SharedSecret::derive(&k_pq, &k_cl)
+    }
+}
+
+/// Respond to the client's `C_INIT`, returning the `S_REPLY` message and the shared secret.
+///
+/// # Errors
+///
+/// Returns [`Error::X25519`] if the client's X25519 public key yields an all-zero shared secret.
+pub fn reply(
+    init: &ClientInit,
+    rng: &mut impl CryptoRngCore,
+) -> Result<(ServerReply, SharedSecret), Error> {
+    let (ct, k_pq) = encapsulate(&init.ek, rng);
+    let x25519 = EphemeralSecret::random_from_rng(&mut *rng);
+    let reply = ServerReply {
+        ct,
+        x25519: PublicKey::from(&x25519),
+    };
+
+    let k_cl = x25519.diffie_hellman(&init.x25519);
+    Ok((reply, SharedSecret::derive(&k_pq, &k_cl)?))
+}
+
+fn x25519_public_key(bytes: &[u8]) -> PublicKey {
+    let bytes: [u8; X25519_LEN] = bytes.try_into().expect("X25519 public keys are 32 bytes");
+    PublicKey::from(bytes)
+}
+
+/// An error in the key exchange
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A message has the wrong length
+    Length,
+
+    /// The client's ML-KEM encapsulation key is not a valid encoding
+    InvalidKey,
+
+    /// The peer's X25519 public key is a low-order point, which yields an all-zero shared secret
+    X25519,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Length => "message has the wrong length",
+            Self::InvalidKey => "invalid ML-KEM encapsulation key",
+            Self::X25519 => "X25519 shared secret is all-zero",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::MlKem768;
+    use ::kem::Decapsulate;
+    use hybrid_array::typenum::Unsigned;
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+    use x25519_dalek::{x25519, X25519_BASEPOINT_BYTES};
+
+    #[test]
+    fn lengths() {
+        type Ek = <MlKem768 as KemCore>::EncapsulationKey;
+        assert_eq!(EK_LEN, <Ek as EncodedSizeUser>::EncodedSize::USIZE);
+        assert_eq!(CT_LEN, <MlKem768 as KemCore>::CiphertextSize::USIZE);
+        assert_eq!(C_INIT_LEN, 1216);
+        assert_eq!(S_REPLY_LEN, 1120);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rng = rand::thread_rng();
+        let (client, init) = Client::new(&mut rng);
+        let c_init = init.to_bytes();
+
+        let parsed = ClientInit::from_bytes(&c_init).unwrap();
+        assert_eq!(parsed, init);
+        let (reply, k_server) = super::reply(&parsed, &mut rng).unwrap();
+        let s_reply = reply.to_bytes();
+
+        let parsed = ServerReply::from_bytes(&s_reply).unwrap();
+        assert_eq!(parsed, reply);
+        let k_client = client.finish(&parsed).unwrap();
+        assert_eq!(k_client, k_server);
+
+        let string = k_client.to_ssh_string();
+        assert_eq!(string[..4], [0, 0, 0, 32]);
+        assert_eq!(&string[4..], k_client.as_bytes());
+    }
+
+    #[test]
+    fn combiner() {
+        // K is the SHA-256 hash of the ML-KEM shared key followed by the X25519 shared secret
+        let mut rng = StdRng::seed_from_u64(0);
+        let (dk, ek) = Kem::<MlKem768Params>::generate(&mut rng);
+        let mut client_x25519 = [0u8; 32];
+        rng.fill_bytes(&mut client_x25519);
+        let init = ClientInit {
+            ek,
+            x25519: PublicKey::from(x25519(client_x25519, X25519_BASEPOINT_BYTES)),
+        };
+
+        let (reply, k) = super::reply(&init, &mut rng).unwrap();
+        let k_pq = dk.decapsulate(&reply.ct).unwrap();
+        let k_cl = x25519(client_x25519, reply.x25519.to_bytes());
+
+        let mut expected = Sha256::new();
+        expected.update(k_pq);
+        expected.update(k_cl);
+        assert_eq!(k.as_bytes(), expected.finalize().as_slice());
+    }
+
+    #[test]
+    fn modified_messages() {
+        let mut rng = rand::thread_rng();
+        let (client, init) = Client::new(&mut rng);
+        let (reply, k_server) = super::reply(&init, &mut rng).unwrap();
+
+        // A modified ciphertext yields a different, implicitly rejected, key
+        let mut s_reply = reply.to_bytes();
+        s_reply[0] ^= 0x01;
+        let reply = ServerReply::from_bytes(&s_reply).unwrap();
+        assert_ne!(client.finish(&reply).unwrap(), k_server);
+    }
+
+    #[test]
+    fn invalid_messages() {
+        let mut rng = rand::thread_rng();
+        let (client, init) = Client::new(&mut rng);
+        let c_init = init.to_bytes();
+        let (reply, _) = super::reply(&init, &mut rng).unwrap();
+        let s_reply = reply.to_bytes();
+
+        // Wrong lengths
+        for len in [0, C_INIT_LEN - 1, C_INIT_LEN + 1] {
+            let mut bytes = [0u8; C_INIT_LEN + 1];
+            bytes[..C_INIT_LEN].copy_from_slice(&c_init);
+            assert_eq!(ClientInit::from_bytes(&bytes[..len]), Err(Error::Length));
+        }
+        for len in [0, S_REPLY_LEN - 1, S_REPLY_LEN + 1] {
+            let mut bytes = [0u8; S_REPLY_LEN + 1];
+            bytes[..S_REPLY_LEN].copy_from_slice(&s_reply);
+            assert_eq!(ServerReply::from_bytes(&bytes[..len]), Err(Error::Length));
+        }
+
+        // An encapsulation key coefficient of at least q
+        let mut bad = c_init;
+        bad[0] = 0xff;
+        bad[1] |= 0x0f;
+        assert_eq!(ClientInit::from_bytes(&bad), Err(Error::InvalidKey));
+
+        // An all-zero X25519 public key is rejected by both parties
+        let mut bad = c_init;
+        bad[EK_LEN..].fill(0);
+        let bad = ClientInit::from_bytes(&bad).unwrap();
+        assert_eq!(super::reply(&bad, &mut rng).err(), Some(Error::X25519));
+
+        let mut bad = s_reply;
+        bad[CT_LEN..].fill(0);
+        let bad = ServerReply::from_bytes(&bad).unwrap();
+        assert_eq!(client.finish(&bad).err(), Some(Error::X25519));
+    }
+}
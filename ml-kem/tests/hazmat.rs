@@ -0,0 +1,127 @@
+#![cfg(feature = "hazmat")]
+#![allow(non_snake_case)] // Allow notation matching the spec
+
+use hybrid_array::{
+    typenum::{U1, U10, U12, U2, U3, U4},
+    Array,
+};
+use ml_kem::hazmat::*;
+
+// A toy version of K-PKE with K = 3, built only from the hazmat API
+#[test]
+fn pke_round_trip() {
+    let d = B32::default();
+    let (rho, sigma) = G(&[&d]);
+
+    // Key generation
+    let A_hat = NttMatrix::<U3>::sample_uniform(&rho, false);
+    let s_hat = PolynomialVector::<U3>::sample_cbd::<U2>(&sigma, 0).ntt();
+    let e_hat = PolynomialVector::<U3>::sample_cbd::<U2>(&sigma, 3).ntt();
+    let mut t_hat = &A_hat * &s_hat;
+    t_hat += &e_hat;
+
+    // Encryption
+    let message = B32::from_fn(|i| i as u8);
+    let randomness = J(&[b"randomness"]);
+    let r_hat = PolynomialVector::<U3>::sample_cbd::<U2>(&randomness, 0).ntt();
+    let e1 = PolynomialVector::<U3>::sample_cbd::<U2>(&randomness, 3);
+    let e2 = Polynomial::sample_cbd::<U2>(&PRF::<U2>(&randomness, 6));
+
+    let mut u = (&A_hat.transpose() * &r_hat).ntt_inverse();
+    u += &e1;
+
+    let mut mu: Polynomial = Encode::<U1>::decode(&message);
+    mu.decompress::<U1>();
+    let mut v = (&t_hat * &r_hat).ntt_inverse();
+    v += &e2;
+    v += &mu;
+
+    u.compress::<U10>();
+    v.compress::<U4>();
+    let c1 = Encode::<U10>::encode(&u);
+    let c2 = Encode::<U4>::encode(&v);
+
+    // Decryption
+    let mut u: PolynomialVector<U3> = Encode::<U10>::decode(&c1);
+    u.decompress::<U10>();
+    let mut v: Polynomial = Encode::<U4>::decode(&c2);
+    v.decompress::<U4>();
+
+    let mut w = v;
+    w -= &(&s_hat * &u.ntt()).ntt_inverse();
+    w.compress::<U1>();
+    assert_eq!(Encode::<U1>::encode(&w), message);
+}
+
+#[test]
+fn ntt_domain_conversions() {
+    let coefficients = Array::from_fn(|i| FieldElement::new(7 * i as Integer + 3000));
+    let f = Polynomial::new(coefficients);
+    assert_eq!(f.coefficients(), &coefficients);
+    assert!(f.coefficients().iter().all(|x| x.value() < FieldElement::Q));
+
+    // Values survive a round trip through the NTT domain, and through encoding there
+    let f_hat = f.ntt();
+    assert_eq!(f_hat.ntt_inverse(), f);
+
+    let enc = Encode::<U12>::encode(&f_hat);
+    let f_hat_decoded: NttPolynomial = Encode::<U12>::decode(&enc);
+    assert_eq!(f_hat_decoded, f_hat);
+
+    // Canonical coefficients convert to and from the NTT domain representation
+    let g_hat = NttPolynomial::from(f_hat.coefficients());
+    assert_eq!(g_hat, f_hat);
+    assert_eq!(Array::from(g_hat), f_hat.coefficients());
+}
+
+// Exercise K-PKE through the hazmat API, including re-randomizing a key pair from its parts
+fn pke_test<P: PkeParams>() {
+    let d = B32::from_fn(|i| i as u8);
+    let (dk, ek) = DecryptionKey::<P>::generate(&d);
+
+    let message = B32::from_fn(|i| 0xa5 ^ i as u8);
+    let randomness = B32::from_fn(|i| 0x5a ^ i as u8);
+    let ct = ek.encrypt(&message, &randomness);
+    assert_eq!(dk.decrypt(&ct), message);
+    assert_eq!(ek.encrypt(&message, &randomness), ct);
+
+    // The encodings agree with the parts of the keys
+    let ek_bytes = ek.as_bytes();
+    let (t_hat_bytes, rho) = P::split_ek(&ek_bytes);
+    assert_eq!(rho, ek.rho());
+    assert_eq!(&P::decode_u12(t_hat_bytes), ek.t_hat());
+    assert_eq!(EncryptionKey::<P>::from_bytes(&ek_bytes), ek);
+    assert_eq!(P::decode_u12(&dk.as_bytes()), *dk.s_hat());
+    assert_eq!(DecryptionKey::<P>::from_bytes(&dk.as_bytes()), dk);
+
+    let (c1, c2) = P::split_ct(&ct);
+    assert_eq!(P::concat_ct(c1.clone(), c2.clone()), ct);
+
+    // Re-randomize: (s_hat + s_hat', t_hat + A_hat s_hat' + e_hat') is another key pair for the
+    // same matrix, with slightly more noise
+    let sigma = J(&[b"re-randomization"]);
+    let A_hat = NttMatrix::<P::K>::sample_uniform(ek.rho(), false);
+    let s_hat_prime = PolynomialVector::<P::K>::sample_cbd::<P::Eta1>(&sigma, 0).ntt();
+    let e_hat_prime = PolynomialVector::<P::K>::sample_cbd::<P::Eta1>(&sigma, 4).ntt();
+
+    let mut s_hat = dk.s_hat().clone();
+    s_hat += &s_hat_prime;
+    let mut t_hat = ek.t_hat().clone();
+    t_hat += &(&A_hat * &s_hat_prime);
+    t_hat += &e_hat_prime;
+
+    let dk2 = DecryptionKey::<P>::from_parts(s_hat);
+    let ek2 = EncryptionKey::<P>::from_parts(t_hat, *rho);
+    assert_ne!(ek2, ek);
+
+    let ct2 = ek2.encrypt(&message, &randomness);
+    assert_ne!(ct2, ct);
+    assert_eq!(dk2.decrypt(&ct2), message);
+}
+
+#[test]
+fn pke() {
+    pke_test::<ml_kem::MlKem512Params>();
+    pke_test::<ml_kem::MlKem768Params>();
+    pke_test::<ml_kem::MlKem1024Params>();
+}
@@ -0,0 +1,233 @@
+use ::kem::{Decapsulate, Encapsulate};
+use hybrid_array::Array;
+use ml_kem::*;
+use sha3::{
+    digest::{ExtendableOutput, Update, XofReader},
+    Shake256,
+};
+
+// The implicit rejection value `J(z || c)`, computed independently of the crate.  The value `z` is
+// the last 32 bytes of the encoded decapsulation key.
+fn rejection_key(dk: &[u8], c: &[u8]) -> [u8; 32] {
+    let z = &dk[dk.len() - 32..];
+
+    let mut h = Shake256::default();
+    h.update(z);
+    h.update(c);
+
+    let mut out = [0u8; 32];
+    h.finalize_xof().read(&mut out);
+    out
+}
+
+// Verify that decapsulating a modified ciphertext yields exactly `J(z || c)`
+fn assert_rejected<K>(dk: &K::DecapsulationKey, c: &Ciphertext<K>, k_send: &SharedKey<K>)
+where
+    K: KemCore,
+{
+    let k_recv = dk.decapsulate(c).unwrap();
+    assert_ne!(&k_recv, k_send);
+    assert_eq!(k_recv.as_slice(), rejection_key(&dk.as_bytes(), c));
+}
+
+fn bit_flip_test<K>()
+where
+    K: KemCore,
+{
+    let mut rng = rand::thread_rng();
+    let (dk, ek) = K::generate(&mut rng);
+    let (ct, k_send) = ek.encapsulate(&mut rng).unwrap();
+
+    // Flip a bit in the first byte, the last byte, and a spread of positions in between, so that
+    // both the `u` and `v` components of the ciphertext are covered.
+    let len = ct.len();
+    let positions = [
+        0,
+        1,
+        len / 4,
+        len / 2,
+        len - 129,
+        len - 128,
+        len - 64,
+        len - 1,
+    ];
+    for i in positions {
+        for bit in [0, 7] {
+            let mut c = ct.clone();
+            c[i] ^= 1 << bit;
+            assert_rejected::<K>(&dk, &c, &k_send);
+        }
+    }
+
+    // The original ciphertext still decapsulates correctly
+    assert_eq!(dk.decapsulate(&ct).unwrap(), k_send);
+}
+
+#[test]
+fn bit_flip() {
+    bit_flip_test::<MlKem512>();
+    bit_flip_test::<MlKem768>();
+    bit_flip_test::<MlKem1024>();
+}
+
+fn truncation_test<K>()
+where
+    K: KemCore,
+{
+    let mut rng = rand::thread_rng();
+    let (dk, ek) = K::generate(&mut rng);
+    let (ct, _) = ek.encapsulate(&mut rng).unwrap();
+
+    // Truncated ciphertexts and keys cannot be passed to decapsulation or encapsulation at all
+    for cut in [1, 16, 32, 128, ct.len() / 2, ct.len() - 1, ct.len()] {
+        let len = ct.len() - cut;
+        assert!(Ciphertext::<K>::try_from(&ct[..len]).is_err());
+    }
+
+    let dk_bytes = dk.as_bytes();
+    let len = dk_bytes.len();
+    assert!(Encoded::<K::DecapsulationKey>::try_from(&dk_bytes[..len - 1]).is_err());
+    let ek_bytes = ek.as_bytes();
+    let len = ek_bytes.len();
+    assert!(Encoded::<K::EncapsulationKey>::try_from(&ek_bytes[..len - 1]).is_err());
+}
+
+#[test]
+fn truncation() {
+    truncation_test::<MlKem512>();
+    truncation_test::<MlKem768>();
+    truncation_test::<MlKem1024>();
+}
+
+fn overwritten_tail_test<K>()
+where
+    K: KemCore,
+{
+    let mut rng = rand::thread_rng();
+    let (dk, ek) = K::generate(&mut rng);
+    let (ct, k_send) = ek.encapsulate(&mut rng).unwrap();
+
+    // Overwrite the tail of the ciphertext with zero or one bytes, keeping its length
+    for cut in [1, 16, 32, 128, ct.len() / 2, ct.len() - 1] {
+        for pad in [0x00, 0xff] {
+            let mut c = ct.clone();
+            let len = c.len();
+            c[len - cut..].fill(pad);
+            if c == ct {
+                continue;
+            }
+
+            assert_rejected::<K>(&dk, &c, &k_send);
+        }
+    }
+}
+
+#[test]
+fn overwritten_tail() {
+    overwritten_tail_test::<MlKem512>();
+    overwritten_tail_test::<MlKem768>();
+    overwritten_tail_test::<MlKem1024>();
+}
+
+// Feed a ciphertext from a larger parameter set, truncated to the valid length for `K`
+fn foreign_ciphertext_test<K, L>()
+where
+    K: KemCore,
+    L: KemCore,
+{
+    let mut rng = rand::thread_rng();
+    let (dk, ek) = K::generate(&mut rng);
+    let (_, k_send) = ek.encapsulate(&mut rng).unwrap();
+
+    let (_, ek_other) = L::generate(&mut rng);
+    let (ct_other, _) = ek_other.encapsulate(&mut rng).unwrap();
+
+    let len = Ciphertext::<K>::default().len();
+    let c = Ciphertext::<K>::try_from(&ct_other[..len]).unwrap();
+    assert_rejected::<K>(&dk, &c, &k_send);
+}
+
+#[test]
+fn foreign_ciphertext() {
+    foreign_ciphertext_test::<MlKem512, MlKem768>();
+    foreign_ciphertext_test::<MlKem512, MlKem1024>();
+    foreign_ciphertext_test::<MlKem768, MlKem1024>();
+}
+
+fn mismatched_z_test<K>()
+where
+    K: KemCore,
+{
+    let mut rng = rand::thread_rng();
+    let (dk, ek) = K::generate(&mut rng);
+    let (ct, k_send) = ek.encapsulate(&mut rng).unwrap();
+
+    // Re-encode the decapsulation key with a different `z`
+    let mut dk_bytes = dk.as_bytes();
+    let len = dk_bytes.len();
+    dk_bytes[len - 32..].iter_mut().for_each(|x| *x ^= 0x5a);
+    let dk_other = K::DecapsulationKey::from_bytes(&dk_bytes);
+    assert_ne!(dk, dk_other);
+
+    // `z` is only used for implicit rejection, so valid ciphertexts are unaffected...
+    assert_eq!(dk_other.decapsulate(&ct).unwrap(), k_send);
+
+    // ... but invalid ciphertexts must produce a different, `z`-dependent rejection key
+    let mut c = ct.clone();
+    c[0] ^= 1;
+    let k_reject = dk.decapsulate(&c).unwrap();
+    let k_reject_other = dk_other.decapsulate(&c).unwrap();
+    assert_ne!(k_reject, k_reject_other);
+    assert_eq!(k_reject.as_slice(), rejection_key(&dk.as_bytes(), &c));
+    assert_eq!(k_reject_other.as_slice(), rejection_key(&dk_bytes, &c));
+}
+
+#[test]
+fn mismatched_z() {
+    mismatched_z_test::<MlKem512>();
+    mismatched_z_test::<MlKem768>();
+    mismatched_z_test::<MlKem1024>();
+}
+
+fn rejection_is_deterministic_test<K>()
+where
+    K: KemCore,
+{
+    let mut rng = rand::thread_rng();
+    let (dk, ek) = K::generate(&mut rng);
+    let (ct, _) = ek.encapsulate(&mut rng).unwrap();
+
+    let mut c1 = ct.clone();
+    c1[3] ^= 0x10;
+    let mut c2 = ct.clone();
+    c2[4] ^= 0x10;
+
+    // The same invalid ciphertext is always rejected to the same key, and distinct invalid
+    // ciphertexts are rejected to distinct keys.
+    let k1 = dk.decapsulate(&c1).unwrap();
+    assert_eq!(k1, dk.decapsulate(&c1).unwrap());
+    assert_ne!(k1, dk.decapsulate(&c2).unwrap());
+
+    // A decapsulation key restored from its encoding rejects identically
+    let dk_decoded = K::DecapsulationKey::from_bytes(&dk.as_bytes());
+    assert_eq!(k1, dk_decoded.decapsulate(&c1).unwrap());
+
+    let zero: Array<u8, _> = Ciphertext::<K>::default();
+    assert_eq!(
+        dk.decapsulate(&zero).unwrap().as_slice(),
+        rejection_key(&dk.as_bytes(), &zero)
+    );
+
+    let ones: Array<u8, _> = Array::from_fn(|_| 0xff);
+    assert_eq!(
+        dk.decapsulate(&ones).unwrap().as_slice(),
+        rejection_key(&dk.as_bytes(), &ones)
+    );
+}
+
+#[test]
+fn rejection_is_deterministic() {
+    rejection_is_deterministic_test::<MlKem512>();
+    rejection_is_deterministic_test::<MlKem768>();
+    rejection_is_deterministic_test::<MlKem1024>();
+}
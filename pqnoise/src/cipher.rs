@@ -0,0 +1,126 @@
+use alloc::vec::Vec;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305,
+};
+
+use crate::Error;
+
+/// The length of a cipher key
+pub(crate) const KEY_LEN: usize = 32;
+
+/// The length of an authentication tag
+pub(crate) const TAG_LEN: usize = 16;
+
+/// A `CipherState` encrypts and decrypts with a key `k` and a counter nonce `n`
+#[derive(Clone, Default)]
+pub(crate) struct CipherState {
+    k: Option<[u8; KEY_LEN]>,
+    n: u64,
+}
+
+impl CipherState {
+    pub fn new(k: [u8; KEY_LEN]) -> Self {
+        Self { k: Some(k), n: 0 }
+    }
+
+    pub fn has_key(&self) -> bool {
+        self.k.is_some()
+    }
+
+    // The ChaChaPoly nonce is 32 bits of zeros followed by the little-endian counter
+    fn nonce(&self) -> [u8; 12] {
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&self.n.to_le_bytes());
+        nonce
+    }
+
+    pub fn encrypt_with_ad(&mut self, ad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let Some(k) = &self.k else {
+            return Ok(plaintext.to_vec());
+        };
+
+        // The maximum nonce 2^64 - 1 is reserved
+        if self.n == u64::MAX {
+            return Err(Error::NonceExhausted);
+        }
+
+        let cipher = ChaCha20Poly1305::new(k.into());
+        let payload = Payload {
+            msg: plaintext,
+            aad: ad,
+        };
+        let ciphertext = cipher
+            .encrypt(&self.nonce().into(), payload)
+            .map_err(|_| Error::MessageTooLong)?;
+        self.n += 1;
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt_with_ad(&mut self, ad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        let Some(k) = &self.k else {
+            return Ok(ciphertext.to_vec());
+        };
+
+        if self.n == u64::MAX {
+            return Err(Error::NonceExhausted);
+        }
+
+        let cipher = ChaCha20Poly1305::new(k.into());
+        let payload = Payload {
+            msg: ciphertext,
+            aad: ad,
+        };
+        let plaintext = cipher
+            .decrypt(&self.nonce().into(), payload)
+            .map_err(|_| Error::Decrypt)?;
+        self.n += 1;
+        Ok(plaintext)
+    }
+
+    /// The length of the encryption of a plaintext of length `len`
+    pub fn ciphertext_len(&self, len: usize) -> usize {
+        if self.has_key() {
+            len + TAG_LEN
+        } else {
+            len
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut send = CipherState::new([7; KEY_LEN]);
+        let mut recv = CipherState::new([7; KEY_LEN]);
+
+        for _ in 0..3 {
+            let ct = send.encrypt_with_ad(b"ad", b"message").unwrap();
+            assert_eq!(ct.len(), send.ciphertext_len(7));
+            assert_eq!(recv.decrypt_with_ad(b"ad", &ct).unwrap(), b"message");
+        }
+
+        // A failed decryption does not advance the nonce
+        let ct = send.encrypt_with_ad(b"ad", b"message").unwrap();
+        assert_eq!(recv.decrypt_with_ad(b"bad", &ct), Err(Error::Decrypt));
+        assert_eq!(recv.decrypt_with_ad(b"ad", &ct).unwrap(), b"message");
+
+        // Without a key, messages are passed through
+        let mut empty = CipherState::default();
+        assert_eq!(
+            empty.encrypt_with_ad(b"ad", b"message").unwrap(),
+            b"message"
+        );
+    }
+
+    #[test]
+    fn nonce_exhaustion() {
+        let mut cipher = CipherState::new([7; KEY_LEN]);
+        cipher.n = u64::MAX - 1;
+        assert!(cipher.encrypt_with_ad(b"", b"").is_ok());
+        assert_eq!(cipher.encrypt_with_ad(b"", b""), Err(Error::NonceExhausted));
+    }
+}
@@ -0,0 +1,404 @@
+use alloc::{format, vec::Vec};
+use hybrid_array::{typenum::Unsigned, Array};
+use kem::Decapsulate;
+use ml_kem::{
+    Ciphertext, EncapsulateDeterministic, EncodedSizeUser, KemCore, MlKem768, SharedKey, B32,
+};
+use rand_core::CryptoRngCore;
+
+use crate::pattern::{Pattern, Token};
+use crate::symmetric::{HashFunction, SymmetricState, HASH_LEN};
+use crate::transport::TransportState;
+use crate::{DecapsulationKey, EncapsulationKey, Error, Keypair, MAX_MESSAGE_LEN};
+
+type EncapsulationKeySize = <EncapsulationKey as EncodedSizeUser>::EncodedSize;
+type CiphertextSize = <MlKem768 as KemCore>::CiphertextSize;
+
+/// A `HandshakeState` runs one side of a handshake.  The initiator and responder take turns to
+/// write and read messages, until the pattern is complete and the state can be converted into a
+/// [`TransportState`].  Both parties must use the same hash function `H`.
+///
+/// If writing or reading a message fails, the handshake has failed, and the state must be
+/// discarded.
+pub struct HandshakeState<H: HashFunction> {
+    symmetric: SymmetricState<H>,
+    pattern: Pattern,
+    initiator: bool,
+    s: Option<Keypair>,
+    e: Option<DecapsulationKey>,
+    rs: Option<EncapsulationKey>,
+    re: Option<EncapsulationKey>,
+    next: usize,
+}
+
+impl<H: HashFunction> HandshakeState<H> {
+    /// Start a handshake as the initiator.  The static key pair `s` is required by the `pqXX`
+    /// and `pqIK` patterns, and the responder's static key `rs` by the `pqIK` pattern.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::MissingKey`] if a key required by the pattern is not provided.
+    pub fn initiator(
+        pattern: Pattern,
+        prologue: &[u8],
+        s: Option<Keypair>,
+        rs: Option<EncapsulationKey>,
+    ) -> Result<Self, Error> {
+        if pattern.responder_key_known() && rs.is_none() {
+            return Err(Error::MissingKey);
+        }
+        Self::new(pattern, true, prologue, s, rs)
+    }
+
+    /// Start a handshake as the responder.  The static key pair `s` is required by the `pqXX`
+    /// and `pqIK` patterns.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::MissingKey`] if a key required by the pattern is not provided.
+    pub fn responder(pattern: Pattern, prologue: &[u8], s: Option<Keypair>) -> Result<Self, Error> {
+        Self::new(pattern, false, prologue, s, None)
+    }
+
+    fn new(
+        pattern: Pattern,
+        initiator: bool,
+        prologue: &[u8],
+        s: Option<Keypair>,
+        rs: Option<EncapsulationKey>,
+    ) -> Result<Self, Error> {
+        if pattern.needs_static(initiator) && s.is_none() {
+            return Err(Error::MissingKey);
+        }
+
+        let name = format!("Noise_{}_MLKEM768_ChaChaPoly_{}", pattern.name(), H::NAME);
+        let mut symmetric = SymmetricState::new(name.as_bytes());
+        symmetric.mix_hash(prologue);
+
+        // The pre-message `<- s`
+        if pattern.responder_key_known() {
+            let responder_key = if initiator {
+                rs.as_ref()
+            } else {
+                s.as_ref().map(|s| &s.ek)
+            };
+            symmetric.mix_hash(&responder_key.ok_or(Error::MissingKey)?.as_bytes());
+        }
+
+        Ok(Self {
+            symmetric,
+            pattern,
+            initiator,
+            s,
+            e: None,
+            rs,
+            re: None,
+            next: 0,
+        })
+    }
+
+    /// Whether the next message is to be written by this party, rather than read
+    #[must_use]
+    pub fn is_my_turn(&self) -> bool {
+        !self.is_finished() && (self.next % 2 == 0) == self.initiator
+    }
+
+    /// Whether all of the messages of the pattern have been written or read
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.next == self.pattern.messages().len()
+    }
+
+    /// The peer's static encapsulation key, if it is known
+    #[must_use]
+    pub fn remote_static(&self) -> Option<&EncapsulationKey> {
+        self.rs.as_ref()
+    }
+
+    /// The handshake hash `h`, which identifies the handshake and can be used for channel binding
+    #[must_use]
+    pub fn handshake_hash(&self) -> [u8; HASH_LEN] {
+        self.symmetric.handshake_hash()
+    }
+
+    /// Write the next handshake message, carrying `payload`.  The randomness for ephemeral keys
+    /// and encapsulations is drawn from `rng`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutOfOrder`] if it is not this party's turn to write, and
+    /// [`Error::MessageTooLong`] if the message would exceed the maximum message length.
+    pub fn write_message(
+        &mut self,
+        payload: &[u8],
+        rng: &mut impl CryptoRngCore,
+    ) -> Result<Vec<u8>, Error> {
+        if !self.is_my_turn() {
+            return Err(Error::OutOfOrder);
+        }
+
+        let mut message = Vec::new();
+        for token in self.pattern.messages()[self.next] {
+            match token {
+                Token::E => {
+                    let d: B32 = random(rng);
+                    let z: B32 = random(rng);
+                    let (dk, ek) = MlKem768::generate_deterministic(&d, &z);
+                    let ek = ek.as_bytes();
+                    self.symmetric.mix_hash(&ek);
+                    message.extend_from_slice(&ek);
+                    self.e = Some(dk);
+                }
+                Token::Ekem | Token::Skem => {
+                    let peer = if *token == Token::Ekem {
+                        &self.re
+                    } else {
+                        &self.rs
+                    };
+                    let peer = peer.as_ref().ok_or(Error::MissingKey)?;
+                    let (ct, k) = encapsulate(peer, rng);
+                    message.extend(self.symmetric.encrypt_and_hash(&ct)?);
+                    self.symmetric.mix_key(&k);
+                }
+                Token::S => {
+                    let s = self.s.as_ref().ok_or(Error::MissingKey)?;
+                    message.extend(self.symmetric.encrypt_and_hash(&s.ek.as_bytes())?);
+                }
+            }
+        }
+        message.extend(self.symmetric.encrypt_and_hash(payload)?);
+
+        if message.len() > MAX_MESSAGE_LEN {
+            return Err(Error::MessageTooLong);
+        }
+        self.next += 1;
+        Ok(message)
+    }
+
+    /// Read the next handshake message, and return its payload.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutOfOrder`] if it is not this party's turn to read,
+    /// [`Error::Truncated`] if the message is too short, [`Error::Decrypt`] if the message does
+    /// not authenticate, and [`Error::InvalidKey`] if it carries an invalid encapsulation key.
+    pub fn read_message(&mut self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        if self.is_finished() || self.is_my_turn() {
+            return Err(Error::OutOfOrder);
+        }
+        if message.len() > MAX_MESSAGE_LEN {
+            return Err(Error::MessageTooLong);
+        }
+
+        let mut reader = Reader(message);
+        for token in self.pattern.messages()[self.next] {
+            match token {
+                Token::E => {
+                    let ek = reader.take(EncapsulationKeySize::USIZE)?;
+                    self.symmetric.mix_hash(ek);
+                    self.re = Some(
+                        EncapsulationKey::from_bytes_checked(&array(ek)?)
+                            .ok_or(Error::InvalidKey)?,
+                    );
+                }
+                Token::Ekem | Token::Skem => {
+                    let dk = if *token == Token::Ekem {
+                        self.e.as_ref()
+                    } else {
+                        self.s.as_ref().map(|s| &s.dk)
+                    };
+                    let dk = dk.ok_or(Error::MissingKey)?;
+                    let len = self.symmetric.ciphertext_len(CiphertextSize::USIZE);
+                    let ct = self.symmetric.decrypt_and_hash(reader.take(len)?)?;
+                    let k = decapsulate(dk, &array(&ct)?);
+                    self.symmetric.mix_key(&k);
+                }
+                Token::S => {
+                    let len = self.symmetric.ciphertext_len(EncapsulationKeySize::USIZE);
+                    let ek = self.symmetric.decrypt_and_hash(reader.take(len)?)?;
+                    self.rs = Some(
+                        EncapsulationKey::from_bytes_checked(&array(&ek)?)
+                            .ok_or(Error::InvalidKey)?,
+                    );
+                }
+            }
+        }
+
+        let payload = reader.0;
+        if self.symmetric.ciphertext_len(0) > payload.len() {
+            return Err(Error::Truncated);
+        }
+        let payload = self.symmetric.decrypt_and_hash(payload)?;
+
+        self.next += 1;
+        Ok(payload)
+    }
+
+    /// Finish the handshake, and derive the transport keys.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutOfOrder`] if the handshake is not finished.
+    pub fn into_transport(self) -> Result<TransportState, Error> {
+        if !self.is_finished() {
+            return Err(Error::OutOfOrder);
+        }
+
+        let (initiator_to_responder, responder_to_initiator) = self.symmetric.split();
+        let (send, recv) = if self.initiator {
+            (initiator_to_responder, responder_to_initiator)
+        } else {
+            (responder_to_initiator, initiator_to_responder)
+        };
+        Ok(TransportState::new(
+            send,
+            recv,
+            self.symmetric.handshake_hash(),
+            self.rs,
+        ))
+    }
+}
+
+// Read fields of known length from the front of a message
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(Error::Truncated);
+        }
+
+        let (field, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(field)
+    }
+}
+
+fn array<N: hybrid_array::ArraySize>(bytes: &[u8]) -> Result<Array<u8, N>, Error> {
+    Array::try_from(bytes).map_err(|_| Error::Truncated)
+}
+
+// ML-KEM encapsulation and decapsulation, whose `Result`s are always `Ok`
+fn encapsulate(
+    ek: &EncapsulationKey,
+    rng: &mut impl CryptoRngCore,
+) -> (Ciphertext<MlKem768>, SharedKey<MlKem768>) {
+    let m: B32 = random(rng);
+    let Ok(encapsulated) = ek.encapsulate_deterministic(&m) else {
+        unreachable!("ML-KEM encapsulation is infallible")
+    };
+    encapsulated
+}
+
+fn decapsulate(dk: &DecapsulationKey, ct: &Ciphertext<MlKem768>) -> SharedKey<MlKem768> {
+    let Ok(k) = dk.decapsulate(ct) else {
+        unreachable!("ML-KEM decapsulation is infallible")
+    };
+    k
+}
+
+fn random(rng: &mut impl CryptoRngCore) -> B32 {
+    let mut value = B32::default();
+    rng.fill_bytes(&mut value);
+    value
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cipher::TAG_LEN;
+    use sha2::Sha256;
+
+    type HandshakeState = super::HandshakeState<Sha256>;
+
+    #[test]
+    fn missing_keys() {
+        let mut rng = rand::thread_rng();
+        let s = Keypair::generate(&mut rng);
+
+        assert!(HandshakeState::initiator(Pattern::NN, b"", None, None).is_ok());
+        assert!(HandshakeState::responder(Pattern::NN, b"", None).is_ok());
+
+        assert_eq!(
+            HandshakeState::initiator(Pattern::XX, b"", None, None).err(),
+            Some(Error::MissingKey)
+        );
+        assert_eq!(
+            HandshakeState::responder(Pattern::XX, b"", None).err(),
+            Some(Error::MissingKey)
+        );
+
+        assert_eq!(
+            HandshakeState::initiator(Pattern::IK, b"", Some(s.clone()), None).err(),
+            Some(Error::MissingKey)
+        );
+        assert_eq!(
+            HandshakeState::responder(Pattern::IK, b"", None).err(),
+            Some(Error::MissingKey)
+        );
+        assert!(HandshakeState::responder(Pattern::IK, b"", Some(s)).is_ok());
+    }
+
+    #[test]
+    fn invalid_key() {
+        let mut rng = rand::thread_rng();
+        let mut initiator = HandshakeState::initiator(Pattern::NN, b"", None, None).unwrap();
+        let mut responder = HandshakeState::responder(Pattern::NN, b"", None).unwrap();
+
+        // Set the first coefficient of the ephemeral key to q, which fails the modulus check
+        let mut message = initiator.write_message(b"", &mut rng).unwrap();
+        message[0] = 0x01;
+        message[1] = (message[1] & 0xf0) | 0x0d;
+        assert_eq!(responder.read_message(&message), Err(Error::InvalidKey));
+    }
+
+    #[test]
+    fn out_of_order() {
+        let mut rng = rand::thread_rng();
+        let mut initiator = HandshakeState::initiator(Pattern::NN, b"", None, None).unwrap();
+        let mut responder = HandshakeState::responder(Pattern::NN, b"", None).unwrap();
+
+        assert!(initiator.is_my_turn());
+        assert!(!responder.is_my_turn());
+        assert_eq!(
+            responder.write_message(b"", &mut rng),
+            Err(Error::OutOfOrder)
+        );
+        assert_eq!(initiator.read_message(b""), Err(Error::OutOfOrder));
+
+        let message = initiator.write_message(b"", &mut rng).unwrap();
+        assert_eq!(
+            responder.read_message(&message[..message.len() - 1]),
+            Err(Error::Truncated)
+        );
+    }
+
+    #[test]
+    fn message_lengths() {
+        let mut rng = rand::thread_rng();
+        let ek = EncapsulationKeySize::USIZE;
+        let ct = CiphertextSize::USIZE;
+
+        let s_i = Keypair::generate(&mut rng);
+        let s_r = Keypair::generate(&mut rng);
+        let mut initiator = HandshakeState::initiator(Pattern::XX, b"", Some(s_i), None).unwrap();
+        let mut responder = HandshakeState::responder(Pattern::XX, b"", Some(s_r)).unwrap();
+
+        let expected = [
+            ek,
+            ct + (ek + TAG_LEN) + TAG_LEN,
+            (ct + TAG_LEN) + (ek + TAG_LEN) + TAG_LEN,
+            (ct + TAG_LEN) + TAG_LEN,
+        ];
+        for (i, &len) in expected.iter().enumerate() {
+            let (writer, reader) = if i % 2 == 0 {
+                (&mut initiator, &mut responder)
+            } else {
+                (&mut responder, &mut initiator)
+            };
+            let message = writer.write_message(b"", &mut rng).unwrap();
+            assert_eq!(message.len(), len);
+            assert!(reader.read_message(&message).unwrap().is_empty());
+        }
+    }
+}
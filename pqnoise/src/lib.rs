@@ -0,0 +1,154 @@
+#![no_std]
+#![cfg_attr(docsrs, feature(doc_auto_cfg))]
+#![doc = include_str!("../README.md")]
+#![doc(
+    html_logo_url = "https://raw.githubusercontent.com/RustCrypto/meta/master/logo.svg",
+    html_favicon_url = "https://raw.githubusercontent.com/RustCrypto/meta/master/logo.svg"
+)]
+#![warn(clippy::pedantic)] // Be pedantic by default
+#![deny(missing_docs)] // Require all public interfaces to be documented
+
+//! # Usage
+//!
+//! Each party creates a [`HandshakeState`] for the chosen [`Pattern`], and the two exchange
+//! handshake messages until the pattern is finished.  Each handshake message can carry a payload,
+//! which is encrypted once a shared key has been established.  The resulting [`TransportState`]s
+//! then protect application messages.
+//!
+//! ```
+//! use pqnoise::{HandshakeState, Keypair, Pattern, Sha256};
+//!
+//! let mut rng = rand::thread_rng();
+//! let initiator_key = Keypair::generate(&mut rng);
+//! let responder_key = Keypair::generate(&mut rng);
+//!
+//! let mut initiator =
+//!     HandshakeState::<Sha256>::initiator(Pattern::XX, b"prologue", Some(initiator_key), None)
+//!         .unwrap();
+//! let mut responder =
+//!     HandshakeState::<Sha256>::responder(Pattern::XX, b"prologue", Some(responder_key))
+//!         .unwrap();
+//!
+//! // -> e
+//! let message = initiator.write_message(b"", &mut rng).unwrap();
+//! responder.read_message(&message).unwrap();
+//!
+//! // <- ekem, s
+//! let message = responder.write_message(b"", &mut rng).unwrap();
+//! initiator.read_message(&message).unwrap();
+//!
+//! // -> skem, s
+//! let message = initiator.write_message(b"", &mut rng).unwrap();
+//! responder.read_message(&message).unwrap();
+//!
+//! // <- skem
+//! let message = responder.write_message(b"", &mut rng).unwrap();
+//! initiator.read_message(&message).unwrap();
+//!
+//! let mut initiator = initiator.into_transport().unwrap();
+//! let mut responder = responder.into_transport().unwrap();
+//!
+//! let message = initiator.write_message(b"hello").unwrap();
+//! assert_eq!(responder.read_message(&message).unwrap(), b"hello");
+//! ```
+//!
+//! The protocol name is `Noise_<pattern>_MLKEM768_ChaChaPoly_<hash>`, where the hash function is
+//! [`Sha256`] or [`Blake2s256`], with the names `SHA256` and `BLAKE2s`.  Encapsulation ciphertexts
+//! and static keys are sent with `EncryptAndHash`, so they are encrypted once a key has been
+//! established, and each encapsulated shared key is mixed in with `MixKey`.
+//!
+//! All randomness is drawn from the RNG passed to [`HandshakeState::write_message`], and ML-KEM
+//! keys and encapsulations are derived from it deterministically.  A handshake driven by a seeded
+//! RNG is therefore reproducible, which is useful for testing.
+
+extern crate alloc;
+
+mod cipher;
+mod handshake;
+mod pattern;
+mod symmetric;
+mod transport;
+
+pub use blake2::Blake2s256;
+pub use handshake::HandshakeState;
+pub use pattern::Pattern;
+pub use sha2::Sha256;
+pub use symmetric::HashFunction;
+pub use transport::TransportState;
+
+use core::fmt;
+use ml_kem::{KemCore, MlKem768, B32};
+use rand_core::CryptoRngCore;
+
+/// An ML-KEM-768 decapsulation key
+pub type DecapsulationKey = <MlKem768 as KemCore>::DecapsulationKey;
+
+/// An ML-KEM-768 encapsulation key
+pub type EncapsulationKey = <MlKem768 as KemCore>::EncapsulationKey;
+
+/// The maximum length of a Noise message
+pub const MAX_MESSAGE_LEN: usize = 65535;
+
+/// A static key pair
+#[derive(Clone, Debug, PartialEq)]
+pub struct Keypair {
+    /// The decapsulation key
+    pub dk: DecapsulationKey,
+
+    /// The encapsulation key
+    pub ek: EncapsulationKey,
+}
+
+impl Keypair {
+    /// Generate a new key pair
+    pub fn generate(rng: &mut impl CryptoRngCore) -> Self {
+        let (dk, ek) = MlKem768::generate(rng);
+        Self { dk, ek }
+    }
+
+    /// Derive a key pair deterministically from the ML-KEM seeds `d` and `z`
+    #[must_use]
+    pub fn from_seeds(d: &B32, z: &B32) -> Self {
+        let (dk, ek) = MlKem768::generate_deterministic(d, z);
+        Self { dk, ek }
+    }
+}
+
+/// An error in a handshake or transport
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A key required by the handshake pattern was not provided
+    MissingKey,
+
+    /// A message was written or read out of turn, or the handshake is not finished
+    OutOfOrder,
+
+    /// A message is shorter than the handshake pattern requires
+    Truncated,
+
+    /// A message is longer than [`MAX_MESSAGE_LEN`]
+    MessageTooLong,
+
+    /// A peer's encapsulation key fails the modulus check of FIPS 203
+    InvalidKey,
+
+    /// A message failed to authenticate
+    Decrypt,
+
+    /// A cipher key has been used for the maximum number of messages
+    NonceExhausted,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::MissingKey => "missing key required by the handshake pattern",
+            Self::OutOfOrder => "message out of order",
+            Self::Truncated => "message truncated",
+            Self::MessageTooLong => "message too long",
+            Self::InvalidKey => "invalid encapsulation key",
+            Self::Decrypt => "message failed to authenticate",
+            Self::NonceExhausted => "nonce exhausted",
+        })
+    }
+}
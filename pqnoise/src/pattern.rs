@@ -0,0 +1,90 @@
+/// A post-quantum Noise handshake pattern.  Each pattern replaces the Diffie-Hellman operations
+/// of the corresponding Noise pattern with encapsulations to the peer's ephemeral (`ekem`) or
+/// static (`skem`) key.
+#[allow(clippy::upper_case_acronyms)] // Pattern names are conventionally upper-case
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Pattern {
+    /// No static keys, so neither party is authenticated:
+    ///
+    /// ```text
+    /// -> e
+    /// <- ekem
+    /// ```
+    NN,
+
+    /// Static keys are transmitted during the handshake, so that both parties are authenticated
+    /// without knowing each other in advance:
+    ///
+    /// ```text
+    /// -> e
+    /// <- ekem, s
+    /// -> skem, s
+    /// <- skem
+    /// ```
+    XX,
+
+    /// The initiator knows the responder's static key in advance, and transmits its own:
+    ///
+    /// ```text
+    /// <- s
+    /// ...
+    /// -> skem, e, s
+    /// <- ekem, skem
+    /// ```
+    IK,
+}
+
+/// A token in a message pattern
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Token {
+    /// Send a new ephemeral encapsulation key
+    E,
+
+    /// Encapsulate to the peer's ephemeral key
+    Ekem,
+
+    /// Send the static encapsulation key
+    S,
+
+    /// Encapsulate to the peer's static key
+    Skem,
+}
+
+impl Pattern {
+    /// The name of the pattern, as used in protocol names
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::NN => "pqNN",
+            Self::XX => "pqXX",
+            Self::IK => "pqIK",
+        }
+    }
+
+    /// The message patterns, alternating between initiator and responder
+    pub(crate) fn messages(self) -> &'static [&'static [Token]] {
+        use Token::{Ekem, Skem, E, S};
+
+        match self {
+            Self::NN => &[&[E], &[Ekem]],
+            Self::XX => &[&[E], &[Ekem, S], &[Skem, S], &[Skem]],
+            Self::IK => &[&[Skem, E, S], &[Ekem, Skem]],
+        }
+    }
+
+    /// Whether the initiator knows the responder's static key before the handshake
+    pub(crate) fn responder_key_known(self) -> bool {
+        self == Self::IK
+    }
+
+    /// Whether the party in the given role needs a static key pair
+    pub(crate) fn needs_static(self, initiator: bool) -> bool {
+        let sender = usize::from(!initiator);
+        self.messages()
+            .iter()
+            .skip(sender)
+            .step_by(2)
+            .any(|tokens| tokens.contains(&Token::S))
+            || (!initiator && self.responder_key_known())
+    }
+}
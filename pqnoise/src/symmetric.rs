@@ -0,0 +1,195 @@
+use alloc::vec::Vec;
+use blake2::Blake2s256;
+use core::marker::PhantomData;
+use hmac::{Mac, SimpleHmac};
+use sha2::{
+    digest::{consts::U32, core_api::BlockSizeUser, OutputSizeUser},
+    Digest, Sha256,
+};
+
+use crate::cipher::{CipherState, KEY_LEN};
+use crate::Error;
+
+/// The output length of the hash function
+pub(crate) const HASH_LEN: usize = 32;
+
+type Hash = [u8; HASH_LEN];
+
+/// A hash function for the handshake.  Only hash functions with 32-byte outputs are supported,
+/// so that every choice of hash function yields the same key and handshake hash lengths.
+pub trait HashFunction: Digest + BlockSizeUser + OutputSizeUser<OutputSize = U32> + Clone {
+    /// The name of the hash function, as used in protocol names
+    const NAME: &'static str;
+}
+
+impl HashFunction for Sha256 {
+    const NAME: &'static str = "SHA256";
+}
+
+impl HashFunction for Blake2s256 {
+    const NAME: &'static str = "BLAKE2s";
+}
+
+/// A `SymmetricState` holds the chaining key `ck` and handshake hash `h` of a handshake, and the
+/// cipher state keyed from the chaining key
+pub(crate) struct SymmetricState<H: HashFunction> {
+    cipher: CipherState,
+    ck: Hash,
+    h: Hash,
+    hash: PhantomData<H>,
+}
+
+impl<H: HashFunction> SymmetricState<H> {
+    /// Initialize the state from the protocol name
+    pub fn new(protocol_name: &[u8]) -> Self {
+        let h = if protocol_name.len() <= HASH_LEN {
+            let mut h = Hash::default();
+            h[..protocol_name.len()].copy_from_slice(protocol_name);
+            h
+        } else {
+            H::digest(protocol_name).into()
+        };
+
+        Self {
+            cipher: CipherState::default(),
+            ck: h,
+            h,
+            hash: PhantomData,
+        }
+    }
+
+    pub fn mix_key(&mut self, input_key_material: &[u8]) {
+        let [ck, k] = hkdf::<H>(&self.ck, input_key_material);
+        self.ck = ck;
+        self.cipher = CipherState::new(k);
+    }
+
+    pub fn mix_hash(&mut self, data: &[u8]) {
+        self.h = H::new()
+            .chain_update(self.h)
+            .chain_update(data)
+            .finalize()
+            .into();
+    }
+
+    pub fn handshake_hash(&self) -> Hash {
+        self.h
+    }
+
+    pub fn encrypt_and_hash(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let ciphertext = self.cipher.encrypt_with_ad(&self.h, plaintext)?;
+        self.mix_hash(&ciphertext);
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt_and_hash(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        let plaintext = self.cipher.decrypt_with_ad(&self.h, ciphertext)?;
+        self.mix_hash(ciphertext);
+        Ok(plaintext)
+    }
+
+    /// The length of the encryption of a plaintext of length `len` in the current state
+    pub fn ciphertext_len(&self, len: usize) -> usize {
+        self.cipher.ciphertext_len(len)
+    }
+
+    /// Derive the cipher states for sending from the initiator and sending from the responder
+    pub fn split(&self) -> (CipherState, CipherState) {
+        let [k1, k2] = hkdf::<H>(&self.ck, &[]);
+        (CipherState::new(k1), CipherState::new(k2))
+    }
+}
+
+// The `HKDF` function of the Noise specification, with two outputs
+fn hkdf<H: HashFunction>(chaining_key: &Hash, input_key_material: &[u8]) -> [[u8; KEY_LEN]; 2] {
+    let temp_key = hmac::<H>(chaining_key, &[input_key_material]);
+    let output1 = hmac::<H>(&temp_key, &[&[0x01]]);
+    let output2 = hmac::<H>(&temp_key, &[&output1, &[0x02]]);
+    [output1, output2]
+}
+
+// HMAC with the block size of `H`, as the Noise specification requires.  `SimpleHmac` supports
+// hash functions with lazy buffering, such as BLAKE2s.
+fn hmac<H: HashFunction>(key: &Hash, inputs: &[&[u8]]) -> Hash {
+    let mut mac = SimpleHmac::<H>::new_from_slice(key).expect("HMAC accepts any key length");
+    for input in inputs {
+        mac.update(input);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use hex_literal::hex;
+
+    fn initialize_test<H: HashFunction>() {
+        // Short names are padded with zeros, long ones are hashed
+        let state = SymmetricState::<H>::new(b"Noise_pqNN");
+        assert_eq!(&state.handshake_hash()[..10], b"Noise_pqNN");
+        assert_eq!(state.handshake_hash()[10..], [0; 22]);
+        assert_eq!(state.ck, state.h);
+
+        let name = b"Noise_pqXX_MLKEM768_ChaChaPoly_SHA256";
+        let state = SymmetricState::<H>::new(name);
+        assert_eq!(state.handshake_hash(), <Hash>::from(H::digest(name)));
+    }
+
+    #[test]
+    fn initialize() {
+        initialize_test::<Sha256>();
+        initialize_test::<Blake2s256>();
+    }
+
+    #[test]
+    fn hmac_known_answer() {
+        // RFC 4231, test case 2, with the input split across calls
+        let mut key = Hash::default();
+        key[..4].copy_from_slice(b"Jefe");
+        let data: &[u8] = b"what do ya want for nothing?";
+        assert_eq!(
+            hmac::<Sha256>(&key, &[&data[..10], &data[10..]]),
+            hex!("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+    }
+
+    fn round_trip_test<H: HashFunction>() {
+        let mut a = SymmetricState::<H>::new(b"test");
+        let mut b = SymmetricState::<H>::new(b"test");
+
+        // Before a key is mixed in, data is only hashed
+        let ct = a.encrypt_and_hash(b"hello").unwrap();
+        assert_eq!(ct, b"hello");
+        assert_eq!(b.decrypt_and_hash(&ct).unwrap(), b"hello");
+
+        a.mix_key(b"shared secret");
+        b.mix_key(b"shared secret");
+        let ct = a.encrypt_and_hash(b"hello").unwrap();
+        assert_eq!(ct.len(), 5 + 16);
+        assert_eq!(b.decrypt_and_hash(&ct).unwrap(), b"hello");
+        assert_eq!(a.handshake_hash(), b.handshake_hash());
+
+        // Diverging hashes are detected
+        a.mix_hash(b"a");
+        b.mix_hash(b"b");
+        let ct = a.encrypt_and_hash(b"hello").unwrap();
+        assert_eq!(b.decrypt_and_hash(&ct), Err(Error::Decrypt));
+    }
+
+    #[test]
+    fn round_trip() {
+        round_trip_test::<Sha256>();
+        round_trip_test::<Blake2s256>();
+    }
+
+    #[test]
+    fn hash_functions_differ() {
+        // The same inputs yield different keys under different hash functions
+        let mut a = SymmetricState::<Sha256>::new(b"test");
+        let mut b = SymmetricState::<Blake2s256>::new(b"test");
+        a.mix_key(b"shared secret");
+        b.mix_key(b"shared secret");
+        let ct = a.encrypt_and_hash(b"hello").unwrap();
+        assert_eq!(b.decrypt_and_hash(&ct), Err(Error::Decrypt));
+    }
+}
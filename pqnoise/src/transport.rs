@@ -0,0 +1,70 @@
+use alloc::vec::Vec;
+
+use crate::cipher::CipherState;
+use crate::symmetric::HASH_LEN;
+use crate::{EncapsulationKey, Error, MAX_MESSAGE_LEN};
+
+/// A `TransportState` protects application messages after a handshake has finished, with one
+/// cipher state for each direction
+pub struct TransportState {
+    send: CipherState,
+    recv: CipherState,
+    handshake_hash: [u8; HASH_LEN],
+    remote_static: Option<EncapsulationKey>,
+}
+
+impl TransportState {
+    pub(crate) fn new(
+        send: CipherState,
+        recv: CipherState,
+        handshake_hash: [u8; HASH_LEN],
+        remote_static: Option<EncapsulationKey>,
+    ) -> Self {
+        Self {
+            send,
+            recv,
+            handshake_hash,
+            remote_static,
+        }
+    }
+
+    /// The handshake hash `h` of the finished handshake
+    #[must_use]
+    pub fn handshake_hash(&self) -> [u8; HASH_LEN] {
+        self.handshake_hash
+    }
+
+    /// The peer's static encapsulation key, if the pattern transmitted or required one
+    #[must_use]
+    pub fn remote_static(&self) -> Option<&EncapsulationKey> {
+        self.remote_static.as_ref()
+    }
+
+    /// Encrypt an application message to the peer.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::MessageTooLong`] if the encrypted message would exceed the maximum
+    /// message length, and [`Error::NonceExhausted`] if the sending key has been used for
+    /// `2^64 - 1` messages.
+    pub fn write_message(&mut self, payload: &[u8]) -> Result<Vec<u8>, Error> {
+        if self.send.ciphertext_len(payload.len()) > MAX_MESSAGE_LEN {
+            return Err(Error::MessageTooLong);
+        }
+        self.send.encrypt_with_ad(&[], payload)
+    }
+
+    /// Decrypt an application message from the peer.  Messages must be read in the order in
+    /// which they were written.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Decrypt`] if the message does not authenticate, for example because it
+    /// was modified, replayed, or reordered.
+    pub fn read_message(&mut self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        if message.len() > MAX_MESSAGE_LEN {
+            return Err(Error::MessageTooLong);
+        }
+        self.recv.decrypt_with_ad(&[], message)
+    }
+}
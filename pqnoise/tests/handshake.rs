@@ -0,0 +1,235 @@
+//! A deterministic driver for complete handshakes.  Static keys are derived from fixed seeds with
+//! `generate_deterministic`, and each party draws the seeds for its ephemeral keys and
+//! encapsulations from its own seeded RNG, so every run produces the same messages.
+
+use pqnoise::{
+    Blake2s256, Error, HandshakeState, HashFunction, Keypair, Pattern, Sha256, TransportState,
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+const PATTERNS: [Pattern; 3] = [Pattern::NN, Pattern::XX, Pattern::IK];
+
+fn static_key(seed: u8) -> Keypair {
+    Keypair::from_seeds(&[seed; 32].into(), &[seed.wrapping_add(1); 32].into())
+}
+
+struct Setup {
+    pattern: Pattern,
+    initiator_prologue: &'static [u8],
+    responder_prologue: &'static [u8],
+    initiator_key: Keypair,
+    responder_key: Keypair,
+    known_responder_key: Keypair,
+    seed: u64,
+}
+
+impl Setup {
+    fn new(pattern: Pattern) -> Self {
+        Self {
+            pattern,
+            initiator_prologue: b"prologue",
+            responder_prologue: b"prologue",
+            initiator_key: static_key(1),
+            responder_key: static_key(2),
+            known_responder_key: static_key(2),
+            seed: 0,
+        }
+    }
+}
+
+struct Transcript {
+    messages: Vec<Vec<u8>>,
+    payloads: Vec<Vec<u8>>,
+    initiator: TransportState,
+    responder: TransportState,
+}
+
+// Run a handshake with the hash function `H`, applying `tamper` to each message before it is
+// delivered
+fn run<H: HashFunction>(
+    setup: &Setup,
+    mut tamper: impl FnMut(usize, &mut Vec<u8>),
+) -> Result<Transcript, Error> {
+    let mut initiator_rng = StdRng::seed_from_u64(setup.seed);
+    let mut responder_rng = StdRng::seed_from_u64(setup.seed + 1);
+
+    let mut initiator = HandshakeState::<H>::initiator(
+        setup.pattern,
+        setup.initiator_prologue,
+        Some(setup.initiator_key.clone()),
+        (setup.pattern == Pattern::IK).then(|| setup.known_responder_key.ek.clone()),
+    )?;
+    let mut responder = HandshakeState::<H>::responder(
+        setup.pattern,
+        setup.responder_prologue,
+        Some(setup.responder_key.clone()),
+    )?;
+
+    let mut messages = Vec::new();
+    let mut payloads = Vec::new();
+    while !(initiator.is_finished() && responder.is_finished()) {
+        let i = messages.len();
+        let (writer, reader, rng) = if initiator.is_my_turn() {
+            (&mut initiator, &mut responder, &mut initiator_rng)
+        } else {
+            (&mut responder, &mut initiator, &mut responder_rng)
+        };
+
+        let payload = format!("payload {i}");
+        let mut message = writer.write_message(payload.as_bytes(), rng)?;
+        tamper(i, &mut message);
+        payloads.push(reader.read_message(&message)?);
+        messages.push(message);
+    }
+
+    assert_eq!(initiator.handshake_hash(), responder.handshake_hash());
+    Ok(Transcript {
+        messages,
+        payloads,
+        initiator: initiator.into_transport()?,
+        responder: responder.into_transport()?,
+    })
+}
+
+fn handshake_test<H: HashFunction>() {
+    for pattern in PATTERNS {
+        let setup = Setup::new(pattern);
+        let mut transcript = run::<H>(&setup, |_, _| {}).unwrap();
+        let expected_messages = if pattern == Pattern::XX { 4 } else { 2 };
+        assert_eq!(transcript.messages.len(), expected_messages);
+        for (i, payload) in transcript.payloads.iter().enumerate() {
+            assert_eq!(payload, format!("payload {i}").as_bytes());
+        }
+
+        // Static keys are learned by the peers that need them
+        let (initiator, responder) = (&mut transcript.initiator, &mut transcript.responder);
+        if pattern == Pattern::NN {
+            assert!(initiator.remote_static().is_none());
+            assert!(responder.remote_static().is_none());
+        } else {
+            assert_eq!(initiator.remote_static(), Some(&setup.responder_key.ek));
+            assert_eq!(responder.remote_static(), Some(&setup.initiator_key.ek));
+        }
+
+        // Transport messages flow in both directions
+        for i in 0..3 {
+            let message = initiator.write_message(&[i; 100]).unwrap();
+            assert_eq!(responder.read_message(&message).unwrap(), [i; 100]);
+            let message = responder.write_message(&[i; 10]).unwrap();
+            assert_eq!(initiator.read_message(&message).unwrap(), [i; 10]);
+        }
+    }
+}
+
+#[test]
+fn handshake() {
+    handshake_test::<Sha256>();
+    handshake_test::<Blake2s256>();
+}
+
+#[test]
+fn hash_functions() {
+    // The hash function is part of the protocol name, so it changes every message after the first
+    let setup = Setup::new(Pattern::XX);
+    let sha256 = run::<Sha256>(&setup, |_, _| {}).unwrap();
+    let blake2s = run::<Blake2s256>(&setup, |_, _| {}).unwrap();
+    assert_eq!(sha256.messages[0], blake2s.messages[0]);
+    assert_ne!(sha256.messages[1], blake2s.messages[1]);
+    assert_ne!(
+        sha256.initiator.handshake_hash(),
+        blake2s.initiator.handshake_hash()
+    );
+
+    // Parties with different hash functions cannot complete a handshake
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut initiator = HandshakeState::<Sha256>::initiator(Pattern::NN, b"", None, None).unwrap();
+    let mut responder = HandshakeState::<Blake2s256>::responder(Pattern::NN, b"", None).unwrap();
+    let message = initiator.write_message(b"", &mut rng).unwrap();
+    responder.read_message(&message).unwrap();
+    let message = responder.write_message(b"", &mut rng).unwrap();
+    assert_eq!(initiator.read_message(&message), Err(Error::Decrypt));
+}
+
+#[test]
+fn deterministic() {
+    for pattern in PATTERNS {
+        let mut setup = Setup::new(pattern);
+        let first = run::<Sha256>(&setup, |_, _| {}).unwrap();
+        let second = run::<Sha256>(&setup, |_, _| {}).unwrap();
+        assert_eq!(first.messages, second.messages);
+        assert_eq!(
+            first.initiator.handshake_hash(),
+            second.initiator.handshake_hash()
+        );
+
+        setup.seed = 2;
+        let third = run::<Sha256>(&setup, |_, _| {}).unwrap();
+        assert_ne!(first.messages, third.messages);
+        assert_ne!(
+            first.initiator.handshake_hash(),
+            third.initiator.handshake_hash()
+        );
+    }
+}
+
+#[test]
+fn tampered_messages() {
+    for pattern in PATTERNS {
+        let setup = Setup::new(pattern);
+        let messages = run::<Sha256>(&setup, |_, _| {}).unwrap().messages;
+
+        // Modifying any byte of any message causes the handshake to fail, possibly only when a
+        // later message is read
+        for (i, message) in messages.iter().enumerate() {
+            for offset in [0, message.len() / 2, message.len() - 1] {
+                let result = run::<Sha256>(&setup, |j, message| {
+                    if i == j {
+                        message[offset] ^= 0x01;
+                    }
+                });
+                assert_eq!(
+                    result.err(),
+                    Some(Error::Decrypt),
+                    "{pattern:?} {i} {offset}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn mismatched_setup() {
+    for pattern in PATTERNS {
+        let mut setup = Setup::new(pattern);
+        setup.responder_prologue = b"other prologue";
+        assert_eq!(run::<Sha256>(&setup, |_, _| {}).err(), Some(Error::Decrypt));
+    }
+
+    // The initiator expects a different responder
+    let mut setup = Setup::new(Pattern::IK);
+    setup.known_responder_key = static_key(3);
+    assert_eq!(run::<Sha256>(&setup, |_, _| {}).err(), Some(Error::Decrypt));
+}
+
+#[test]
+fn replayed_transport_messages() {
+    let mut transcript = run::<Sha256>(&Setup::new(Pattern::XX), |_, _| {}).unwrap();
+
+    let first = transcript.initiator.write_message(b"first").unwrap();
+    let second = transcript.initiator.write_message(b"second").unwrap();
+
+    // Messages must be read in order, and only once
+    assert_eq!(
+        transcript.responder.read_message(&second),
+        Err(Error::Decrypt)
+    );
+    assert_eq!(transcript.responder.read_message(&first).unwrap(), b"first");
+    assert_eq!(
+        transcript.responder.read_message(&first),
+        Err(Error::Decrypt)
+    );
+    assert_eq!(
+        transcript.responder.read_message(&second).unwrap(),
+        b"second"
+    );
+}